@@ -1,53 +1,264 @@
-use crate::cli::{Cli, Command, RunArgs};
-use crate::command::run_command_and_stream;
+use crate::ci::CiContext;
+use crate::cleanup::run_cleanup;
+use crate::cli::{
+    CiContextMode, Cli, Command, GroupAction, HistoryAction, MaintenancePolicy, NestedPolicy,
+    RunArgs,
+};
+use crate::clipboard::copy_to_clipboard;
+use crate::command::{create_run_tmpdir, run_command_and_stream, RunControl, CANCELLED_EXIT_CODE};
+use crate::crash::signal_message;
+use crate::diagnostics::sarif_log;
+use crate::digest::{run_digest, run_slo_report};
 use crate::error::AppError;
+use crate::files;
+use crate::fixtures::write_fixtures;
+use crate::git::GitContext;
+use crate::groups::{end_group, start_group, GroupRun, GroupStore};
+use crate::health::run_health_checks;
+use crate::history::{
+    export_history, job_name, outcome_strip, Flakiness, HistoryEntry, HistoryStore,
+};
+#[cfg(feature = "shell")]
+use crate::jobs::JobTable;
+use crate::keys::{PauseKeyListener, PAUSE_KEY};
+use crate::links::Linker;
+use crate::maintenance::{active_window, load_calendar};
 use crate::message::StreamMessage;
-use crate::webhook::{run_webhook_sender, send_message};
-use clap::Parser;
-use dirs::home_dir;
-use reqwest::Client;
-use rustyline::error::ReadlineError;
-use rustyline::DefaultEditor;
-use std::io::ErrorKind;
+use crate::notify::{notify_desktop, ring_bell};
+#[cfg(feature = "shell")]
+use crate::prompt::{Prompt, PromptInput};
+use crate::render::{renderer_for, Renderer};
+use crate::report::{unix_millis, CapturedEnvironment, DeliveryLog, RunReport};
+use crate::runs::{
+    forward_run, kill_run, new_run_id, notify_interrupted_runs, print_logs, print_status,
+    run_detached_worker, spawn_detached, unix_now, wait_for_child_pid,
+};
+use crate::script::script_title;
+use crate::session::SessionEvent;
+use crate::severity::score;
+use crate::telemetry::BatchStats;
+use crate::transport::{transport_for, Transport};
+use crate::units::Units;
+#[cfg(feature = "shell")]
+use crate::variables::SessionVariables;
+use crate::watermark::Watermark;
+use crate::webhook::{run_coalescing_sender, run_webhook_sender, send_message, send_warning};
+#[cfg(any(feature = "native-tls", feature = "minimal-tls"))]
+use reqwest::Identity;
+use reqwest::{Client, ClientBuilder};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::io::{ErrorKind, Write};
+use std::mem::ManuallyDrop;
+use std::os::fd::FromRawFd;
+use std::os::unix::process::ExitStatusExt;
 use std::process::ExitStatus;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
 
 const CHANNEL_BUFFER_SIZE: usize = 100;
-const HISTORY_FILE: &str = ".shell_hook_history";
 
 /// Shared application context to avoid passing many arguments.
 pub struct AppContext {
     pub cli: Arc<Cli>,
+    /// Identifies this run in history entries, reports, watermarks, the result line
+    /// and shard pinning: the detached run's ID, or one minted for this invocation.
+    pub run_id: String,
     pub client: Client,
+    pub renderer: Box<dyn Renderer>,
+    pub transport: Box<dyn Transport>,
+    /// Delivery attempts made through `transport`, drained into `run --report`.
+    pub deliveries: DeliveryLog,
+    pub linker: Option<Linker>,
+    /// Measurements of each output batch, reported with `-v`.
+    pub batches: BatchStats,
+    /// The CI job this run is part of, with `--ci-context auto`.
+    pub ci: Option<CiContext>,
+    /// Stamps every payload with its origin, with `--watermark`.
+    pub watermark: Option<Watermark>,
+    /// Receives structured events when the context is driven by a [`Session`](crate::session::Session).
+    pub events: Option<mpsc::UnboundedSender<SessionEvent>>,
+}
+
+impl AppContext {
+    /// Validates the options and builds the renderer and transport they select.
+    pub fn new(cli: Arc<Cli>) -> Result<Self, AppError> {
+        if cli.webhook_url.is_none() && !cli.dry_run && cli.notifies_webhook() {
+            return Err(AppError::MissingWebhookUrl);
+        }
+
+        let client = with_client_identity(Client::builder(), &cli)?.build()?;
+        let deliveries = DeliveryLog::default();
+        let run_id = cli.run_id.clone().unwrap_or_else(new_run_id);
+        Ok(Self {
+            renderer: renderer_for(&cli.format),
+            transport: transport_for(&cli, &client, &deliveries, &run_id),
+            deliveries,
+            linker: Linker::from_cli(&cli),
+            batches: BatchStats::default(),
+            ci: match cli.ci_context {
+                CiContextMode::Auto => CiContext::detect(),
+                CiContextMode::Off => None,
+            },
+            watermark: cli.watermark.then(|| Watermark::new(run_id.clone())),
+            run_id,
+            cli,
+            client,
+            events: None,
+        })
+    }
+
+    /// Prints a warning about shell_hook itself, such as a message it could not
+    /// send, and forwards it to the session listener and, with
+    /// `--forward-warnings`, to the webhook.
+    pub async fn warn(&self, message: String) {
+        eprintln!("[shell_hook] Warning: {}", message);
+        if self.cli.forward_warnings {
+            if let Err(e) = send_warning(self, &message).await {
+                // Not through `warn`, which would try to forward this one too.
+                eprintln!("[shell_hook] Warning: Failed to forward warning: {}", e);
+            }
+        }
+        self.emit(SessionEvent::Warning { message });
+    }
+
+    /// Forwards an event to the session listener, if there is one.
+    pub fn emit(&self, event: SessionEvent) {
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+}
+
+/// Adds the `--client-cert`/`--client-key` identity for mutual TLS, if given.
+#[cfg(any(feature = "native-tls", feature = "minimal-tls"))]
+fn with_client_identity(builder: ClientBuilder, cli: &Cli) -> Result<ClientBuilder, AppError> {
+    let (Some(cert_path), Some(key_path)) = (&cli.client_cert, &cli.client_key) else {
+        return Ok(builder);
+    };
+    let cert = std::fs::read(cert_path)?;
+    let key = std::fs::read(key_path)?;
+    #[cfg(feature = "native-tls")]
+    let identity = Identity::from_pkcs8_pem(&cert, &key);
+    #[cfg(not(feature = "native-tls"))]
+    let identity = Identity::from_pem(&[key, cert].concat());
+    let identity = identity.map_err(|e| AppError::InvalidClientCertificate(e.to_string()))?;
+    Ok(builder.identity(identity))
+}
+
+#[cfg(not(any(feature = "native-tls", feature = "minimal-tls")))]
+fn with_client_identity(builder: ClientBuilder, cli: &Cli) -> Result<ClientBuilder, AppError> {
+    match cli.client_cert {
+        Some(_) => Err(AppError::InvalidClientCertificate(
+            "this build has no TLS support".to_string(),
+        )),
+        None => Ok(builder),
+    }
 }
 
 /// The main application logic.
 pub async fn run() -> Result<i32, AppError> {
-    let cli = Cli::try_parse()?;
+    let cli = Cli::try_parse_args(std::env::args_os())?;
     run_app(cli).await
 }
 
 pub async fn run_from(args: Vec<&str>) -> Result<i32, AppError> {
-    let cli = Cli::try_parse_from(args)?;
+    let cli = Cli::try_parse_args(args)?;
     run_app(cli).await
 }
 
-pub async fn run_app(cli: Cli) -> Result<i32, AppError> {
-    let cli = Arc::new(cli);
-    // Validate arguments
-    if cli.webhook_url.is_none() && !cli.dry_run {
-        return Err(AppError::MissingWebhookUrl);
+pub async fn run_app(mut cli: Cli) -> Result<i32, AppError> {
+    files::set_umask(cli.umask);
+    if let Command::RunScript(run_args) = &cli.command {
+        let mut run_args = run_args.clone();
+        run_args.run_as_script = true;
+        if cli.title.is_none() {
+            cli.title = Some(script_title(&run_args.command[0]));
+        }
+        cli.command = Command::Run(run_args);
     }
-
-    let context = Arc::new(AppContext {
-        cli: cli.clone(),
-        client: Client::new(),
-    });
+    let cli = Arc::new(cli);
+    let context = || AppContext::new(cli.clone()).map(Arc::new);
 
     match &cli.command {
-        Command::Run(run_args) => run_single_command(&context, run_args).await,
-        Command::Shell => run_shell_session(&context).await,
+        Command::Run(run_args) if run_args.handoff => {
+            context()?;
+            let id = spawn_detached(&cli, run_args)?;
+            println!("{}", wait_for_child_pid(&cli, &id).await?);
+            eprintln!(
+                "[shell_hook] Handed off as run {0}. Use `shell_hook status {0}` to check on it.",
+                id
+            );
+            Ok(0)
+        }
+        Command::Run(run_args) if run_args.detach => {
+            // Validate before detaching, so mistakes are reported to the caller.
+            context()?;
+            let id = spawn_detached(&cli, run_args)?;
+            println!("{}", id);
+            eprintln!(
+                "[shell_hook] Detached as run {0}. Use `shell_hook status {0}` or `shell_hook logs {0}`.",
+                id
+            );
+            Ok(0)
+        }
+        Command::Run(run_args) => match &cli.run_id {
+            Some(id) => run_detached_worker(cli.clone(), run_args, id).await,
+            None => run_single_command(&context()?, run_args).await,
+        },
+        Command::RunScript(_) => unreachable!("run-script is rewritten to run above"),
+        #[cfg(feature = "shell")]
+        Command::Shell => run_shell_session(&context()?).await,
+        Command::Status { id } => print_status(&cli, id.as_deref()),
+        Command::Logs { id } => print_logs(&cli, id),
+        Command::Kill { id, reason } => kill_run(&cli, id, reason.as_deref()).await,
+        Command::Fixtures { out } => {
+            for path in write_fixtures(out).await? {
+                println!("{}", path.display());
+            }
+            Ok(0)
+        }
+        Command::Cleanup { older_than } => run_cleanup(&cli, *older_than),
+        Command::Group { action } => match action {
+            GroupAction::Start { name } => start_group(&context()?, name).await,
+            GroupAction::End => {
+                let id = cli.group.as_deref().ok_or(AppError::MissingGroup)?;
+                end_group(&context()?, id).await
+            }
+        },
+        Command::History {
+            action: HistoryAction::Export { export_format, out },
+        } => {
+            export_history(&cli, *export_format, out.as_deref())?;
+            Ok(0)
+        }
+        Command::History {
+            action: HistoryAction::Forward { id },
+        } => forward_run(&context()?, id).await,
+        Command::Digest {
+            post,
+            markdown,
+            slo: true,
+            windows,
+            targets,
+            ..
+        } => run_slo_report(&cli, windows, targets, *post, markdown.as_deref()).await,
+        Command::Digest {
+            since,
+            post,
+            markdown,
+            ..
+        } => run_digest(&cli, *since, *post, markdown.as_deref()).await,
+        Command::Check { command, .. } if command.is_empty() => {
+            notify_interrupted_runs(&context()?).await?;
+            Ok(0)
+        }
+        Command::Check {
+            interval,
+            failures,
+            count,
+            command,
+        } => run_health_checks(&context()?, command, *interval, *failures, *count).await,
     }
 }
 
@@ -55,61 +266,369 @@ pub async fn run_single_command(
     context: &Arc<AppContext>,
     run_args: &RunArgs,
 ) -> Result<i32, AppError> {
-    // --- Setup communication channel and tasks ---
+    run_controlled_command(context, run_args, Arc::default()).await
+}
+
+/// Like [`run_single_command`], but lets the caller adjust the run while it is in flight.
+pub async fn run_controlled_command(
+    context: &Arc<AppContext>,
+    run_args: &RunArgs,
+    control: Arc<RunControl>,
+) -> Result<i32, AppError> {
+    if let Some(max) = run_args.splay {
+        let delay = splay_delay(max);
+        eprintln!(
+            "[shell_hook] Splaying start by {}",
+            context.cli.units.duration(delay)
+        );
+        tokio::time::sleep(delay).await;
+    }
+    if !wait_out_maintenance(context, run_args).await? {
+        return Ok(0);
+    }
+
+    let started_at_ms = unix_millis();
+    let environment = run_args
+        .capture_env
+        .then(|| CapturedEnvironment::capture(run_args));
+
+    if run_args.tmpdir {
+        control.set_tmpdir(create_run_tmpdir()?);
+    }
+    if run_args.emit_sarif.is_some() {
+        control.collect_diagnostics();
+    }
+    if let Some(pattern) = &run_args.expect_output {
+        control.expect_output(pattern.clone());
+    }
+
+    // --- Setup communication channel ---
     let (tx, rx) = mpsc::channel::<StreamMessage>(CHANNEL_BUFFER_SIZE);
-    let sender_task = tokio::spawn(run_webhook_sender(context.clone(), rx));
 
-    // --- Send initial message ---
-    let command_str = run_args.command.join(" ");
+    // --- Send initial message and start the sender ---
+    let command_str = run_args.command_line();
     let start_message = format_with_title(
         &context.cli,
-        &format!("🚀 Starting command: `{}`", command_str),
+        &with_ci_context(
+            context,
+            with_git_context(
+                &context.cli,
+                format!("🚀 Starting command: `{}`", command_str),
+            ),
+        ),
     );
-    println!("{}", start_message);
-    if let Err(e) = send_message(context, &start_message).await {
-        eprintln!("[shell_hook] Warning: Failed to send start message: {}", e);
+    echo_status(&context.cli, &start_message, false);
+    let sender_context = context.clone();
+    let sender_task = if let Some(window) = run_args.coalesce_under {
+        tokio::spawn(run_coalescing_sender(
+            sender_context,
+            rx,
+            start_message,
+            window,
+            control.clone(),
+        ))
+    } else if context.cli.warm_up {
+        // Connect and deliver the start message while the command spawns. Output
+        // queues behind it, so it still arrives first.
+        tokio::spawn(async move {
+            send_start_message(&sender_context, &start_message).await;
+            run_webhook_sender(sender_context, rx).await
+        })
+    } else {
+        send_start_message(context, &start_message).await;
+        tokio::spawn(run_webhook_sender(sender_context, rx))
+    };
+    context.emit(SessionEvent::Started {
+        command: command_str.clone(),
+    });
+
+    // --- Listen for the pause key while the command runs ---
+    let (toggle_tx, toggle_rx) = mpsc::unbounded_channel();
+    let pause_listener = match context
+        .cli
+        .pause_key
+        .then(|| PauseKeyListener::start(control.clone(), toggle_tx))
+        .transpose()
+    {
+        Ok(listener) => listener.flatten(),
+        Err(e) => {
+            context.warn(format!("Pause key unavailable: {}", e)).await;
+            None
+        }
+    };
+    if pause_listener.is_some() {
+        eprintln!(
+            "[shell_hook] Press '{}' to pause or resume webhook streaming.",
+            PAUSE_KEY as char
+        );
     }
+    let pause_notices = tokio::spawn(send_pause_notices(
+        context.clone(),
+        control.clone(),
+        toggle_rx,
+    ));
 
     // --- Run command and stream output ---
-    let status_result = run_command_and_stream(context.clone(), tx, run_args).await;
+    let status_result =
+        run_command_and_stream(context.clone(), tx, run_args, control.clone()).await;
+    drop(pause_listener);
+    let _ = pause_notices.await;
+    control.set_streaming_paused(false);
 
     // --- Wait for sender to finish sending buffered messages ---
     let _ = sender_task.await?;
+    if context.cli.verbose > 0 {
+        if let Some(summary) = context
+            .batches
+            .summary(context.cli.units, context.cli.buffer_size)
+        {
+            eprintln!("{}", summary);
+        }
+    }
 
     // --- Handle command result and send final message ---
-    handle_command_result(context, status_result, run_args).await
+    let exit_code = handle_command_result(context, status_result, run_args, &control).await?;
+
+    if let Some(group) = &context.cli.group {
+        let run = GroupRun {
+            command: command_str.clone(),
+            exit_code,
+            started_at: started_at_ms / 1000,
+            finished_at: unix_now(),
+        };
+        if let Err(e) = GroupStore::from_cli(&context.cli).record_run(group, &run) {
+            context
+                .warn(format!("Failed to record run in group: {}", e))
+                .await;
+        }
+    }
+
+    if context.cli.history {
+        let entry = HistoryEntry {
+            run_id: context.run_id.clone(),
+            job: job_name(&context.cli, run_args),
+            command: command_str.clone(),
+            exit_code,
+            started_at_ms,
+            finished_at_ms: unix_millis(),
+            deliveries: context.deliveries.len(),
+            failed_deliveries: context.deliveries.failures(),
+        };
+        if let Err(e) = HistoryStore::from_cli(&context.cli).append(&entry) {
+            context
+                .warn(format!("Failed to record run history: {}", e))
+                .await;
+        }
+    }
+
+    if let Some(path) = &run_args.emit_sarif {
+        let tool = run_args.command.first().map_or("shell_hook", |program| {
+            program.split_whitespace().next().unwrap_or(program)
+        });
+        let root = std::env::current_dir().ok();
+        let sarif = sarif_log(tool, &control.take_diagnostics(), root.as_deref());
+        let written = serde_json::to_vec_pretty(&sarif)
+            .map_err(std::io::Error::from)
+            .and_then(|json| files::write(path, json));
+        if let Err(e) = written {
+            context.warn(format!("Failed to write SARIF: {}", e)).await;
+        }
+    }
+
+    if let Some(path) = &run_args.report {
+        let report = RunReport {
+            run_id: context.run_id.clone(),
+            command: command_str,
+            exit_code,
+            started_at_ms,
+            finished_at_ms: unix_millis(),
+            deliveries: context.deliveries.take(),
+            line_lag: context.batches.lag(),
+            environment,
+        };
+        if let Err(e) = report.write(path) {
+            context.warn(format!("Failed to write report: {}", e)).await;
+        }
+    }
+
+    if run_args.result_line {
+        let line = format!(
+            "SHELL_HOOK_RESULT exit={} duration={} run_id={}",
+            exit_code,
+            Units::Raw.duration(Duration::from_millis(unix_millis() - started_at_ms)),
+            context.run_id
+        );
+        if let Err(e) = write_result_line(run_args.result_fd, &line) {
+            context
+                .warn(format!("Failed to write result line: {}", e))
+                .await;
+        }
+    }
+    Ok(exit_code)
+}
+
+/// Writes `line` to descriptor `fd` (stdout by default) without taking ownership of it.
+fn write_result_line(fd: Option<i32>, line: &str) -> std::io::Result<()> {
+    let Some(fd) = fd else {
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{}", line)?;
+        return stdout.flush();
+    };
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // The descriptor stays open for whoever passed it in.
+    let mut file = ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) });
+    writeln!(file, "{}", line)
+}
+
+async fn send_start_message(context: &Arc<AppContext>, message: &str) {
+    if let Err(e) = send_message(context, message).await {
+        context
+            .warn(format!("Failed to send start message: {}", e))
+            .await;
+    }
+}
+
+/// Posts a notice to the webhook each time streaming is paused or resumed with `--pause-key`.
+async fn send_pause_notices(
+    context: Arc<AppContext>,
+    control: Arc<RunControl>,
+    mut toggles: mpsc::UnboundedReceiver<bool>,
+) {
+    while let Some(paused) = toggles.recv().await {
+        let notice = if paused {
+            "⏸️ Streaming paused".to_string()
+        } else {
+            format!(
+                "▶️ Streaming resumed ({} not sent)",
+                context
+                    .cli
+                    .units
+                    .count(control.take_withheld_lines(), "line")
+            )
+        };
+        let notice = format_with_title(&context.cli, &notice);
+        echo_status(&context.cli, &notice, false);
+        if let Err(e) = send_message(&context, &notice).await {
+            context
+                .warn(format!("Failed to send pause notice: {}", e))
+                .await;
+        }
+    }
+}
+
+/// Picks a random delay between zero and `max`.
+pub fn splay_delay(max: Duration) -> Duration {
+    let mut bytes = [0u8; 8];
+    if SystemRandom::new().fill(&mut bytes).is_err() {
+        return Duration::ZERO;
+    }
+    max.mul_f64(u64::from_le_bytes(bytes) as f64 / u64::MAX as f64)
+}
+
+/// Applies `--during-maintenance` if the run would start inside a maintenance window.
+///
+/// Returns `false` if the run should be skipped.
+async fn wait_out_maintenance(
+    context: &Arc<AppContext>,
+    run_args: &RunArgs,
+) -> Result<bool, AppError> {
+    let Some(path) = &run_args.maintenance_calendar else {
+        return Ok(true);
+    };
+    let windows = load_calendar(path)?;
+    let command_str = run_args.command_line();
+
+    while let Some(window) = active_window(&windows, unix_now()) {
+        let notice = match run_args.during_maintenance {
+            MaintenancePolicy::Skip => {
+                format!(
+                    "⏸️ Skipped `{}` due to maintenance window '{}'.",
+                    command_str, window.name
+                )
+            }
+            MaintenancePolicy::Defer => format!(
+                "⏸️ Deferring `{}` until maintenance window '{}' ends.",
+                command_str, window.name
+            ),
+        };
+        let notice = format_with_title(&context.cli, &notice);
+        echo_status(&context.cli, &notice, false);
+        if let Err(e) = send_message(context, &notice).await {
+            context
+                .warn(format!("Failed to send maintenance notice: {}", e))
+                .await;
+        }
+        if run_args.during_maintenance == MaintenancePolicy::Skip {
+            return Ok(false);
+        }
+        let remaining = window.end.saturating_sub(unix_now());
+        tokio::time::sleep(Duration::from_secs(remaining)).await;
+    }
+    Ok(true)
 }
 
 /// Processes a single line of input from the shell session.
-pub async fn process_shell_command(context: &Arc<AppContext>, line: &str) -> Result<i32, AppError> {
-    let run_args = crate::cli::RunArgs {
+#[cfg(feature = "shell")]
+pub async fn process_shell_command(
+    context: &Arc<AppContext>,
+    line: &str,
+    variables: &SessionVariables,
+) -> Result<i32, AppError> {
+    let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    if word == ":note" {
+        return post_note(context, rest.trim()).await;
+    }
+
+    let run_args = RunArgs {
         command: vec![line.to_string()],
-        on_success: None,
-        on_failure: None,
-        quiet: false,
+        env: variables.env(),
+        ..Default::default()
     };
 
     run_single_command(context, &run_args).await
 }
 
-pub async fn run_shell_session(context: &Arc<AppContext>) -> Result<i32, AppError> {
-    println!("Starting interactive shell session. Type 'exit' to quit.");
-    let mut rl = DefaultEditor::new()?;
+/// Posts an operator-written annotation to the webhook without running anything.
+#[cfg(feature = "shell")]
+async fn post_note(context: &Arc<AppContext>, note: &str) -> Result<i32, AppError> {
+    if note.is_empty() {
+        eprintln!("[shell_hook] Usage: :note <text>");
+        return Ok(1);
+    }
 
-    let history_path = home_dir().map(|p| p.join(HISTORY_FILE));
-    if let Some(ref path) = history_path {
-        if let Err(e) = rl.load_history(path) {
-            eprintln!("[shell_hook] Warning: Could not load history file: {}", e);
-        }
+    let message = format_with_title(&context.cli, &format!("📝 Note: {}", note));
+    echo_status(&context.cli, &message, false);
+    if let Err(e) = send_message(context, &message).await {
+        context.warn(format!("Failed to send note: {}", e)).await;
     }
+    Ok(0)
+}
+
+#[cfg(feature = "shell")]
+pub async fn run_shell_session(context: &Arc<AppContext>) -> Result<i32, AppError> {
+    println!(
+        "Starting interactive shell session. Type 'exit' to quit, ':note <text>' to post a note, \
+         '<command> &' to run in the background, ':jobs' and ':attach <id>' to manage jobs, \
+         ':set NAME=value' (or ':secret') to set a variable."
+    );
+    let mut prompt = Prompt::new()?;
+    let mut jobs = JobTable::default();
+    let mut variables = SessionVariables::default();
 
     loop {
-        let readline = rl.readline(">> ");
-        match readline {
-            Ok(line) => {
-                if let Err(e) = rl.add_history_entry(line.as_str()) {
-                    eprintln!("[shell_hook] Warning: Could not add to history: {}", e);
-                }
+        // Read on a blocking thread so background jobs keep streaming while
+        // the prompt waits, even on a current-thread runtime.
+        let (returned, input) = tokio::task::spawn_blocking(move || {
+            let input = prompt.read_line(">> ");
+            (prompt, input)
+        })
+        .await?;
+        prompt = returned;
+
+        match input {
+            Ok(PromptInput::Line(line)) => {
                 let line = line.trim();
                 if line.is_empty() {
                     continue;
@@ -117,28 +636,34 @@ pub async fn run_shell_session(context: &Arc<AppContext>) -> Result<i32, AppErro
                 if line == "exit" {
                     break;
                 }
+                if variables.handle_line(context, line).await {
+                    continue;
+                }
+                if jobs.handle_line(context, line, &variables).await {
+                    continue;
+                }
 
-                if let Err(e) = process_shell_command(context, line).await {
+                if let Err(e) = process_shell_command(context, line, &variables).await {
                     eprintln!("[shell_hook] Error executing command: {}", e);
                 }
             }
-            Err(ReadlineError::Interrupted) => {
+            Ok(PromptInput::Interrupted) => {
                 println!("(To exit, press Ctrl-D or type \"exit\")");
             }
-            Err(ReadlineError::Eof) => {
+            Ok(PromptInput::Eof) => {
                 break;
             }
             Err(err) => {
-                eprintln!("[shell_hook] Readline error: {}", err);
+                eprintln!("[shell_hook] {}", err);
                 break;
             }
         }
     }
 
-    if let Some(ref path) = history_path {
-        if let Err(e) = rl.save_history(path) {
-            eprintln!("[shell_hook] Warning: Could not save history file: {}", e);
-        }
+    prompt.save_history();
+    if !jobs.is_empty() {
+        println!("Waiting for background jobs to finish...");
+        jobs.wait_all().await;
     }
 
     Ok(0)
@@ -149,18 +674,22 @@ pub async fn handle_command_result(
     context: &Arc<AppContext>,
     status_result: std::io::Result<ExitStatus>,
     run_args: &RunArgs,
+    control: &RunControl,
 ) -> Result<i32, AppError> {
-    match status_result {
+    let (base_message, is_error, exit_code, scored_code) = match status_result {
         Ok(status) => {
             let exit_code = status.code().unwrap_or(1);
             let (base_message, is_error) = match status.code() {
-                Some(0) => (
-                    run_args
-                        .on_success
-                        .clone()
-                        .unwrap_or_else(|| "✅ Command finished successfully.".to_string()),
-                    false,
-                ),
+                Some(0) => match unmet_expectation(&context.cli, run_args, control) {
+                    Some(warning) => (warning, true),
+                    None => (
+                        run_args
+                            .on_success
+                            .clone()
+                            .unwrap_or_else(|| "✅ Command finished successfully.".to_string()),
+                        false,
+                    ),
+                },
                 Some(code) => (
                     run_args
                         .on_failure
@@ -168,42 +697,231 @@ pub async fn handle_command_result(
                         .unwrap_or_else(|| format!("❌ Command failed with exit code {}.", code)),
                     true,
                 ),
-                None => ("❌ Command was terminated by a signal.".to_string(), true),
+                None => (
+                    match status.signal() {
+                        Some(signal) => {
+                            signal_message(signal, status.core_dumped(), &control.stderr_tail())
+                        }
+                        None => "❌ Command was terminated by a signal.".to_string(),
+                    },
+                    true,
+                ),
             };
-
-            let final_message = format_with_title(&context.cli, &base_message);
-            if is_error {
-                eprintln!("{}", final_message);
-            } else {
-                println!("{}", final_message);
-            }
-            if let Err(e) = send_message(context, &final_message).await {
-                eprintln!("[shell_hook] Warning: Failed to send final message: {}", e);
-            }
-            Ok(exit_code)
+            (base_message, is_error, exit_code, status.code())
         }
         Err(e) => {
             let base_message = run_args
                 .on_failure
                 .clone()
                 .unwrap_or_else(|| format!("❌ Command failed to start: {}.", e));
-            let final_message = format_with_title(&context.cli, &base_message);
-            eprintln!("{}", final_message);
-            if let Err(e) = send_message(context, &final_message).await {
-                eprintln!(
-                    "[shell_hook] Warning: Failed to send failure message: {}",
-                    e
-                );
-            }
             // Decide on an exit code for command start failure
-            match e.kind() {
-                ErrorKind::NotFound => Ok(127),
-                _ => Ok(1),
+            let exit_code = match e.kind() {
+                ErrorKind::NotFound => 127,
+                _ => 1,
+            };
+            (base_message, true, exit_code, None)
+        }
+    };
+
+    let (base_message, is_error, exit_code, scored_code) = match control.cancellation() {
+        Some(reason) if reason.is_empty() => (
+            "🛑 Command was cancelled.".to_string(),
+            true,
+            CANCELLED_EXIT_CODE,
+            None,
+        ),
+        Some(reason) => (
+            format!("🛑 Command was cancelled: {}", reason),
+            true,
+            CANCELLED_EXIT_CODE,
+            None,
+        ),
+        None => (base_message, is_error, exit_code, scored_code),
+    };
+    let base_message = with_severity(&context.cli, base_message, scored_code, control);
+    let base_message = match control.tmpdir() {
+        Some(dir) if is_error => format!("{} Temp dir kept: {}", base_message, dir.display()),
+        Some(dir) => {
+            if let Err(e) = std::fs::remove_dir_all(&dir) {
+                context
+                    .warn(format!("Failed to remove temp dir: {}", e))
+                    .await;
+            }
+            base_message
+        }
+        None => base_message,
+    };
+    let base_message = if is_error {
+        with_flakiness(context, run_args, base_message).await
+    } else {
+        base_message
+    };
+    let base_message = with_outcome_strip(context, run_args, base_message, exit_code).await;
+    let summary_body = with_ci_context(context, with_git_context(&context.cli, base_message));
+    let final_message = format_with_title(&context.cli, &summary_body);
+    echo_status(&context.cli, &final_message, is_error);
+    let delivered = match control.take_coalesced() {
+        Some(held) => format!("{}\n{}", held, summary_body),
+        None => final_message.clone(),
+    };
+    if let Err(e) = send_message(context, &delivered).await {
+        context
+            .warn(format!("Failed to send final message: {}", e))
+            .await;
+    }
+    if context.cli.notifies_locally() && !context.cli.dry_run {
+        let title = context.cli.title.as_deref().unwrap_or("shell_hook");
+        if let Err(e) = notify_desktop(title, &summary_body, is_error) {
+            context
+                .warn(format!("Failed to show notification: {}", e))
+                .await;
+        }
+    }
+    if run_args.bell {
+        if let Err(e) = ring_bell(is_error).await {
+            context.warn(format!("Failed to ring bell: {}", e)).await;
+        }
+    }
+    if run_args.copy_summary {
+        if let Err(e) = copy_to_clipboard(&run_summary(&final_message, run_args)) {
+            context.warn(format!("Failed to copy summary: {}", e)).await;
+        }
+    }
+    context.emit(SessionEvent::Finished {
+        exit_code,
+        message: final_message,
+    });
+    Ok(exit_code)
+}
+
+/// Why a run that exited 0 still falls short of `--expect-*`, as its final message.
+fn unmet_expectation(cli: &Cli, run_args: &RunArgs, control: &RunControl) -> Option<String> {
+    if control.saw_expected_output() == Some(false) {
+        let pattern = run_args
+            .expect_output
+            .as_ref()
+            .map_or("", |regex| regex.as_str());
+        return Some(format!(
+            "⚠️ Command completed but expected output missing: no line matched `{}`.",
+            pattern
+        ));
+    }
+    let ran_for = control.run_time()?;
+    let took = cli.units.duration(ran_for);
+    if let Some(max) = run_args.expect_max_duration.filter(|max| ran_for > *max) {
+        return Some(format!(
+            "⚠️ Command finished successfully but took {}, longer than the expected {}.",
+            took,
+            cli.units.duration(max)
+        ));
+    }
+    if let Some(min) = run_args.expect_min_duration.filter(|min| ran_for < *min) {
+        return Some(format!(
+            "⚠️ Command finished successfully but took only {}, less than the expected {}.",
+            took,
+            cli.units.duration(min)
+        ));
+    }
+    None
+}
+
+/// Notes how often the job failed recently when `--flaky-threshold` says it is flaky.
+async fn with_flakiness(context: &AppContext, run_args: &RunArgs, message: String) -> String {
+    let cli = &context.cli;
+    let Some(threshold) = cli.flaky_threshold else {
+        return message;
+    };
+    let job = job_name(cli, run_args);
+    let previous =
+        match HistoryStore::from_cli(cli).recent(&job, cli.flaky_window.saturating_sub(1)) {
+            Ok(previous) => previous,
+            Err(e) => {
+                context
+                    .warn(format!("Failed to read run history: {}", e))
+                    .await;
+                return message;
             }
+        };
+    match Flakiness::of_failure(&previous, threshold) {
+        Some(flakiness) => format!("{} ({})", message, flakiness.describe()),
+        None => message,
+    }
+}
+
+/// Adds the `--outcome-strip` of the job's recent runs on a line of its own.
+async fn with_outcome_strip(
+    context: &AppContext,
+    run_args: &RunArgs,
+    message: String,
+    exit_code: i32,
+) -> String {
+    let cli = &context.cli;
+    let Some(runs) = cli.outcome_strip else {
+        return message;
+    };
+    let job = job_name(cli, run_args);
+    match HistoryStore::from_cli(cli).recent(&job, runs as usize - 1) {
+        Ok(previous) => format!(
+            "{}\nRecent runs: {}",
+            message,
+            outcome_strip(&previous, exit_code)
+        ),
+        Err(e) => {
+            context
+                .warn(format!("Failed to read run history: {}", e))
+                .await;
+            message
         }
     }
 }
 
+/// The text `--copy-summary` puts on the clipboard: the final message, then the report path.
+pub fn run_summary(final_message: &str, run_args: &RunArgs) -> String {
+    match &run_args.report {
+        Some(path) => format!("{}\nReport: {}", final_message, path.display()),
+        None => final_message.to_string(),
+    }
+}
+
+/// Appends the run's severity when `--severity-rule` is used, and the mention
+/// when the severity reaches `--mention-threshold`.
+fn with_severity(
+    cli: &Cli,
+    message: String,
+    exit_code: Option<i32>,
+    control: &RunControl,
+) -> String {
+    if cli.severity_rules.is_empty() && cli.mention.is_none() {
+        return message;
+    }
+    let severity = score(&cli.severity_rules, exit_code, control.matched_severity());
+    let mut message = if cli.severity_rules.is_empty() {
+        message
+    } else {
+        format!("{} (severity: {})", message, severity)
+    };
+    if let Some(mention) = cli
+        .mention
+        .as_ref()
+        .filter(|_| severity >= cli.mention_threshold)
+    {
+        message = format!("{} {}", message, mention);
+    }
+    message
+}
+
+/// Prints a status message locally, unless `--status-to-webhook-only` is set.
+pub(crate) fn echo_status(cli: &Cli, message: &str, is_error: bool) {
+    if cli.status_to_webhook_only || cli.nesting() == Some(NestedPolicy::Mute) {
+        return;
+    }
+    if is_error {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
 /// Formats a message with the title prefix if a title is provided.
 pub fn format_with_title(cli: &Cli, message: &str) -> String {
     if let Some(title) = &cli.title {
@@ -212,3 +930,25 @@ pub fn format_with_title(cli: &Cli, message: &str) -> String {
         message.to_string()
     }
 }
+
+/// Appends the detected CI job to `message` when `--ci-context auto` found one.
+pub fn with_ci_context(context: &AppContext, message: String) -> String {
+    match &context.ci {
+        Some(ci) => format!("{} ({})", message, ci.describe(context.renderer.as_ref())),
+        None => message,
+    }
+}
+
+/// Appends the current git context to `message` when `--git-context` is set.
+pub fn with_git_context(cli: &Cli, message: String) -> String {
+    if !cli.git_context {
+        return message;
+    }
+    let git = std::env::current_dir()
+        .ok()
+        .and_then(|dir| GitContext::detect(&dir));
+    match git {
+        Some(git) => format!("{} ({})", message, git),
+        None => message,
+    }
+}
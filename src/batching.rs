@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+/// The most lines an adaptive batch grows to.
+const MAX_LINES: usize = 500;
+/// The longest an adaptive batch waits for more output.
+const MAX_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Why a batch was sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlushTrigger {
+    /// The batch reached its line or byte limit.
+    Full,
+    /// No output arrived within the flush interval.
+    Timeout,
+}
+
+/// Decides when buffered output is sent.
+///
+/// Fixed batching uses `--buffer-size` and `--buffer-timeout` as given. Adaptive
+/// batching starts from them and doubles the batch size when output fills batches
+/// faster than they are sent, and the size and interval together when the endpoint
+/// is slow, so fewer, larger messages go out. Both shrink back towards the
+/// configured values once output slows down and the endpoint keeps up.
+#[derive(Clone, Debug)]
+pub struct Batching {
+    adaptive: bool,
+    min_lines: usize,
+    min_interval: Duration,
+    lines: usize,
+    interval: Duration,
+    max_bytes: usize,
+}
+
+impl Batching {
+    pub fn fixed(lines: usize, interval: Duration) -> Self {
+        Self {
+            adaptive: false,
+            min_lines: lines,
+            min_interval: interval,
+            lines,
+            interval,
+            max_bytes: usize::MAX,
+        }
+    }
+
+    /// Adaptive batching starting at `lines` and `interval`, never letting a
+    /// batch exceed `max_bytes`, the format's message size limit.
+    pub fn adaptive(lines: usize, interval: Duration, max_bytes: usize) -> Self {
+        Self {
+            adaptive: true,
+            max_bytes,
+            ..Self::fixed(lines, interval)
+        }
+    }
+
+    /// How many lines currently make a full batch.
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// How long to wait for more output before sending what is buffered.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Whether a line of `len` bytes still fits in a batch already holding `bytes`.
+    pub fn fits(&self, bytes: usize, len: usize) -> bool {
+        bytes.saturating_add(len + 1) <= self.max_bytes
+    }
+
+    /// Adjusts the batch size and interval after a batch of `lines` lines was sent.
+    pub fn observe(&mut self, trigger: FlushTrigger, lines: usize, latency: Duration) {
+        if !self.adaptive {
+            return;
+        }
+        let max_lines = MAX_LINES.max(self.min_lines);
+        let max_interval = MAX_INTERVAL.max(self.min_interval);
+        let slow_endpoint = latency * 2 > self.interval;
+        if slow_endpoint {
+            self.lines = (self.lines * 2).min(max_lines);
+            self.interval = (self.interval * 2).min(max_interval);
+        } else if trigger == FlushTrigger::Full {
+            self.lines = (self.lines * 2).min(max_lines);
+        } else if lines * 4 <= self.lines {
+            self.lines = (self.lines / 2).max(self.min_lines);
+            self.interval = (self.interval / 2).max(self.min_interval);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::render::Renderer;
+
+/// The CI job a run belongs to, read from the provider's environment variables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CiContext {
+    pub provider: &'static str,
+    pub job: Option<String>,
+    pub url: Option<String>,
+    pub actor: Option<String>,
+}
+
+impl CiContext {
+    /// Detects GitHub Actions, GitLab CI, Buildkite or Jenkins from the process environment.
+    pub fn detect() -> Option<Self> {
+        Self::detect_with(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+    }
+
+    /// Like [`detect`](Self::detect), reading variables through `var`.
+    pub fn detect_with(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        if var("GITHUB_ACTIONS").as_deref() == Some("true") {
+            let url = match (
+                var("GITHUB_SERVER_URL"),
+                var("GITHUB_REPOSITORY"),
+                var("GITHUB_RUN_ID"),
+            ) {
+                (Some(server), Some(repo), Some(run)) => {
+                    Some(format!("{}/{}/actions/runs/{}", server, repo, run))
+                }
+                _ => None,
+            };
+            let job = match (var("GITHUB_WORKFLOW"), var("GITHUB_JOB")) {
+                (Some(workflow), Some(job)) => Some(format!("{} / {}", workflow, job)),
+                (workflow, job) => workflow.or(job),
+            };
+            return Some(Self {
+                provider: "GitHub Actions",
+                job,
+                url,
+                actor: var("GITHUB_ACTOR"),
+            });
+        }
+        if var("GITLAB_CI").is_some() {
+            return Some(Self {
+                provider: "GitLab CI",
+                job: var("CI_JOB_NAME"),
+                url: var("CI_JOB_URL"),
+                actor: var("GITLAB_USER_LOGIN"),
+            });
+        }
+        if var("BUILDKITE").as_deref() == Some("true") {
+            return Some(Self {
+                provider: "Buildkite",
+                job: var("BUILDKITE_LABEL").or_else(|| var("BUILDKITE_PIPELINE_SLUG")),
+                url: var("BUILDKITE_BUILD_URL"),
+                actor: var("BUILDKITE_BUILD_CREATOR"),
+            });
+        }
+        if var("JENKINS_URL").is_some() {
+            return Some(Self {
+                provider: "Jenkins",
+                job: var("JOB_NAME"),
+                url: var("BUILD_URL"),
+                // Only set with the Build User Vars plugin.
+                actor: var("BUILD_USER_ID"),
+            });
+        }
+        None
+    }
+
+    /// Describes the job for a message, e.g. `GitHub Actions <url|CI / test>, by octocat`.
+    pub fn describe(&self, renderer: &dyn Renderer) -> String {
+        let mut description = self.provider.to_string();
+        match (&self.job, &self.url) {
+            (Some(job), Some(url)) => {
+                description.push(' ');
+                description.push_str(&renderer.link(url, job));
+            }
+            (Some(job), None) => {
+                description.push(' ');
+                description.push_str(job);
+            }
+            (None, Some(url)) => {
+                description.push(' ');
+                description.push_str(&renderer.link(url, "job"));
+            }
+            (None, None) => {}
+        }
+        if let Some(actor) = &self.actor {
+            description.push_str(", by ");
+            description.push_str(actor);
+        }
+        description
+    }
+}
@@ -0,0 +1,176 @@
+//! `shell_hook cleanup`: removes state left behind by old or crashed runs.
+
+use crate::cli::Cli;
+use crate::command::RUN_TMPDIR_PREFIX;
+use crate::digest::describe_period;
+use crate::error::AppError;
+use crate::groups::GroupStore;
+use crate::runs::{current_boot_id, unix_now, RunState, RunStore};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Something `cleanup` can remove, and what it was.
+#[derive(Debug, PartialEq)]
+pub struct Leftover {
+    pub path: PathBuf,
+    pub description: String,
+}
+
+/// Finds leftovers last touched before `cutoff` (Unix seconds).
+pub struct Sweep {
+    runs: RunStore,
+    groups: GroupStore,
+    temp_dir: PathBuf,
+    cutoff: u64,
+}
+
+impl Sweep {
+    pub fn new(runs: RunStore, groups: GroupStore, temp_dir: PathBuf, cutoff: u64) -> Self {
+        Self {
+            runs,
+            groups,
+            temp_dir,
+            cutoff,
+        }
+    }
+
+    /// Sweeps `--state-dir` and the system temp directory for anything older than `older_than`.
+    pub fn from_cli(cli: &Cli, older_than: Duration) -> Self {
+        Self::new(
+            RunStore::from_cli(cli),
+            GroupStore::from_cli(cli),
+            std::env::temp_dir(),
+            unix_now().saturating_sub(older_than.as_secs()),
+        )
+    }
+
+    /// Detached runs that are over or whose process is gone, groups that were
+    /// never ended, and scratch directories kept by failed `--tmpdir` runs.
+    ///
+    /// Directories without a readable record, e.g. from a crash while a run was
+    /// being registered, count by their modification time.
+    pub fn find(&self) -> io::Result<Vec<Leftover>> {
+        let boot_id = current_boot_id();
+        let mut found = Vec::new();
+        for (id, path) in subdirectories(self.runs.root())? {
+            let Ok(record) = self.runs.load(&id) else {
+                if modified_before(&path, self.cutoff) {
+                    found.push(Leftover {
+                        description: format!("unreadable run {}", id),
+                        path,
+                    });
+                }
+                continue;
+            };
+            let over = matches!(record.state, RunState::Finished | RunState::Interrupted)
+                || record.is_interrupted(boot_id.as_deref())
+                || record.is_orphaned();
+            if over && record.finished_at.unwrap_or(record.started_at) < self.cutoff {
+                found.push(Leftover {
+                    description: format!("run {} ({})", id, record.command),
+                    path,
+                });
+            }
+        }
+        for (id, path) in subdirectories(self.groups.root())? {
+            let stale = match self.groups.load(&id) {
+                Ok(group) => group.started_at < self.cutoff,
+                Err(_) => modified_before(&path, self.cutoff),
+            };
+            if stale {
+                found.push(Leftover {
+                    description: format!("group {}, never ended", id),
+                    path,
+                });
+            }
+        }
+        for (name, path) in subdirectories(&self.temp_dir)? {
+            if name.starts_with(RUN_TMPDIR_PREFIX) && modified_before(&path, self.cutoff) {
+                found.push(Leftover {
+                    description: "temp dir kept by a failed run".to_string(),
+                    path,
+                });
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// The subdirectories of `dir` by name; none if `dir` doesn't exist.
+fn subdirectories(dir: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut dirs: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .map(|entry| {
+            (
+                entry.file_name().to_string_lossy().into_owned(),
+                entry.path(),
+            )
+        })
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn modified_before(path: &Path, cutoff: u64) -> bool {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .is_some_and(|age| age.as_secs() < cutoff)
+}
+
+/// Removes everything [`Sweep::find`] turns up, or with `--dry-run` only lists it.
+///
+/// Returns 1 if anything could not be removed.
+pub fn run_cleanup(cli: &Cli, older_than: Duration) -> Result<i32, AppError> {
+    let leftovers = Sweep::from_cli(cli, older_than).find()?;
+    let mut failed = 0;
+    for leftover in &leftovers {
+        if cli.dry_run {
+            println!(
+                "Would remove {}: {}",
+                leftover.description,
+                leftover.path.display()
+            );
+            continue;
+        }
+        match fs::remove_dir_all(&leftover.path) {
+            Ok(()) => println!(
+                "Removed {}: {}",
+                leftover.description,
+                leftover.path.display()
+            ),
+            Err(e) => {
+                eprintln!(
+                    "[shell_hook] Warning: Failed to remove {}: {}",
+                    leftover.path.display(),
+                    e
+                );
+                failed += 1;
+            }
+        }
+    }
+    let period = describe_period(older_than, cli.units);
+    if cli.dry_run {
+        eprintln!(
+            "[shell_hook] Would remove {} older than {}",
+            cli.units.count(leftovers.len() as u64, "item"),
+            period
+        );
+    } else {
+        eprintln!(
+            "[shell_hook] Removed {} older than {}",
+            cli.units.count((leftovers.len() - failed) as u64, "item"),
+            period
+        );
+    }
+    Ok(if failed > 0 { 1 } else { 0 })
+}
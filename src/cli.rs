@@ -1,4 +1,21 @@
+use crate::digest::SloTarget;
+use crate::encryption::EncryptionKey;
+use crate::files::parse_umask;
+use crate::links::parse_link_template;
+use crate::noise::NoisePreset;
+use crate::oauth::ClientSecret;
+use crate::rewrite::RewriteRule;
+use crate::severity::{Severity, SeverityRule};
+use crate::sigv4::SigV4Scope;
+use crate::units::Units;
 use clap::{Parser, Subcommand, ValueEnum};
+use regex::Regex;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Set in the environment of wrapped commands, so nested invocations can tell they are nested.
+pub const PARENT_ENV: &str = "SHELL_HOOK_PARENT";
 
 /// A powerful CLI tool to stream command output to webhooks with buffering,
 /// custom messages, and multi-platform support.
@@ -17,6 +34,92 @@ pub struct Cli {
     #[arg(long, global = true, env = "WEBHOOK_URL", value_name = "URL")]
     pub webhook_url: Option<String>,
 
+    /// A webhook URL to try when delivery to the ones before it fails. Repeatable; tried in order.
+    #[arg(long = "fallback-webhook-url", global = true, value_name = "URL")]
+    pub fallback_webhook_urls: Vec<String>,
+
+    /// Another URL to spread deliveries over alongside --webhook-url. Repeatable.
+    #[arg(long = "shard-url", global = true, value_name = "URL")]
+    pub shard_urls: Vec<String>,
+
+    /// How deliveries are distributed when --shard-url is given.
+    #[arg(long, global = true, value_enum, default_value_t = ShardStrategy::RoundRobin)]
+    pub shard_by: ShardStrategy,
+
+    /// Where to announce results: `webhook`, `local` (a desktop notification when the run
+    /// finishes) or both. Repeatable [default: webhook].
+    #[arg(long = "notify", global = true, value_enum, value_name = "SINK")]
+    pub notify: Vec<NotifySink>,
+
+    /// Encrypt every payload with this base64 32-byte key (ChaCha20-Poly1305) and send
+    /// `{"alg", "nonce", "ciphertext"}` instead, for relays that must not read messages.
+    #[arg(
+        long,
+        global = true,
+        env = "SHELL_HOOK_ENCRYPT_KEY",
+        value_name = "KEY",
+        hide_env_values = true
+    )]
+    pub encrypt_key: Option<EncryptionKey>,
+
+    /// Fetch OAuth2 access tokens from this URL (client-credentials grant) and send them as
+    /// Bearer tokens with every webhook request.
+    #[arg(
+        long,
+        global = true,
+        value_name = "URL",
+        requires_all = ["oauth_client_id", "oauth_client_secret"],
+        conflicts_with = "aws_sigv4"
+    )]
+    pub oauth_token_url: Option<String>,
+
+    /// The OAuth2 client ID used with --oauth-token-url.
+    #[arg(
+        long,
+        global = true,
+        env = "SHELL_HOOK_OAUTH_CLIENT_ID",
+        value_name = "ID"
+    )]
+    pub oauth_client_id: Option<String>,
+
+    /// The OAuth2 client secret used with --oauth-token-url.
+    #[arg(
+        long,
+        global = true,
+        env = "SHELL_HOOK_OAUTH_CLIENT_SECRET",
+        value_name = "SECRET",
+        hide_env_values = true
+    )]
+    pub oauth_client_secret: Option<ClientSecret>,
+
+    /// Space-separated scopes to request with the token.
+    #[arg(long, global = true, value_name = "SCOPES")]
+    pub oauth_scope: Option<String>,
+
+    /// Sign webhook requests with AWS SigV4 for REGION/SERVICE (e.g. `us-east-1/lambda`),
+    /// using credentials from the AWS_* variables or ~/.aws/credentials.
+    #[arg(long, global = true, value_name = "REGION/SERVICE")]
+    pub aws_sigv4: Option<SigV4Scope>,
+
+    /// A PEM client certificate to present to webhook endpoints that require mutual TLS.
+    #[arg(long, global = true, value_name = "FILE", requires = "client_key")]
+    pub client_cert: Option<PathBuf>,
+
+    /// The PEM (PKCS#8) private key for --client-cert.
+    #[arg(long, global = true, value_name = "FILE", requires = "client_cert")]
+    pub client_key: Option<PathBuf>,
+
+    /// What to do when running inside a command that another shell_hook run is wrapping.
+    #[arg(
+        long,
+        global = true,
+        env = "SHELL_HOOK_NESTED",
+        value_enum,
+        default_value_t,
+        value_name = "POLICY"
+    )]
+    pub nested: NestedPolicy,
+
     /// A title to prepend to all messages, e.g., "[My Project]".
     #[arg(short, long, global = true, value_name = "TITLE")]
     pub title: Option<String>,
@@ -33,21 +136,359 @@ pub struct Cli {
     #[arg(long, global = true, default_value_t = 2.0, value_name = "SECONDS")]
     pub buffer_timeout: f64,
 
+    /// Grow batches beyond --buffer-size and --buffer-timeout while output is fast or
+    /// the endpoint is slow, and keep each batch within the format's message size limit.
+    #[arg(long, global = true)]
+    pub adaptive_batching: bool,
+
     /// Don't execute the command or send webhooks; just print what would be done.
     #[arg(long, global = true)]
     pub dry_run: bool,
+
+    /// The encoding of the command's output; lines are transcoded to UTF-8 before processing.
+    #[arg(long, global = true, value_enum, default_value_t = InputEncoding::Utf8, value_name = "ENCODING")]
+    pub input_encoding: InputEncoding,
+
+    /// Redirect the command's stderr into its stdout pipe (2>&1) so lines keep their exact order.
+    #[arg(long, global = true)]
+    pub merge_streams: bool,
+
+    /// Copy the command's stdout to ours byte for byte, without line buffering or re-encoding.
+    /// The webhook still gets decoded lines.
+    #[arg(long, global = true)]
+    pub raw_passthrough: bool,
+
+    /// Send output to the webhook even when it looks binary. By default a stream
+    /// whose start is mostly non-printable bytes is replaced by a byte count.
+    #[arg(long, global = true)]
+    pub allow_binary: bool,
+
+    /// Treat `::shell_hook flush::`, `::shell_hook section TITLE::` and
+    /// `::shell_hook notice TEXT::` lines as ordinary output instead of acting on them.
+    #[arg(long, global = true)]
+    pub no_control_lines: bool,
+
+    /// Send start, finish and other status messages to the webhook without printing them, so
+    /// stdout carries only the command's own output.
+    #[arg(long, global = true)]
+    pub status_to_webhook_only: bool,
+
+    /// Start the command without waiting for the start message, so connecting to the webhook
+    /// (DNS, TLS handshake) overlaps with the command's startup. Messages keep their order.
+    #[arg(long, global = true)]
+    pub warm_up: bool,
+
+    /// Press `p` during a run to pause or resume streaming to the webhook (terminal only).
+    /// The command's stdin is detached so it doesn't compete for keys.
+    #[arg(long, global = true)]
+    pub pause_key: bool,
+
+    /// Stop streaming output to the webhook after this much (e.g. `1MB`), posting one
+    /// truncation notice. The command keeps running and local output is unaffected.
+    #[arg(long, global = true, value_name = "SIZE", value_parser = parse_size)]
+    pub max_total_webhook_bytes: Option<u64>,
+
+    /// Prefix each line sent to the webhook with its line number in the run, e.g. `0421 | `.
+    #[arg(long, global = true)]
+    pub line_numbers: bool,
+
+    /// Score runs with exit=CODE:SEVERITY or match=REGEX:SEVERITY; repeatable.
+    #[arg(long = "severity-rule", global = true, value_name = "RULE")]
+    pub severity_rules: Vec<SeverityRule>,
+
+    /// Text such as `<!here>` or `<users/123>` appended to the final message of severe runs.
+    #[arg(long, global = true, value_name = "TEXT")]
+    pub mention: Option<String>,
+
+    /// The lowest severity that triggers --mention.
+    #[arg(long, global = true, value_enum, default_value_t = Severity::High)]
+    pub mention_threshold: Severity,
+
+    /// Leave well-known noise from a tool (progress ticks, deprecation spam) out of the
+    /// webhook messages; repeatable.
+    #[arg(
+        long = "noise-preset",
+        global = true,
+        value_enum,
+        value_name = "PRESET"
+    )]
+    pub noise_presets: Vec<NoisePreset>,
+
+    /// A sed-style rule (s/PATTERN/REPLACEMENT/[gi]) applied to each line before it is sent; repeatable.
+    #[arg(long = "rewrite", global = true, value_name = "RULE")]
+    pub rewrites: Vec<RewriteRule>,
+
+    /// Emphasize matches of this regex (e.g. 'ERROR|FAIL') in streamed output.
+    #[arg(long, global = true, value_name = "REGEX")]
+    pub highlight: Option<Regex>,
+
+    /// Mark added and removed lines with 🟩/🟥 when a batch of output is a unified diff.
+    #[arg(long, global = true)]
+    pub diff_markup: bool,
+
+    /// Send each batch of output as a code block, tagged `json`, `yaml` or `diff` when the
+    /// batch is mostly that. Other markup such as --highlight isn't applied inside blocks.
+    #[arg(long, global = true)]
+    pub code_fence: bool,
+
+    /// Render runs of delimited output lines as aligned monospace tables.
+    #[arg(long, global = true, value_enum, value_name = "DELIMITER")]
+    pub table: Option<TableMode>,
+
+    /// Directory for detached run state and logs [default: $XDG_STATE_HOME/shell_hook].
+    #[arg(long, global = true, env = "SHELL_HOOK_STATE_DIR", value_name = "DIR")]
+    pub state_dir: Option<PathBuf>,
+
+    /// Permission bits (octal) withheld from the state, logs, history and reports shell_hook
+    /// creates. The default `077` keeps them to the owner, since output can contain secrets.
+    #[arg(long, global = true, env = "SHELL_HOOK_UMASK", default_value = "077", value_parser = parse_umask, value_name = "OCTAL")]
+    pub umask: u32,
+
+    /// Record the outcome of each run in the history under --state-dir.
+    #[arg(long, global = true, env = "SHELL_HOOK_HISTORY")]
+    pub history: bool,
+
+    /// Mark a failure as flaky when at least this fraction (e.g. `0.2`) of the job's last
+    /// --flaky-window runs failed and it also succeeded among them.
+    #[arg(long, global = true, value_name = "RATE", value_parser = parse_rate, requires = "history")]
+    pub flaky_threshold: Option<f64>,
+
+    /// How many recent runs, including the current one, --flaky-threshold looks at.
+    #[arg(long, global = true, default_value_t = 20, value_name = "RUNS")]
+    pub flaky_window: usize,
+
+    /// Show the outcomes of the job's last RUNS runs, including this one, as a strip
+    /// such as `✅✅❌✅✅` in the final message.
+    #[arg(long, global = true, value_name = "RUNS", value_parser = clap::value_parser!(u32).range(1..), requires = "history")]
+    pub outcome_strip: Option<u32>,
+
+    /// Link `file:line` references in output using a URL template with `{sha}`, `{path}` and `{line}`.
+    #[arg(long, global = true, value_name = "TEMPLATE", value_parser = parse_link_template)]
+    pub link_template: Option<String>,
+
+    /// Add the git branch, short SHA and dirty state to start and finish messages.
+    #[arg(long, global = true)]
+    pub git_context: bool,
+
+    /// Add the CI job, its URL and who triggered it to start and finish messages.
+    #[arg(long, global = true, value_enum, default_value_t, value_name = "MODE")]
+    pub ci_context: CiContextMode,
+
+    /// Add a `shell_hook` object with the host, boot, run and a sequence number to
+    /// every payload, for collectors that merge streams from many hosts.
+    #[arg(long, global = true)]
+    pub watermark: bool,
+
+    /// Also send shell_hook's own warnings, such as failed or retried deliveries,
+    /// to the webhook, tagged with `"source": "shell_hook"`.
+    #[arg(long, global = true)]
+    pub forward_warnings: bool,
+
+    /// Print a summary of the output batches sent to stderr when the run ends.
+    /// Repeat (`-vv`) to also print lines, size, render time, latency and retries of each batch.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// How durations, sizes and line counts are written in messages.
+    #[arg(long, global = true, value_enum, default_value_t, value_name = "STYLE")]
+    pub units: Units,
+
+    /// Record runs in this group, as printed by `group start`.
+    #[arg(long, global = true, env = "SHELL_HOOK_GROUP", value_name = "ID")]
+    pub group: Option<String>,
+
+    /// Fail this fraction (0.0 to 1.0) of webhook deliveries without sending them,
+    /// for testing retries and fallbacks in staging. Simulated failures are
+    /// recorded like real ones; deliveries are only retried when
+    /// `--fallback-webhook-url` is set.
+    #[arg(long, global = true, hide = true, value_name = "RATE", value_parser = parse_rate)]
+    pub simulate_webhook_failure_rate: Option<f64>,
+
+    /// Delay every webhook delivery by DURATION, for testing slow endpoints.
+    #[arg(long, global = true, hide = true, value_name = "DURATION", value_parser = parse_duration)]
+    pub simulate_latency: Option<Duration>,
+
+    /// Set on the background process started by `run --detach`.
+    #[arg(long, global = true, hide = true)]
+    pub run_id: Option<String>,
+
+    /// The arguments this was parsed from, which `run --detach` hands to the
+    /// background process; filled in by [`Cli::try_parse_args`].
+    #[arg(skip)]
+    pub argv: Vec<OsString>,
+}
+
+impl Cli {
+    /// Parses `args` like [`Parser::try_parse_from`] and keeps them in [`Cli::argv`].
+    pub fn try_parse_args<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let argv: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        let mut cli = Self::try_parse_from(&argv)?;
+        cli.argv = argv;
+        Ok(cli)
+    }
+
+    /// Whether messages go to the webhook; true unless `--notify` leaves it out or
+    /// `--nested` silences this invocation.
+    pub fn notifies_webhook(&self) -> bool {
+        let silenced = matches!(
+            self.nesting(),
+            Some(NestedPolicy::Inherit | NestedPolicy::Mute)
+        );
+        !silenced && (self.notify.is_empty() || self.notify.contains(&NotifySink::Webhook))
+    }
+
+    /// The `--nested` policy in effect, or `None` when no shell_hook run is wrapping this one.
+    pub fn nesting(&self) -> Option<NestedPolicy> {
+        std::env::var_os(PARENT_ENV).map(|_| self.nested)
+    }
+
+    /// Whether finished runs raise a desktop notification.
+    pub fn notifies_locally(&self) -> bool {
+        self.notify.contains(&NotifySink::Local)
+    }
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Command {
     /// Run a single command and stream its output.
     Run(RunArgs),
+    /// Run a script file with the interpreter from its shebang, passing it the
+    /// arguments after it. The title defaults to the script's name.
+    #[command(name = "run-script")]
+    RunScript(RunArgs),
     /// Start an interactive shell session.
+    #[cfg(feature = "shell")]
     Shell,
+    /// Show the state of a detached run, or list all of them.
+    Status {
+        /// The run ID printed by `run --detach`.
+        id: Option<String>,
+    },
+    /// Cancel a detached run. Its final message says it was cancelled, and why.
+    Kill {
+        /// The run ID printed by `run --detach`.
+        id: String,
+        /// Why the run is being cancelled, quoted in its final message.
+        #[arg(long, value_name = "TEXT")]
+        reason: Option<String>,
+    },
+    /// Print the captured output of a detached run.
+    Logs {
+        /// The run ID printed by `run --detach`.
+        id: String,
+    },
+    /// Notify about detached runs that a reboot cut short. Suitable for `@reboot` cron.
+    ///
+    /// Given a command, run it as a health check every --interval instead, notifying
+    /// only when it starts failing or recovers.
+    Check {
+        /// Time between health checks.
+        #[arg(long, default_value = "60s", value_parser = parse_duration, value_name = "DURATION", requires = "command")]
+        interval: Duration,
+        /// Consecutive failed checks before notifying, to ride out flaps.
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..), value_name = "N", requires = "command")]
+        failures: u32,
+        /// Stop after N checks, exiting non-zero if the last one failed.
+        #[arg(long, value_name = "N", requires = "command")]
+        count: Option<u32>,
+        /// The health check command, e.g. `curl -sf https://svc/health`.
+        #[arg(value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+    /// Remove detached runs, unended groups and kept --tmpdir directories older than
+    /// --older-than. With --dry-run, only list them.
+    Cleanup {
+        /// How old leftovers must be, e.g. `7d`, `2w` or `12h`.
+        #[arg(long, default_value = "7d", value_parser = crate::digest::parse_period, value_name = "PERIOD")]
+        older_than: Duration,
+    },
+    /// Group separate invocations under one umbrella with an aggregate summary.
+    Group {
+        #[command(subcommand)]
+        action: GroupAction,
+    },
+    /// Work with the run history recorded by --history.
+    History {
+        #[command(subcommand)]
+        action: HistoryAction,
+    },
+    /// Summarize the run history: success rates per job, slowest runs and top failures.
+    Digest {
+        /// How far back to look, e.g. `7d`, `2w` or `12h`.
+        #[arg(long, default_value = "7d", value_parser = crate::digest::parse_period, value_name = "PERIOD")]
+        since: Duration,
+        /// Send the digest to the webhook.
+        #[arg(long)]
+        post: bool,
+        /// Write the digest to FILE as Markdown instead of printing it.
+        #[arg(long, value_name = "FILE")]
+        markdown: Option<PathBuf>,
+        /// Report each job's success rate over every --window instead, like an SLO,
+        /// and exit with 1 if a job is below its --target.
+        #[arg(long, conflicts_with = "since")]
+        slo: bool,
+        /// A window for --slo, e.g. `30d`; repeat for several. Default: `7d` and `30d`.
+        #[arg(long = "window", requires = "slo", value_parser = crate::digest::parse_period, value_name = "PERIOD")]
+        windows: Vec<Duration>,
+        /// The success rate jobs should reach with --slo: `PERCENT` for every job, or
+        /// `JOB=PERCENT` for one; repeat for several.
+        #[arg(long = "target", requires = "slo", value_parser = crate::digest::parse_slo_target, value_name = "[JOB=]PERCENT")]
+        targets: Vec<SloTarget>,
+    },
+    /// Write sample webhook payloads for every event and format, for testing receivers.
+    Fixtures {
+        /// Directory to write `<format>/<event>.json` files into.
+        #[arg(long, value_name = "DIR")]
+        out: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum GroupAction {
+    /// Start a group and print the `export SHELL_HOOK_GROUP=...` line that joins later runs to it.
+    Start {
+        /// What the group is, e.g. "Release 1.2".
+        name: String,
+    },
+    /// Send the group's summary and close it.
+    End,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HistoryAction {
+    /// Write every recorded run, with its duration and delivery counts, for spreadsheets
+    /// or data warehouses.
+    Export {
+        /// The file format.
+        #[arg(long = "as", value_enum, default_value_t, value_name = "FORMAT")]
+        export_format: ExportFormat,
+        /// Write to FILE instead of stdout.
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /// Send a detached run's captured output again, to the webhook and format given
+    /// with this command, e.g. when the original channel was misconfigured.
+    Forward {
+        /// The run ID printed by `run --detach`.
+        id: String,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row.
+    #[default]
+    Csv,
+    /// One JSON object per line, as loaded by most data warehouses.
+    Jsonl,
 }
 
 /// Arguments for running a single command.
-#[derive(Parser, Debug, Clone)]
+#[derive(Parser, Debug, Clone, Default)]
 pub struct RunArgs {
     /// Custom message to send on command success.
     #[arg(long, value_name = "MESSAGE")]
@@ -61,9 +502,133 @@ pub struct RunArgs {
     #[arg(short, long)]
     pub quiet: bool,
 
+    /// Run in the background and print a run ID for `status` and `logs`.
+    #[arg(long)]
+    pub detach: bool,
+
+    /// Like --detach, but wait for the command to start and print its PID instead of a run ID.
+    #[arg(long, conflicts_with = "detach")]
+    pub handoff: bool,
+
+    /// Print `SHELL_HOOK_RESULT exit=0 duration=123s run_id=...` as the last line of
+    /// stdout when the run finishes, for calling scripts to capture.
+    #[arg(long)]
+    pub result_line: bool,
+
+    /// Write the --result-line to file descriptor FD instead of stdout.
+    #[arg(long, value_name = "FD", requires = "result_line")]
+    pub result_fd: Option<i32>,
+
+    /// Write a JSON report of the run, including every delivery attempt, to FILE when it finishes.
+    #[arg(long, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// Include the environment, working directory, git commit and tool versions in the report.
+    #[arg(long, requires = "report")]
+    pub capture_env: bool,
+
+    /// Only capture environment variables whose names match REGEX.
+    #[arg(long, value_name = "REGEX", requires = "capture_env")]
+    pub capture_env_filter: Option<Regex>,
+
+    /// A program whose `--version` output is captured with the environment. Repeatable.
+    #[arg(
+        long = "capture-tool",
+        value_name = "PROGRAM",
+        requires = "capture_env"
+    )]
+    pub capture_tools: Vec<String>,
+
+    /// Parse `file:line[:col]: level: message` diagnostics from the output and write
+    /// them to FILE as SARIF, for code-scanning UIs.
+    #[arg(long, value_name = "FILE")]
+    pub emit_sarif: Option<PathBuf>,
+
+    /// Ring the terminal bell when the run ends: once on success, three times on failure.
+    #[arg(long)]
+    pub bell: bool,
+
+    /// Copy the final status line, and the report path if any, to the clipboard when the run ends.
+    #[arg(long)]
+    pub copy_summary: bool,
+
+    /// Send a single message combining start, output and result when the command finishes
+    /// within DURATION (e.g. `5s`), instead of separate messages.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub coalesce_under: Option<Duration>,
+
+    /// Wait a random time up to DURATION (e.g. `30s`, `5m`) before starting, so
+    /// machines on the same schedule don't all fire at once.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub splay: Option<Duration>,
+
+    /// Require a line of output matching REGEX for the run to count as a success, for
+    /// jobs whose exit code can't be trusted.
+    #[arg(long, value_name = "REGEX")]
+    pub expect_output: Option<Regex>,
+
+    /// Report a successful run that took longer than DURATION as a warning.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub expect_max_duration: Option<Duration>,
+
+    /// Report a successful run that finished in under DURATION as a warning, e.g. a
+    /// backup that had nothing to back up.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub expect_min_duration: Option<Duration>,
+
+    /// A JSON or iCalendar file of maintenance windows during which the run must not start.
+    #[arg(long, value_name = "FILE")]
+    pub maintenance_calendar: Option<PathBuf>,
+
+    /// What to do when the run would start inside a maintenance window.
+    #[arg(long, value_enum, default_value_t, value_name = "POLICY")]
+    pub during_maintenance: MaintenancePolicy,
+
     /// The command to execute and stream its output.
     #[arg(required = true, value_name = "COMMAND")]
     pub command: Vec<String>,
+
+    /// Run the command with a fresh private directory as TMPDIR (also SHELL_HOOK_TMP).
+    /// It is deleted after a successful run and kept, with its path in the message, on failure.
+    #[arg(long)]
+    pub tmpdir: bool,
+
+    /// Line-buffer the command's stdout and stderr with `stdbuf -oL -eL` (and
+    /// `PYTHONUNBUFFERED=1`), so tools that block-buffer pipes stream live.
+    #[arg(long)]
+    pub unbuffer: bool,
+
+    /// Print the command line handed to the shell to stderr before running it.
+    #[arg(long)]
+    pub show_effective_command: bool,
+
+    /// Run `command` as a script path and its arguments instead of through `sh -c`.
+    #[arg(skip)]
+    pub run_as_script: bool,
+
+    /// Extra environment variables for the command, such as the interactive shell's `:set` variables.
+    #[arg(skip)]
+    pub env: Vec<(String, String)>,
+}
+
+impl RunArgs {
+    /// The command line passed to `sh -c`.
+    ///
+    /// A single argument is taken as a shell snippet and kept as written, so
+    /// `run -- "make && make test"` still uses the shell. Several arguments are
+    /// quoted one by one, so each reaches the program exactly as given.
+    pub fn command_line(&self) -> String {
+        shell_command_line(&self.command)
+    }
+}
+
+/// Joins command arguments into a line for `sh -c`, as [`RunArgs::command_line`] does.
+pub fn shell_command_line(command: &[String]) -> String {
+    match command {
+        [snippet] => snippet.clone(),
+        args => shlex::try_join(args.iter().map(String::as_str))
+            .expect("command-line arguments cannot contain NUL bytes"),
+    }
 }
 
 #[derive(ValueEnum, Clone, Debug, Default)]
@@ -72,3 +637,116 @@ pub enum WebhookFormat {
     GoogleChat,
     Slack,
 }
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputEncoding {
+    /// UTF-8; invalid sequences are replaced rather than ending the stream.
+    #[default]
+    Utf8,
+    /// ISO-8859-1 (decoded as its Windows-1252 superset).
+    Latin1,
+    ShiftJis,
+    /// UTF-8 when a line is valid UTF-8, Latin-1 otherwise.
+    Auto,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShardStrategy {
+    /// Each delivery goes to the next URL in turn.
+    #[default]
+    RoundRobin,
+    /// Every delivery from one run goes to the same URL, keeping its output in order.
+    RunId,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CiContextMode {
+    #[default]
+    Off,
+    /// Detect GitHub Actions, GitLab CI, Buildkite or Jenkins from the environment.
+    Auto,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NestedPolicy {
+    /// Post nothing; the outer run already streams this one's output and status lines.
+    #[default]
+    Inherit,
+    /// Post nothing and print no status lines, passing through only the command's output.
+    Mute,
+    /// Post as if this were the only run.
+    Independent,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotifySink {
+    /// The configured webhook.
+    Webhook,
+    /// A desktop notification on this machine.
+    Local,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MaintenancePolicy {
+    /// Don't run, and post a notice saying so.
+    #[default]
+    Skip,
+    /// Wait for the window to end, then run.
+    Defer,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableMode {
+    /// Tab-separated fields.
+    Tab,
+    /// Comma-separated fields, with optional double quotes.
+    Csv,
+}
+
+/// Parses a fraction between 0 and 1, such as `0.3`.
+pub fn parse_rate(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+        _ => Err(format!("'{}' is not a rate between 0 and 1", s)),
+    }
+}
+
+/// Parses durations such as `500ms`, `30s`, `5m` or `2h`. A bare number is seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", s))?;
+    let seconds = match unit {
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        _ => return Err(format!("unknown unit '{}' in duration '{}'", unit, s)),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("invalid duration '{}': {}", s, e))
+}
+
+/// Parses sizes such as `512`, `64KB` or `1MB` (powers of 1024; `K`, `KiB` etc. also accepted).
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", s))?;
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        _ => return Err(format!("unknown unit '{}' in size '{}'", unit, s)),
+    };
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size '{}' is too large", s))
+}
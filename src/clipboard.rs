@@ -0,0 +1,58 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Programs tried, in order, when there is no terminal to send OSC 52 to.
+const CLIPBOARD_PROGRAMS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+];
+
+/// Builds the OSC 52 escape sequence that asks the terminal to put `text` on the clipboard.
+///
+/// Inside tmux the sequence is wrapped in a passthrough so it reaches the outer terminal.
+pub fn osc52_sequence(text: &str, in_tmux: bool) -> String {
+    let osc = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    if in_tmux {
+        format!("\x1bPtmux;{}\x1b\\", osc.replace('\x1b', "\x1b\x1b"))
+    } else {
+        osc
+    }
+}
+
+/// Copies `text` to the clipboard.
+///
+/// OSC 52 goes through the controlling terminal, so it also works over SSH; without one,
+/// the first available native clipboard program is used.
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    if let Ok(mut tty) = OpenOptions::new().write(true).open("/dev/tty") {
+        let sequence = osc52_sequence(text, std::env::var_os("TMUX").is_some());
+        tty.write_all(sequence.as_bytes())?;
+        return tty.flush();
+    }
+
+    for (program, args) in CLIPBOARD_PROGRAMS {
+        let Ok(mut child) = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "no terminal or clipboard program available",
+    ))
+}
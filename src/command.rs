@@ -1,39 +1,358 @@
 use crate::app::AppContext;
-use crate::cli::RunArgs;
-use crate::message::StreamMessage;
+use crate::cli::{Cli, InputEncoding, RunArgs, PARENT_ENV};
+use crate::crash::STDERR_TAIL_LINES;
+use crate::diagnostics::{parse_diagnostic, Diagnostic};
+use crate::directives::parse_directive;
+use crate::message::{OutputStream, StreamMessage};
+use crate::noise::NoiseFilter;
+use crate::rewrite::apply_rewrites;
+use crate::runs::new_run_id;
+use crate::script::script_argv;
+use crate::session::SessionEvent;
+use crate::severity::{line_severity, Severity};
+use encoding_rs::{SHIFT_JIS, WINDOWS_1252};
+use regex::Regex;
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
 use std::process::{ExitStatus, Stdio};
-use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::unix::pipe;
 use tokio::process::Command;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
+
+/// Per-run switches that can be flipped while the command is running, and
+/// what the run has observed so far.
+#[derive(Debug)]
+pub struct RunControl {
+    local_echo: AtomicBool,
+    streaming_paused: AtomicBool,
+    /// Lines not sent to the webhook because streaming was paused.
+    withheld_lines: AtomicU64,
+    /// Worst severity matched in the output, as `Severity as u8 + 1`; 0 is none.
+    matched_severity: AtomicU8,
+    lines_read: AtomicU64,
+    child_pid: watch::Sender<Option<u32>>,
+    /// Start message and output held back by `--coalesce-under`, sent with the final message.
+    coalesced: Mutex<Option<String>>,
+    /// The scratch directory made by `--tmpdir`, exported to the command.
+    tmpdir: Mutex<Option<PathBuf>>,
+    /// Diagnostics parsed from the output for `--emit-sarif`; `None` when not collecting.
+    diagnostics: Mutex<Option<Vec<Diagnostic>>>,
+    /// How long the command ran, from spawning it to its exit.
+    run_time: Mutex<Option<Duration>>,
+    /// The last lines the command wrote to stderr, quoted if it crashes.
+    stderr_tail: Mutex<VecDeque<String>>,
+    /// The `--expect-output` pattern and whether a line has matched it yet.
+    expected_output: Mutex<Option<(Regex, bool)>>,
+    /// Why the run was cancelled with `shell_hook kill`, once it has been.
+    cancellation: Mutex<Option<String>>,
+}
+
+/// Exit code of a cancelled run, as if the command had been ended by SIGTERM.
+pub const CANCELLED_EXIT_CODE: i32 = 128 + libc::SIGTERM;
+
+impl Default for RunControl {
+    fn default() -> Self {
+        Self {
+            local_echo: AtomicBool::new(true),
+            streaming_paused: AtomicBool::new(false),
+            withheld_lines: AtomicU64::new(0),
+            matched_severity: AtomicU8::new(0),
+            lines_read: AtomicU64::new(0),
+            child_pid: watch::Sender::new(None),
+            coalesced: Mutex::new(None),
+            tmpdir: Mutex::new(None),
+            diagnostics: Mutex::new(None),
+            run_time: Mutex::new(None),
+            expected_output: Mutex::new(None),
+            stderr_tail: Mutex::new(VecDeque::new()),
+            cancellation: Mutex::new(None),
+        }
+    }
+}
+
+impl RunControl {
+    /// Whether output lines are printed to the local terminal.
+    pub fn local_echo(&self) -> bool {
+        self.local_echo.load(Ordering::Relaxed)
+    }
+
+    pub fn set_local_echo(&self, enabled: bool) {
+        self.local_echo.store(enabled, Ordering::Relaxed);
+    }
+
+    /// The worst severity a `match=` rule assigned to the output so far.
+    pub fn matched_severity(&self) -> Option<Severity> {
+        match self.matched_severity.load(Ordering::Relaxed) {
+            0 => None,
+            n => Some(Severity::from_u8(n - 1)),
+        }
+    }
+
+    /// Whether output is currently kept from the webhook. Local echo is unaffected.
+    pub fn streaming_paused(&self) -> bool {
+        self.streaming_paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_streaming_paused(&self, paused: bool) {
+        self.streaming_paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Returns how many lines were withheld since the last call, and resets the count.
+    pub fn take_withheld_lines(&self) -> u64 {
+        self.withheld_lines.swap(0, Ordering::Relaxed)
+    }
+
+    /// Stores `text` to be delivered together with the final message instead of on its own.
+    pub fn coalesce(&self, text: String) {
+        *self.coalesced.lock().unwrap() = Some(text);
+    }
+
+    pub fn take_coalesced(&self) -> Option<String> {
+        self.coalesced.lock().unwrap().take()
+    }
+
+    /// Gives the command `dir` as its `TMPDIR` and `SHELL_HOOK_TMP`.
+    pub fn set_tmpdir(&self, dir: PathBuf) {
+        *self.tmpdir.lock().unwrap() = Some(dir);
+    }
+
+    pub fn tmpdir(&self) -> Option<PathBuf> {
+        self.tmpdir.lock().unwrap().clone()
+    }
+
+    /// How long the command ran; `None` until it has exited.
+    pub fn run_time(&self) -> Option<Duration> {
+        *self.run_time.lock().unwrap()
+    }
+
+    /// Starts parsing output lines as linter or compiler diagnostics.
+    pub fn collect_diagnostics(&self) {
+        self.diagnostics
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Vec::new);
+    }
+
+    fn record_diagnostic(&self, line: &str) {
+        if let Some(diagnostics) = self.diagnostics.lock().unwrap().as_mut() {
+            diagnostics.extend(parse_diagnostic(line));
+        }
+    }
+
+    pub fn take_diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.lock().unwrap().take().unwrap_or_default()
+    }
+
+    fn record_stderr(&self, line: &str) {
+        let mut tail = self.stderr_tail.lock().unwrap();
+        if tail.len() == STDERR_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line.to_string());
+    }
+
+    /// The last [`STDERR_TAIL_LINES`] lines of stderr, oldest first.
+    pub fn stderr_tail(&self) -> Vec<String> {
+        self.stderr_tail.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Starts watching the output for a line matching `pattern`.
+    pub fn expect_output(&self, pattern: Regex) {
+        *self.expected_output.lock().unwrap() = Some((pattern, false));
+    }
+
+    fn record_expected_output(&self, line: &str) {
+        if let Some((pattern, seen)) = self.expected_output.lock().unwrap().as_mut() {
+            *seen = *seen || pattern.is_match(line);
+        }
+    }
+
+    /// Whether a line matched the expected output; `None` when not watching for one.
+    pub fn saw_expected_output(&self) -> Option<bool> {
+        self.expected_output
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(_, seen)| *seen)
+    }
+
+    /// Receives the PID of the command once it has been spawned.
+    pub fn watch_child_pid(&self) -> watch::Receiver<Option<u32>> {
+        self.child_pid.subscribe()
+    }
+
+    /// Ends the command with SIGTERM and has the final message report `reason`
+    /// instead of the signal. A command not started yet is ended as soon as it is.
+    pub fn cancel(&self, reason: &str) {
+        *self.cancellation.lock().unwrap() = Some(reason.to_string());
+        self.terminate_child();
+    }
+
+    pub fn cancellation(&self) -> Option<String> {
+        self.cancellation.lock().unwrap().clone()
+    }
+
+    /// Signals the command's process group, so whatever `sh -c` started goes too,
+    /// or just the command when it does not lead one.
+    fn terminate_child(&self) {
+        if let Some(pid) = *self.child_pid.borrow() {
+            let pid = pid as libc::pid_t;
+            // SAFETY: kill has no memory-safety requirements; a stale PID only fails with ESRCH.
+            unsafe {
+                if libc::kill(-pid, libc::SIGTERM) != 0 {
+                    libc::kill(pid, libc::SIGTERM);
+                }
+            }
+        }
+    }
+
+    /// Counts a line of output and returns its 1-based number across both streams.
+    pub fn next_line_number(&self) -> u64 {
+        self.lines_read.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn raise_severity(&self, severity: Severity) {
+        self.matched_severity
+            .fetch_max(severity as u8 + 1, Ordering::Relaxed);
+    }
+}
+
+/// The first executable called `name` in `PATH`.
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| {
+            path.metadata()
+                .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        })
+}
 
 /// Spawns the command, captures its stdout/stderr, and sends lines to the channel.
 pub async fn run_command_and_stream(
-    _context: Arc<AppContext>,
+    context: Arc<AppContext>,
     tx: mpsc::Sender<StreamMessage>,
     run_args: &RunArgs,
+    control: Arc<RunControl>,
 ) -> std::io::Result<ExitStatus> {
     // For the `run` subcommand, we execute the command directly.
     // For the `shell` subcommand, we wrap the command in `sh -c`.
     // This is now handled in `app.rs` by creating the appropriate command vector.
-    let command_str = run_args.command.join(" ");
-    let mut child = Command::new("sh")
-        .arg("-c")
-        .arg(&command_str)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
+    let mut argv = if run_args.run_as_script {
+        let (script, args) = run_args
+            .command
+            .split_first()
+            .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, "no script given"))?;
+        script_argv(script, args)?
+    } else {
+        vec!["sh".to_string(), "-c".to_string(), run_args.command_line()]
+    };
+    if run_args.unbuffer {
+        match find_in_path("stdbuf") {
+            Some(stdbuf) => {
+                let prefix = [
+                    stdbuf.to_string_lossy().into_owned(),
+                    "-oL".into(),
+                    "-eL".into(),
+                ];
+                argv.splice(0..0, prefix);
+            }
+            None => {
+                context
+                    .warn("stdbuf not found; running without --unbuffer.".to_string())
+                    .await
+            }
+        }
+    }
+    if run_args.show_effective_command {
+        eprintln!(
+            "[shell_hook] Effective command: {}",
+            shlex::try_join(argv.iter().map(String::as_str))
+                .expect("command-line arguments cannot contain NUL bytes")
+        );
+    }
+    let mut command = Command::new(&argv[0]);
+    command.args(&argv[1..]);
+    if run_args.unbuffer {
+        // stdbuf only reaches C stdio; Python keeps its own buffers.
+        command.env("PYTHONUNBUFFERED", "1");
+    }
+    command.envs(run_args.env.iter().map(|(name, value)| (name, value)));
+    if context.cli.run_id.is_some() {
+        // A detached run has no terminal whose signals the command would miss, so it
+        // can lead its own process group for `shell_hook kill` to end as a whole.
+        command.process_group(0);
+    }
+    // Lets shell_hook invocations inside the command apply `--nested`.
+    command.env(PARENT_ENV, std::process::id().to_string());
+    if let Some(dir) = control.tmpdir() {
+        command.env("TMPDIR", &dir).env("SHELL_HOOK_TMP", &dir);
+    }
+    if context.cli.pause_key {
+        // The terminal belongs to the pause key listener for the duration of the run.
+        command.stdin(Stdio::null());
+    }
+
+    // With --merge-streams both descriptors of the child point at one pipe,
+    // so the kernel preserves the order in which lines were written.
+    let merged_output = if context.cli.merge_streams {
+        let (reader, writer) = std::io::pipe()?;
+        command.stdout(writer.try_clone()?).stderr(writer);
+        Some(reader)
+    } else {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        None
+    };
+    let mut child = command.spawn()?;
+    let spawned_at = Instant::now();
+    control.child_pid.send_replace(child.id());
+    if control.cancellation().is_some() {
+        control.terminate_child();
+    }
+    // Close our copies of the write end, or the reader would never see EOF.
+    drop(command);
 
     let mut tasks = Vec::new();
+    if let Some(reader) = merged_output {
+        tasks.push(stream_output(
+            context.clone(),
+            control.clone(),
+            pipe::Receiver::from_owned_fd(reader.into())?,
+            tx.clone(),
+            run_args.quiet,
+            OutputStream::Stdout,
+        ));
+    }
     if let Some(stdout) = child.stdout.take() {
-        tasks.push(stream_output(stdout, tx.clone(), run_args.quiet, false));
+        tasks.push(stream_output(
+            context.clone(),
+            control.clone(),
+            stdout,
+            tx.clone(),
+            run_args.quiet,
+            OutputStream::Stdout,
+        ));
     }
     if let Some(stderr) = child.stderr.take() {
-        tasks.push(stream_output(stderr, tx.clone(), run_args.quiet, true));
+        tasks.push(stream_output(
+            context.clone(),
+            control.clone(),
+            stderr,
+            tx.clone(),
+            run_args.quiet,
+            OutputStream::Stderr,
+        ));
     }
 
     // Wait for the command to complete and for readers to finish
     let status = child.wait().await?;
+    *control.run_time.lock().unwrap() = Some(spawned_at.elapsed());
     for task in tasks {
         let _ = task.await;
     }
@@ -44,24 +363,218 @@ pub async fn run_command_and_stream(
     Ok(status)
 }
 
+/// Names the `--tmpdir` scratch directories, which are kept when a run fails.
+pub(crate) const RUN_TMPDIR_PREFIX: &str = "shell_hook_run_";
+
+/// How many names already taken in the temp directory `create_run_tmpdir` skips.
+const TMPDIR_ATTEMPTS: u32 = 8;
+
+/// Creates a private scratch directory for one run under the system temp directory.
+///
+/// The temp directory is shared with other users, so a name someone else has
+/// taken is replaced by a fresh one.
+pub fn create_run_tmpdir() -> std::io::Result<PathBuf> {
+    let mut attempts = 0;
+    loop {
+        let dir = std::env::temp_dir().join(format!("{}{}", RUN_TMPDIR_PREFIX, new_run_id()));
+        match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e)
+                if e.kind() == std::io::ErrorKind::AlreadyExists && attempts < TMPDIR_ATTEMPTS =>
+            {
+                attempts += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// How much of a stream's start is inspected for binary output.
+const BINARY_SAMPLE_BYTES: usize = 1024;
+
+/// Whether `sample` looks like binary data rather than text: it contains a NUL
+/// byte, or more than 30% of it is control characters other than whitespace and
+/// the escape that starts terminal color codes.
+pub fn looks_binary(sample: &[u8]) -> bool {
+    let control = sample
+        .iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x1b)) || b == 0x7f)
+        .count();
+    sample.contains(&0) || control * 10 > sample.len() * 3
+}
+
+/// The line sent in place of a stream that looked binary.
+fn binary_notice(stream: OutputStream, bytes: u64, cli: &Cli) -> String {
+    let name = match stream {
+        OutputStream::Stdout => "stdout",
+        OutputStream::Stderr => "stderr",
+    };
+    format!(
+        "[{} suppressed (binary output detected), {}]",
+        name,
+        cli.units.bytes(bytes)
+    )
+}
+
 /// Helper to stream output from a reader to a channel, printing lines to stdout/stderr.
 fn stream_output<R: tokio::io::AsyncRead + Unpin + Send + 'static>(
+    context: Arc<AppContext>,
+    control: Arc<RunControl>,
     reader: R,
     tx: mpsc::Sender<StreamMessage>,
     quiet_mode: bool,
-    is_stderr: bool,
+    stream: OutputStream,
 ) -> tokio::task::JoinHandle<()> {
+    let encoding = context.cli.input_encoding;
+    let passthrough = context.cli.raw_passthrough && matches!(stream, OutputStream::Stdout);
+    // Passthrough has already echoed the line by the time it could be stripped.
+    let directives = !context.cli.no_control_lines && !passthrough;
+    let noise = NoiseFilter::new(&context.cli.noise_presets);
     tokio::spawn(async move {
-        let mut reader = BufReader::new(reader).lines();
-        while let Ok(Some(line)) = reader.next_line().await {
-            if is_stderr {
-                eprintln!("{}", line);
+        let mut reader = BufReader::new(reader);
+        let mut stdout = tokio::io::stdout();
+        let mut buf = Vec::new();
+        let mut sample = Vec::new();
+        let mut binary_bytes: Option<u64> = None;
+        loop {
+            buf.clear();
+            let read = if passthrough {
+                let echo = control.local_echo();
+                read_until_passthrough(&mut reader, &mut buf, &mut stdout, echo).await
             } else {
-                println!("{}", line);
+                reader.read_until(b'\n', &mut buf).await
+            };
+            match read {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            if !context.cli.allow_binary
+                && binary_bytes.is_none()
+                && sample.len() < BINARY_SAMPLE_BYTES
+            {
+                let wanted = (BINARY_SAMPLE_BYTES - sample.len()).min(buf.len());
+                sample.extend_from_slice(&buf[..wanted]);
+                if looks_binary(&sample) {
+                    binary_bytes = Some(0);
+                }
+            }
+            let read_at = Instant::now();
+            let line = decode_line(&buf, encoding);
+            if let Some(directive) = directives.then(|| parse_directive(&line)).flatten() {
+                if quiet_mode || control.streaming_paused() {
+                    continue;
+                }
+                for message in directive.messages() {
+                    if tx.send(message).await.is_err() {
+                        return; // Receiver has been dropped
+                    }
+                }
+                continue;
+            }
+            let line_number = control.next_line_number();
+            if control.local_echo() && !passthrough {
+                match stream {
+                    OutputStream::Stdout => println!("{}", line),
+                    OutputStream::Stderr => eprintln!("{}", line),
+                }
+            }
+            if let Some(bytes) = binary_bytes.as_mut() {
+                *bytes += buf.len() as u64;
+                continue;
+            }
+            control.record_diagnostic(&line);
+            control.record_expected_output(&line);
+            if stream == OutputStream::Stderr || context.cli.merge_streams {
+                control.record_stderr(&line);
+            }
+            if let Some(severity) = line_severity(&context.cli.severity_rules, &line) {
+                control.raise_severity(severity);
             }
-            if !quiet_mode && tx.send(StreamMessage::Line(line)).await.is_err() {
+            context.emit(SessionEvent::Output {
+                line: line.clone(),
+                stream,
+            });
+            if noise.as_ref().is_some_and(|noise| noise.is_noise(&line)) {
+                continue;
+            }
+            let mut line = apply_rewrites(&context.cli.rewrites, line);
+            if context.cli.line_numbers {
+                line = format!("{:04} | {}", line_number, line);
+            }
+            if control.streaming_paused() {
+                control.withheld_lines.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            if !quiet_mode
+                && tx
+                    .send(StreamMessage::Output { line, read_at })
+                    .await
+                    .is_err()
+            {
                 break; // Receiver has been dropped
             }
         }
+        if let (Some(bytes), false) = (binary_bytes, quiet_mode) {
+            let notice = binary_notice(stream, bytes, &context.cli);
+            let _ = tx.send(StreamMessage::Line(notice)).await;
+        }
     })
 }
+
+/// Reads up to and including the next newline like `read_until`, copying each
+/// chunk to `out` as soon as it arrives so partial lines and binary data pass
+/// through unchanged.
+async fn read_until_passthrough<R, W>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    out: &mut W,
+    echo: bool,
+) -> std::io::Result<usize>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut total = 0;
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok(total);
+        }
+        let (chunk, found_newline) = match available.iter().position(|&b| b == b'\n') {
+            Some(i) => (&available[..=i], true),
+            None => (available, false),
+        };
+        if echo {
+            out.write_all(chunk).await?;
+            out.flush().await?;
+        }
+        buf.extend_from_slice(chunk);
+        let len = chunk.len();
+        reader.consume(len);
+        total += len;
+        if found_newline {
+            return Ok(total);
+        }
+    }
+}
+
+/// Decodes one raw line of child output into UTF-8, stripping the line terminator.
+pub fn decode_line(bytes: &[u8], encoding: InputEncoding) -> String {
+    let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    match encoding {
+        InputEncoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+        InputEncoding::Latin1 => WINDOWS_1252
+            .decode_without_bom_handling(bytes)
+            .0
+            .into_owned(),
+        InputEncoding::ShiftJis => SHIFT_JIS.decode_without_bom_handling(bytes).0.into_owned(),
+        InputEncoding::Auto => match std::str::from_utf8(bytes) {
+            Ok(line) => line.to_string(),
+            Err(_) => WINDOWS_1252
+                .decode_without_bom_handling(bytes)
+                .0
+                .into_owned(),
+        },
+    }
+}
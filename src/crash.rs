@@ -0,0 +1,108 @@
+//! Describes a command killed by a signal, with core dump hints when it crashed.
+
+use std::fs;
+
+/// How many lines of stderr a crash report quotes.
+pub const STDERR_TAIL_LINES: usize = 10;
+
+/// The conventional name of `signal`, e.g. `SIGSEGV`.
+pub fn signal_name(signal: i32) -> String {
+    let name = match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGSYS => "SIGSYS",
+        _ => return format!("signal {}", signal),
+    };
+    name.to_string()
+}
+
+/// What went wrong for the signals a program raises on itself when it crashes,
+/// as opposed to being told to stop.
+pub fn crash_kind(signal: i32) -> Option<&'static str> {
+    match signal {
+        libc::SIGSEGV => Some("segmentation fault"),
+        libc::SIGABRT => Some("aborted"),
+        libc::SIGBUS => Some("bus error"),
+        libc::SIGFPE => Some("arithmetic error"),
+        libc::SIGILL => Some("illegal instruction"),
+        libc::SIGTRAP => Some("trace trap"),
+        libc::SIGSYS => Some("bad system call"),
+        _ => None,
+    }
+}
+
+/// Where the core went, or why there isn't one.
+pub fn core_dump_hint(core_dumped: bool) -> String {
+    if !core_dumped {
+        return match core_size_limit() {
+            Some(0) => {
+                "no core dump (core size limit is 0; `ulimit -c unlimited` keeps one)".to_string()
+            }
+            _ => "no core dump".to_string(),
+        };
+    }
+    let pattern = fs::read_to_string("/proc/sys/kernel/core_pattern").unwrap_or_default();
+    let pattern = pattern.trim();
+    if let Some(handler) = pattern.strip_prefix('|') {
+        let program = handler.split_whitespace().next().unwrap_or(handler);
+        format!("core dumped, handed to `{}`", program)
+    } else if pattern.is_empty() || !pattern.starts_with('/') {
+        format!(
+            "core dumped as `{}` in the command's working directory",
+            if pattern.is_empty() { "core" } else { pattern }
+        )
+    } else {
+        format!("core dumped to `{}`", pattern)
+    }
+}
+
+/// The soft `RLIMIT_CORE` the command inherited, in bytes; `None` if unlimited or unknown.
+fn core_size_limit() -> Option<libc::rlim_t> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut limit) } != 0
+        || limit.rlim_cur == libc::RLIM_INFINITY
+    {
+        return None;
+    }
+    Some(limit.rlim_cur)
+}
+
+/// The final message for a command killed by `signal`.
+///
+/// Crashes name what went wrong, where the core dump is, and quote the last
+/// lines the command wrote to stderr.
+pub fn signal_message(signal: i32, core_dumped: bool, stderr_tail: &[String]) -> String {
+    let name = signal_name(signal);
+    let Some(kind) = crash_kind(signal) else {
+        return format!("❌ Command was terminated by {}.", name);
+    };
+    let mut message = format!(
+        "💥 Command crashed with {} ({}), {}.",
+        name,
+        kind,
+        core_dump_hint(core_dumped)
+    );
+    if !stderr_tail.is_empty() {
+        message.push_str(&format!(
+            "\nLast error output:\n```\n{}\n```",
+            stderr_tail.join("\n")
+        ));
+    }
+    message
+}
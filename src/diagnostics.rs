@@ -0,0 +1,97 @@
+use regex::Regex;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// The `file:line[:column]: [level:] message` form used by compilers and most
+/// linters (gcc, clang, rustc `--error-format short`, eslint `-f unix`, flake8,
+/// shellcheck `-f gcc`, ...). As with links, the file needs an extension.
+static DIAGNOSTIC: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:\./)?((?:/?[\w.-]+/)*[\w-][\w.-]*\.[A-Za-z0-9]+):(\d+)(?::(\d+))?:\s*(?:((?i:fatal error|error|warning|note|info))\b:?\s*)?(.+)$",
+    )
+    .unwrap()
+});
+
+/// How serious a diagnostic is, in SARIF's terms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+    Note,
+}
+
+impl DiagnosticLevel {
+    fn sarif(self) -> &'static str {
+        match self {
+            DiagnosticLevel::Error => "error",
+            DiagnosticLevel::Warning => "warning",
+            DiagnosticLevel::Note => "note",
+        }
+    }
+}
+
+/// One finding reported by a wrapped linter or compiler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub path: String,
+    pub line: u64,
+    pub column: Option<u64>,
+    pub level: DiagnosticLevel,
+    pub message: String,
+}
+
+/// Parses an output line as a diagnostic. Lines without a level are warnings.
+pub fn parse_diagnostic(line: &str) -> Option<Diagnostic> {
+    let caps = DIAGNOSTIC.captures(line.trim_end())?;
+    let level = match caps.get(4).map(|m| m.as_str().to_ascii_lowercase()) {
+        Some(level) if level.ends_with("error") => DiagnosticLevel::Error,
+        Some(level) if level == "note" || level == "info" => DiagnosticLevel::Note,
+        _ => DiagnosticLevel::Warning,
+    };
+    Some(Diagnostic {
+        path: caps[1].to_string(),
+        line: caps[2].parse().ok()?,
+        column: caps.get(3).and_then(|m| m.as_str().parse().ok()),
+        level,
+        message: caps[5].to_string(),
+    })
+}
+
+/// A SARIF 2.1.0 log with one run of `tool` holding `diagnostics`. Absolute
+/// paths under `root` are made relative, as code-scanning UIs expect.
+pub fn sarif_log(tool: &str, diagnostics: &[Diagnostic], root: Option<&Path>) -> Value {
+    let results: Vec<Value> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let uri = root
+                .and_then(|root| Path::new(&diagnostic.path).strip_prefix(root).ok())
+                .map_or_else(
+                    || diagnostic.path.clone(),
+                    |path| path.to_string_lossy().into_owned(),
+                );
+            let mut region = json!({ "startLine": diagnostic.line });
+            if let Some(column) = diagnostic.column {
+                region["startColumn"] = column.into();
+            }
+            json!({
+                "level": diagnostic.level.sarif(),
+                "message": { "text": diagnostic.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": uri },
+                        "region": region,
+                    }
+                }],
+            })
+        })
+        .collect();
+    json!({
+        "version": "2.1.0",
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "runs": [{
+            "tool": { "driver": { "name": tool } },
+            "results": results,
+        }],
+    })
+}
@@ -0,0 +1,502 @@
+use crate::app::{format_with_title, AppContext};
+use crate::cli::{parse_duration, Cli};
+use crate::error::AppError;
+use crate::files;
+use crate::history::{HistoryEntry, HistoryStore};
+use crate::report::unix_millis;
+use crate::units::Units;
+use crate::webhook::send_message;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How many runs and jobs the "slowest" and "top failures" sections list.
+const TOP: usize = 5;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Parses a look-back period: `7d` or `2w`, or anything [`parse_duration`] accepts.
+pub fn parse_period(s: &str) -> Result<Duration, String> {
+    let days = |n: &str, per: u64| {
+        n.parse::<u64>()
+            .map(|n| Duration::from_secs(n * per * DAY))
+            .map_err(|_| format!("invalid period '{}'", s))
+    };
+    if let Some(n) = s.strip_suffix('d') {
+        days(n, 1)
+    } else if let Some(n) = s.strip_suffix('w') {
+        days(n, 7)
+    } else {
+        parse_duration(s)
+    }
+}
+
+/// Writes whole days as `7d`, anything else the way `units` writes durations.
+pub(crate) fn describe_period(period: Duration, units: Units) -> String {
+    let secs = period.as_secs();
+    if secs > 0 && secs.is_multiple_of(DAY) {
+        format!("{}d", secs / DAY)
+    } else {
+        units.duration(period)
+    }
+}
+
+/// Success rate and timings of one job over the digest period.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JobStats {
+    pub job: String,
+    pub runs: usize,
+    pub failed: usize,
+    pub total_duration: Duration,
+    pub last_failure_code: Option<i32>,
+}
+
+impl JobStats {
+    fn success_percent(&self) -> usize {
+        (self.runs - self.failed) * 100 / self.runs
+    }
+}
+
+/// Aggregates of the run history over a period, as sent by `shell_hook digest`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Digest {
+    pub period: Duration,
+    /// Per job, ordered by name.
+    pub jobs: Vec<JobStats>,
+    /// The longest runs, longest first.
+    pub slowest: Vec<(String, Duration)>,
+}
+
+impl Digest {
+    /// Summarizes the entries that finished within `period` before `now_ms`.
+    pub fn new(entries: &[HistoryEntry], period: Duration, now_ms: u64) -> Self {
+        let cutoff = now_ms.saturating_sub(period.as_millis() as u64);
+        let recent: Vec<&HistoryEntry> = entries
+            .iter()
+            .filter(|entry| entry.finished_at_ms >= cutoff)
+            .collect();
+        let duration = |entry: &HistoryEntry| {
+            Duration::from_millis(entry.finished_at_ms.saturating_sub(entry.started_at_ms))
+        };
+
+        let mut jobs: BTreeMap<&str, JobStats> = BTreeMap::new();
+        for entry in &recent {
+            let stats = jobs.entry(&entry.job).or_insert_with(|| JobStats {
+                job: entry.job.clone(),
+                runs: 0,
+                failed: 0,
+                total_duration: Duration::ZERO,
+                last_failure_code: None,
+            });
+            stats.runs += 1;
+            stats.total_duration += duration(entry);
+            if entry.exit_code != 0 {
+                stats.failed += 1;
+                stats.last_failure_code = Some(entry.exit_code);
+            }
+        }
+
+        let mut slowest: Vec<(String, Duration)> = recent
+            .iter()
+            .map(|entry| (entry.job.clone(), duration(entry)))
+            .collect();
+        slowest.sort_by_key(|&(_, duration)| std::cmp::Reverse(duration));
+        slowest.truncate(TOP);
+
+        Self {
+            period,
+            jobs: jobs.into_values().collect(),
+            slowest,
+        }
+    }
+
+    fn totals(&self) -> (usize, usize) {
+        let runs = self.jobs.iter().map(|job| job.runs).sum();
+        let failed = self.jobs.iter().map(|job| job.failed).sum();
+        (runs, failed)
+    }
+
+    /// Jobs with failures, most failures first.
+    fn top_failures(&self) -> Vec<&JobStats> {
+        let mut failing: Vec<&JobStats> = self.jobs.iter().filter(|job| job.failed > 0).collect();
+        failing.sort_by(|a, b| b.failed.cmp(&a.failed).then_with(|| a.job.cmp(&b.job)));
+        failing.truncate(TOP);
+        failing
+    }
+
+    fn headline(&self, units: Units) -> String {
+        let (runs, failed) = self.totals();
+        if runs == 0 {
+            return format!(
+                "No runs recorded in the last {}. Record runs with --history.",
+                describe_period(self.period, units)
+            );
+        }
+        format!(
+            "{} of {} in the last {}, {}% succeeded",
+            units.count(runs as u64, "run"),
+            units.count(self.jobs.len() as u64, "job"),
+            describe_period(self.period, units),
+            (runs - failed) * 100 / runs
+        )
+    }
+
+    /// The chat message: a headline, then a section per aggregate.
+    pub fn to_message(&self, units: Units) -> String {
+        let mut message = format!("📊 Digest: {}", self.headline(units));
+        if self.jobs.is_empty() {
+            return message;
+        }
+        message.push_str("\nSuccess rates:");
+        for job in &self.jobs {
+            let icon = if job.failed == 0 { "✅" } else { "⚠️" };
+            let _ = write!(
+                message,
+                "\n{} {}: {}/{} ({}%), avg {}",
+                icon,
+                job.job,
+                job.runs - job.failed,
+                job.runs,
+                job.success_percent(),
+                units.duration(job.total_duration / job.runs as u32)
+            );
+        }
+        message.push_str("\nSlowest runs:");
+        for (job, duration) in &self.slowest {
+            let _ = write!(message, "\n• {}: {}", job, units.duration(*duration));
+        }
+        let failures = self.top_failures();
+        if !failures.is_empty() {
+            message.push_str("\nTop failures:");
+            for job in failures {
+                let _ = write!(
+                    message,
+                    "\n• {}: {}, last exit code {}",
+                    job.job,
+                    units.count(job.failed as u64, "failure"),
+                    job.last_failure_code.unwrap_or_default()
+                );
+            }
+        }
+        message
+    }
+
+    /// The same digest as a Markdown document with tables.
+    pub fn to_markdown(&self, units: Units) -> String {
+        let mut markdown = format!("# shell_hook digest\n\n{}.\n", self.headline(units));
+        if self.jobs.is_empty() {
+            return markdown;
+        }
+        markdown.push_str(
+            "\n## Success rates\n\n| Job | Runs | Failed | Succeeded | Avg duration |\n|---|---|---|---|---|\n",
+        );
+        for job in &self.jobs {
+            let _ = writeln!(
+                markdown,
+                "| {} | {} | {} | {}% | {} |",
+                escape_cell(&job.job),
+                job.runs,
+                job.failed,
+                job.success_percent(),
+                units.duration(job.total_duration / job.runs as u32)
+            );
+        }
+        markdown.push_str("\n## Slowest runs\n\n| Job | Duration |\n|---|---|\n");
+        for (job, duration) in &self.slowest {
+            let _ = writeln!(
+                markdown,
+                "| {} | {} |",
+                escape_cell(job),
+                units.duration(*duration)
+            );
+        }
+        let failures = self.top_failures();
+        if !failures.is_empty() {
+            markdown.push_str(
+                "\n## Top failures\n\n| Job | Failures | Last exit code |\n|---|---|---|\n",
+            );
+            for job in failures {
+                let _ = writeln!(
+                    markdown,
+                    "| {} | {} | {} |",
+                    escape_cell(&job.job),
+                    job.failed,
+                    job.last_failure_code.unwrap_or_default()
+                );
+            }
+        }
+        markdown
+    }
+}
+
+/// The windows `digest --slo` reports on when none are given.
+pub const DEFAULT_SLO_WINDOWS: [Duration; 2] =
+    [Duration::from_secs(7 * DAY), Duration::from_secs(30 * DAY)];
+
+/// A success percentage one job, or every job without its own, should reach.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SloTarget {
+    pub job: Option<String>,
+    pub percent: f64,
+}
+
+/// Parses `--target`: `PERCENT` for every job, or `JOB=PERCENT`.
+pub fn parse_slo_target(s: &str) -> Result<SloTarget, String> {
+    let (job, percent) = match s.rsplit_once('=') {
+        Some((job, percent)) => (Some(job.to_string()), percent),
+        None => (None, s),
+    };
+    let percent = percent
+        .trim_end_matches('%')
+        .parse::<f64>()
+        .ok()
+        .filter(|p| (0.0..=100.0).contains(p))
+        .ok_or_else(|| format!("invalid target '{}': expected a percentage such as 99.5", s))?;
+    Ok(SloTarget { job, percent })
+}
+
+/// One job's success over each window of an [`SloReport`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct JobAvailability {
+    pub job: String,
+    /// `(succeeded, runs)` per window; `runs` is 0 when the job did not run in it.
+    pub windows: Vec<(usize, usize)>,
+    pub target: Option<f64>,
+}
+
+impl JobAvailability {
+    /// Whether the job fell short of its target in any window it ran in.
+    pub fn below_target(&self) -> bool {
+        let Some(target) = self.target else {
+            return false;
+        };
+        self.windows
+            .iter()
+            .any(|&(succeeded, runs)| runs > 0 && (succeeded as f64) * 100.0 < target * runs as f64)
+    }
+}
+
+/// Per-job success percentages over several windows, as sent by `shell_hook digest --slo`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SloReport {
+    pub windows: Vec<Duration>,
+    /// Every job that ran in the longest window, ordered by name.
+    pub jobs: Vec<JobAvailability>,
+}
+
+impl SloReport {
+    pub fn new(
+        entries: &[HistoryEntry],
+        windows: &[Duration],
+        targets: &[SloTarget],
+        now_ms: u64,
+    ) -> Self {
+        let cutoffs: Vec<u64> = windows
+            .iter()
+            .map(|window| now_ms.saturating_sub(window.as_millis() as u64))
+            .collect();
+        let longest = cutoffs.iter().copied().min().unwrap_or(now_ms);
+        let target_for = |job: &str| {
+            let target = |wanted: Option<&str>| {
+                targets
+                    .iter()
+                    .rfind(|target| target.job.as_deref() == wanted)
+                    .map(|target| target.percent)
+            };
+            target(Some(job)).or_else(|| target(None))
+        };
+
+        let mut jobs: BTreeMap<&str, JobAvailability> = BTreeMap::new();
+        for entry in entries
+            .iter()
+            .filter(|entry| entry.finished_at_ms >= longest)
+        {
+            let job = jobs.entry(&entry.job).or_insert_with(|| JobAvailability {
+                job: entry.job.clone(),
+                windows: vec![(0, 0); windows.len()],
+                target: target_for(&entry.job),
+            });
+            for (counts, &cutoff) in job.windows.iter_mut().zip(&cutoffs) {
+                if entry.finished_at_ms >= cutoff {
+                    counts.1 += 1;
+                    if entry.exit_code == 0 {
+                        counts.0 += 1;
+                    }
+                }
+            }
+        }
+
+        Self {
+            windows: windows.to_vec(),
+            jobs: jobs.into_values().collect(),
+        }
+    }
+
+    pub fn below_target(&self) -> Vec<&JobAvailability> {
+        self.jobs.iter().filter(|job| job.below_target()).collect()
+    }
+
+    fn describe_windows(&self, units: Units) -> String {
+        self.windows
+            .iter()
+            .map(|&window| describe_period(window, units))
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+
+    /// The chat message: a line per job with its success in each window, led by
+    /// a warning naming the jobs below target.
+    pub fn to_message(&self, units: Units) -> String {
+        let mut message = format!("📈 Availability over {}:", self.describe_windows(units));
+        if self.jobs.is_empty() {
+            message.push_str(" no runs recorded. Record runs with --history.");
+            return message;
+        }
+        let below = self.below_target();
+        if !below.is_empty() {
+            let names: Vec<&str> = below.iter().map(|job| job.job.as_str()).collect();
+            let _ = write!(message, "\n⚠️ Below target: {}", names.join(", "));
+        }
+        for job in &self.jobs {
+            let icon = match job.target {
+                Some(_) if job.below_target() => "🔴",
+                Some(_) => "✅",
+                None => "•",
+            };
+            let percents: Vec<String> = job
+                .windows
+                .iter()
+                .map(|&(succeeded, runs)| availability(succeeded, runs))
+                .collect();
+            let _ = write!(message, "\n{} {}: {}", icon, job.job, percents.join(" / "));
+            if let Some(target) = job.target {
+                let _ = write!(message, " (target {}%)", target);
+            }
+        }
+        message
+    }
+
+    /// The same report as a Markdown table with a column per window.
+    pub fn to_markdown(&self, units: Units) -> String {
+        let mut markdown = format!(
+            "# shell_hook availability\n\nSuccess rate per job over {}.\n",
+            self.describe_windows(units)
+        );
+        if self.jobs.is_empty() {
+            return markdown;
+        }
+        markdown.push_str("\n| Job |");
+        for &window in &self.windows {
+            let _ = write!(markdown, " {} |", describe_period(window, units));
+        }
+        markdown.push_str(" Target |\n|---|");
+        markdown.push_str(&"---|".repeat(self.windows.len() + 1));
+        markdown.push('\n');
+        for job in &self.jobs {
+            let _ = write!(markdown, "| {} |", escape_cell(&job.job));
+            for &(succeeded, runs) in &job.windows {
+                let _ = write!(markdown, " {} |", availability(succeeded, runs));
+            }
+            match job.target {
+                Some(target) if job.below_target() => {
+                    let _ = writeln!(markdown, " {}% (missed) |", target);
+                }
+                Some(target) => {
+                    let _ = writeln!(markdown, " {}% |", target);
+                }
+                None => markdown.push_str(" - |\n"),
+            }
+        }
+        markdown
+    }
+}
+
+/// Writes a success rate to one decimal, rounded down so a target is never shown
+/// as met when it was not; `-` when there were no runs.
+fn availability(succeeded: usize, runs: usize) -> String {
+    if runs == 0 {
+        return "-".to_string();
+    }
+    let permille = succeeded * 1000 / runs;
+    match permille % 10 {
+        0 => format!("{}%", permille / 10),
+        tenths => format!("{}.{}%", permille / 10, tenths),
+    }
+}
+
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// Builds the digest from the history, then prints it, writes it as Markdown
+/// and/or sends it to the webhook.
+pub async fn run_digest(
+    cli: &Arc<Cli>,
+    since: Duration,
+    post: bool,
+    markdown: Option<&Path>,
+) -> Result<i32, AppError> {
+    let entries = HistoryStore::from_cli(cli).load()?;
+    let digest = Digest::new(&entries, since, unix_millis());
+    publish(
+        cli,
+        &digest.to_message(cli.units),
+        markdown.map(|path| (path, digest.to_markdown(cli.units))),
+        post,
+    )
+    .await
+}
+
+/// Like [`run_digest`] for `digest --slo`. Exits with 1 when a job is below its target.
+pub async fn run_slo_report(
+    cli: &Arc<Cli>,
+    windows: &[Duration],
+    targets: &[SloTarget],
+    post: bool,
+    markdown: Option<&Path>,
+) -> Result<i32, AppError> {
+    let windows = if windows.is_empty() {
+        &DEFAULT_SLO_WINDOWS[..]
+    } else {
+        windows
+    };
+    let entries = HistoryStore::from_cli(cli).load()?;
+    let report = SloReport::new(&entries, windows, targets, unix_millis());
+    let code = publish(
+        cli,
+        &report.to_message(cli.units),
+        markdown.map(|path| (path, report.to_markdown(cli.units))),
+        post,
+    )
+    .await?;
+    Ok(if report.below_target().is_empty() {
+        code
+    } else {
+        1
+    })
+}
+
+/// Prints `message`, or writes the Markdown version to its path instead, and sends
+/// `message` to the webhook with `post`.
+async fn publish(
+    cli: &Arc<Cli>,
+    message: &str,
+    markdown: Option<(&Path, String)>,
+    post: bool,
+) -> Result<i32, AppError> {
+    let context = post.then(|| AppContext::new(cli.clone())).transpose()?;
+    let message = format_with_title(cli, message);
+    match markdown {
+        Some((path, markdown)) => files::write(path, markdown)?,
+        None => println!("{}", message),
+    }
+    if let Some(context) = context.map(Arc::new) {
+        if let Err(e) = send_message(&context, &message).await {
+            context.warn(format!("Failed to send digest: {}", e)).await;
+            return Ok(1);
+        }
+    }
+    Ok(0)
+}
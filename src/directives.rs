@@ -0,0 +1,53 @@
+//! Control lines a wrapped command prints to steer streaming, e.g. `::shell_hook flush::`.
+
+use crate::message::StreamMessage;
+
+const PREFIX: &str = "::shell_hook ";
+const SUFFIX: &str = "::";
+
+/// What a control line asks the streaming layer to do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Directive {
+    /// `::shell_hook flush::` sends the lines buffered so far.
+    Flush,
+    /// `::shell_hook section TITLE::` starts a new message headed by TITLE.
+    Section(String),
+    /// `::shell_hook notice TEXT::` sends TEXT as a message of its own.
+    Notice(String),
+}
+
+/// Parses a control line. Anything else, including unknown `::shell_hook ...::`
+/// lines, is ordinary output.
+pub fn parse_directive(line: &str) -> Option<Directive> {
+    let body = line
+        .trim()
+        .strip_prefix(PREFIX)?
+        .strip_suffix(SUFFIX)?
+        .trim();
+    let (verb, arg) = body.split_once(' ').unwrap_or((body, ""));
+    let arg = arg.trim();
+    match (verb, arg) {
+        ("flush", "") => Some(Directive::Flush),
+        ("section", title) if !title.is_empty() => Some(Directive::Section(title.to_string())),
+        ("notice", text) if !text.is_empty() => Some(Directive::Notice(text.to_string())),
+        _ => None,
+    }
+}
+
+impl Directive {
+    /// The messages that carry out the directive in the sender.
+    pub fn messages(&self) -> Vec<StreamMessage> {
+        match self {
+            Directive::Flush => vec![StreamMessage::Flush],
+            Directive::Section(title) => vec![
+                StreamMessage::Flush,
+                StreamMessage::Line(format!("▶️ {}", title)),
+            ],
+            Directive::Notice(text) => vec![
+                StreamMessage::Flush,
+                StreamMessage::Line(format!("📌 {}", text)),
+                StreamMessage::Flush,
+            ],
+        }
+    }
+}
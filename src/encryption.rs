@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The `alg` value of every envelope this version produces.
+pub const ALGORITHM: &str = "chacha20-poly1305";
+
+/// A 256-bit symmetric key for `--encrypt-key`, given as standard base64.
+///
+/// Generate one with `head -c 32 /dev/urandom | base64`.
+#[derive(Clone)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    fn aead_key(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &self.0).expect("key is 32 bytes"))
+    }
+
+    /// Encrypts a rendered payload into the JSON envelope that is delivered instead.
+    pub fn seal(&self, payload: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("failed to generate a nonce"))?;
+
+        let mut ciphertext = payload.to_vec();
+        self.aead_key()
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(ALGORITHM),
+                &mut ciphertext,
+            )
+            .map_err(|_| anyhow!("failed to encrypt payload"))?;
+
+        Ok(serde_json::to_vec(&Envelope {
+            alg: ALGORITHM.to_string(),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        })?)
+    }
+
+    /// Decrypts an envelope produced by [`seal`](Self::seal), returning the original payload.
+    pub fn open(&self, envelope: &[u8]) -> Result<Vec<u8>> {
+        let envelope: Envelope = serde_json::from_slice(envelope)?;
+        if envelope.alg != ALGORITHM {
+            return Err(anyhow!("unsupported algorithm '{}'", envelope.alg));
+        }
+        let nonce: [u8; NONCE_LEN] = STANDARD
+            .decode(&envelope.nonce)?
+            .try_into()
+            .map_err(|_| anyhow!("nonce must be {} bytes", NONCE_LEN))?;
+        let mut ciphertext = STANDARD.decode(&envelope.ciphertext)?;
+
+        let plaintext = self
+            .aead_key()
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(ALGORITHM),
+                &mut ciphertext,
+            )
+            .map_err(|_| anyhow!("payload failed authentication"))?;
+        Ok(plaintext.to_vec())
+    }
+}
+
+impl FromStr for EncryptionKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = STANDARD
+            .decode(s.trim())
+            .map_err(|e| format!("encryption key is not valid base64: {}", e))?;
+        let key = bytes.try_into().map_err(|bytes: Vec<u8>| {
+            format!("encryption key must be 32 bytes, got {}", bytes.len())
+        })?;
+        Ok(Self(key))
+    }
+}
+
+// Keep the key out of `--help` defaults and debug output.
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+/// The JSON body delivered when `--encrypt-key` is set.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    alg: String,
+    nonce: String,
+    ciphertext: String,
+}
@@ -1,4 +1,5 @@
 use crate::message::StreamMessage;
+#[cfg(feature = "readline")]
 use rustyline::error::ReadlineError;
 
 #[derive(Debug, thiserror::Error)]
@@ -9,6 +10,21 @@ pub enum AppError {
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
+    #[error("No run with ID '{0}'. Use `status` to list known runs.")]
+    UnknownRun(String),
+
+    #[error("No open group with ID '{0}'. Start one with `group start`.")]
+    UnknownGroup(String),
+
+    #[error("No group to end: set SHELL_HOOK_GROUP or --group.")]
+    MissingGroup,
+
+    #[error("Invalid maintenance calendar: {0}")]
+    InvalidCalendar(String),
+
+    #[error("Invalid client certificate: {0}")]
+    InvalidClientCertificate(String),
+
     #[error("Failed to send message to the channel")]
     MpscSendError(#[from] tokio::sync::mpsc::error::SendError<StreamMessage>),
 
@@ -18,6 +34,7 @@ pub enum AppError {
     #[error("Webhook request failed: {0}")]
     WebhookError(#[from] reqwest::Error),
 
+    #[cfg(feature = "readline")]
     #[error("Readline error: {0}")]
     ReadlineError(#[from] ReadlineError),
 
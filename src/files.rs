@@ -0,0 +1,71 @@
+//! Creates shell_hook's own files privately: logs, history and reports can hold
+//! secrets from command output, so they default to `0600` and their directories to `0700`.
+
+use std::fs::{DirBuilder, File, OpenOptions};
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// The permission bits taken away from created files and directories, from `--umask`.
+static UMASK: AtomicU32 = AtomicU32::new(DEFAULT_UMASK);
+
+pub const DEFAULT_UMASK: u32 = 0o077;
+
+/// Parses an octal umask such as `077` or `0027`.
+pub fn parse_umask(s: &str) -> Result<u32, String> {
+    u32::from_str_radix(s, 8)
+        .ok()
+        .filter(|umask| *umask <= 0o777)
+        .ok_or_else(|| format!("invalid umask '{}': expected octal such as 077", s))
+}
+
+/// Applies to files and directories created from now on. The command's own umask is unaffected.
+pub fn set_umask(umask: u32) {
+    UMASK.store(umask & 0o777, Ordering::Relaxed);
+}
+
+fn file_mode() -> u32 {
+    0o666 & !UMASK.load(Ordering::Relaxed)
+}
+
+fn dir_mode() -> u32 {
+    0o777 & !UMASK.load(Ordering::Relaxed)
+}
+
+/// Like [`fs::create_dir_all`]; directories that already exist keep their mode.
+pub fn create_dir_all(path: &Path) -> io::Result<()> {
+    DirBuilder::new()
+        .recursive(true)
+        .mode(dir_mode())
+        .create(path)
+}
+
+/// Like [`fs::create_dir`]: fails if `path` already exists.
+pub fn create_dir(path: &Path) -> io::Result<()> {
+    DirBuilder::new().mode(dir_mode()).create(path)
+}
+
+/// Like [`File::create`]; a file that already exists keeps its mode.
+pub fn create(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(file_mode())
+        .open(path)
+}
+
+/// Like [`fs::write`].
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    io::Write::write_all(&mut create(path)?, contents.as_ref())
+}
+
+/// Opens `path` for appending, creating it if needed.
+pub fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .mode(file_mode())
+        .open(path)
+}
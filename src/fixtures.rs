@@ -0,0 +1,93 @@
+use crate::app::{run_controlled_command, AppContext};
+use crate::cli::{Cli, Command, WebhookFormat};
+use crate::command::RunControl;
+use crate::error::AppError;
+use crate::files;
+use crate::transport::Transport;
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::{Parser, ValueEnum};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The runs fixtures are recorded from, and the name of their final event.
+const SAMPLE_RUNS: &[(&str, &str)] = &[
+    (
+        "succeeded",
+        "echo 'Compiling shell_hook v0.2.0'; echo 'Finished release [optimized] target(s)'",
+    ),
+    ("failed", "echo 'error: 2 tests failed' >&2; exit 1"),
+    ("killed", "kill -9 $$"),
+];
+
+/// Keeps payloads instead of delivering them.
+#[derive(Clone, Default)]
+struct CaptureTransport(Arc<Mutex<Vec<Vec<u8>>>>);
+
+#[async_trait]
+impl Transport for CaptureTransport {
+    async fn deliver(&self, body: &[u8]) -> Result<()> {
+        self.0.lock().unwrap().push(body.to_vec());
+        Ok(())
+    }
+}
+
+/// Writes `<out>/<format>/<event>.json` for every webhook format, recorded by
+/// running sample commands through the real pipeline.
+///
+/// Returns the paths written.
+pub async fn write_fixtures(out: &Path) -> Result<Vec<PathBuf>, AppError> {
+    let mut written = Vec::new();
+    for format in WebhookFormat::value_variants() {
+        let format_name = format.to_possible_value().unwrap().get_name().to_string();
+        let dir = out.join(&format_name);
+        files::create_dir_all(&dir)?;
+
+        for (final_event, command) in SAMPLE_RUNS {
+            let payloads = record_run(&format_name, command).await?;
+            let [start, output @ .., last] = payloads.as_slice() else {
+                continue;
+            };
+            let mut events = vec![(*final_event, last)];
+            if *final_event == "succeeded" {
+                events.push(("started", start));
+                events.extend(output.first().map(|batch| ("output", batch)));
+            }
+            for (event, payload) in events {
+                let path = dir.join(format!("{}.json", event));
+                let value: serde_json::Value =
+                    serde_json::from_slice(payload).map_err(|e| AppError::Io(e.into()))?;
+                files::write(&path, serde_json::to_vec_pretty(&value).unwrap())?;
+                written.push(path);
+            }
+        }
+    }
+    Ok(written)
+}
+
+async fn record_run(format: &str, command: &str) -> Result<Vec<Vec<u8>>, AppError> {
+    let cli = Cli::try_parse_from([
+        "shell_hook",
+        "--dry-run",
+        "--status-to-webhook-only",
+        "--format",
+        format,
+        "run",
+        "--",
+        command,
+    ])?;
+    let mut context = AppContext::new(Arc::new(cli))?;
+    let capture = CaptureTransport::default();
+    context.transport = Box::new(capture.clone());
+    let context = Arc::new(context);
+
+    let Command::Run(run_args) = &context.cli.command else {
+        unreachable!("parsed as a run above");
+    };
+    let control = Arc::new(RunControl::default());
+    control.set_local_echo(false);
+    run_controlled_command(&context, run_args, control).await?;
+
+    let payloads = std::mem::take(&mut *capture.0.lock().unwrap());
+    Ok(payloads)
+}
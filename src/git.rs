@@ -0,0 +1,57 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Runs `git` in `dir` and returns its trimmed stdout, or `None` if it fails.
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The full SHA of `HEAD` when `dir` is inside a git work tree.
+pub fn head_commit(dir: &Path) -> Option<String> {
+    git(dir, &["rev-parse", "HEAD"])
+}
+
+/// Where in the history a run happened, as shown by `--git-context`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitContext {
+    /// `None` on a detached `HEAD`.
+    pub branch: Option<String>,
+    pub short_sha: String,
+    pub dirty: bool,
+}
+
+impl GitContext {
+    /// Returns `None` when `dir` is not inside a git work tree.
+    pub fn detect(dir: &Path) -> Option<Self> {
+        let short_sha = git(dir, &["rev-parse", "--short", "HEAD"])?;
+        Some(Self {
+            branch: git(dir, &["symbolic-ref", "--short", "-q", "HEAD"]),
+            short_sha,
+            dirty: git(dir, &["status", "--porcelain"]).is_some_and(|s| !s.is_empty()),
+        })
+    }
+}
+
+impl std::fmt::Display for GitContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}@{}",
+            self.branch.as_deref().unwrap_or("HEAD"),
+            self.short_sha
+        )?;
+        if self.dirty {
+            write!(f, ", dirty")?;
+        }
+        Ok(())
+    }
+}
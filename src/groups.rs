@@ -0,0 +1,169 @@
+use crate::app::{format_with_title, AppContext};
+use crate::cli::Cli;
+use crate::error::AppError;
+use crate::files;
+use crate::runs::{default_state_dir, new_run_id, unix_now};
+use crate::units::Units;
+use crate::webhook::send_message;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const GROUP_FILE: &str = "group.json";
+/// One JSON line per finished run, appended so concurrent members don't overwrite each other.
+const RUNS_FILE: &str = "runs.jsonl";
+
+/// An umbrella over separate invocations, started with `group start`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GroupRecord {
+    pub id: String,
+    pub name: String,
+    pub started_at: u64,
+}
+
+/// The outcome of one run inside a group.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GroupRun {
+    pub command: String,
+    pub exit_code: i32,
+    pub started_at: u64,
+    pub finished_at: u64,
+}
+
+/// On-disk directory of open groups, one subdirectory per group ID.
+pub struct GroupStore {
+    root: PathBuf,
+}
+
+impl GroupStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Uses the same state directory as detached runs.
+    pub fn from_cli(cli: &Cli) -> Self {
+        let base = cli.state_dir.clone().unwrap_or_else(default_state_dir);
+        Self::new(base.join("groups"))
+    }
+
+    /// The directory holding one subdirectory per open group.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn group_dir(&self, id: &str) -> PathBuf {
+        self.root.join(id)
+    }
+
+    pub fn create(&self, name: &str) -> io::Result<GroupRecord> {
+        let record = GroupRecord {
+            id: new_run_id(),
+            name: name.to_string(),
+            started_at: unix_now(),
+        };
+        files::create_dir_all(&self.group_dir(&record.id))?;
+        files::write(
+            &self.group_dir(&record.id).join(GROUP_FILE),
+            serde_json::to_vec_pretty(&record)?,
+        )?;
+        Ok(record)
+    }
+
+    pub fn load(&self, id: &str) -> Result<GroupRecord, AppError> {
+        match fs::read(self.group_dir(id).join(GROUP_FILE)) {
+            Ok(json) => serde_json::from_slice(&json).map_err(|e| AppError::Io(e.into())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                Err(AppError::UnknownGroup(id.to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Adds a finished run to an open group.
+    pub fn record_run(&self, id: &str, run: &GroupRun) -> Result<(), AppError> {
+        self.load(id)?;
+        let mut line = serde_json::to_vec(run).map_err(io::Error::from)?;
+        line.push(b'\n');
+        files::append(&self.group_dir(id).join(RUNS_FILE))?.write_all(&line)?;
+        Ok(())
+    }
+
+    /// The runs recorded in a group, in the order they finished.
+    pub fn runs(&self, id: &str) -> Result<Vec<GroupRun>, AppError> {
+        let contents = match fs::read_to_string(self.group_dir(id).join(RUNS_FILE)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Closes a group, deleting its state.
+    pub fn remove(&self, id: &str) -> io::Result<()> {
+        fs::remove_dir_all(self.group_dir(id))
+    }
+}
+
+/// Starts a group, announces it and prints the `export` line that makes later
+/// invocations join it.
+pub async fn start_group(context: &Arc<AppContext>, name: &str) -> Result<i32, AppError> {
+    let group = GroupStore::from_cli(&context.cli).create(name)?;
+    let message = format_with_title(&context.cli, &format!("📦 Started group: {}", name));
+    eprintln!("{}", message);
+    if let Err(e) = send_message(context, &message).await {
+        context
+            .warn(format!("Failed to send group start: {}", e))
+            .await;
+    }
+    println!("export SHELL_HOOK_GROUP={}", group.id);
+    Ok(0)
+}
+
+/// Ends a group with one summary of every run in it.
+///
+/// Returns 1 if any run in the group failed, so scripts can act on the overall result.
+pub async fn end_group(context: &Arc<AppContext>, id: &str) -> Result<i32, AppError> {
+    let store = GroupStore::from_cli(&context.cli);
+    let group = store.load(id)?;
+    let runs = store.runs(id)?;
+
+    let message = format_with_title(
+        &context.cli,
+        &group_summary(&group, &runs, unix_now(), context.cli.units),
+    );
+    println!("{}", message);
+    if let Err(e) = send_message(context, &message).await {
+        context
+            .warn(format!("Failed to send group summary: {}", e))
+            .await;
+    }
+    store.remove(id)?;
+    Ok(i32::from(runs.iter().any(|run| run.exit_code != 0)))
+}
+
+/// The aggregate message sent by `group end`: a headline, then one line per run.
+pub fn group_summary(group: &GroupRecord, runs: &[GroupRun], now: u64, units: Units) -> String {
+    let failed = runs.iter().filter(|run| run.exit_code != 0).count();
+    let icon = if failed == 0 { "✅" } else { "❌" };
+    let mut summary = format!(
+        "{} Group {} finished: {}, {} failed, in {}",
+        icon,
+        group.name,
+        units.count(runs.len() as u64, "run"),
+        failed,
+        units.seconds(now.saturating_sub(group.started_at))
+    );
+    for run in runs {
+        let elapsed = units.seconds(run.finished_at.saturating_sub(run.started_at));
+        summary.push_str(&match run.exit_code {
+            0 => format!("\n✅ `{}` ({})", run.command, elapsed),
+            code => format!("\n❌ `{}` exited {} ({})", run.command, code, elapsed),
+        });
+    }
+    summary
+}
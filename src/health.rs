@@ -0,0 +1,188 @@
+//! `check COMMAND`: a health check run on a timer that only notifies when its state changes.
+
+use crate::app::{echo_status, format_with_title, AppContext};
+use crate::cli::shell_command_line;
+use crate::error::AppError;
+use crate::units::Units;
+use crate::webhook::send_message;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// How many lines of a failed check's output go into the alert.
+const OUTPUT_TAIL_LINES: usize = 5;
+
+/// Whether the health check is passing, as far as notifications are concerned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Health {
+    Healthy,
+    Failing,
+}
+
+/// Turns individual check results into state changes worth notifying about.
+///
+/// A failure is only reported once `threshold` checks in a row have failed, so a
+/// single flap stays quiet. The first passing check after that reports recovery.
+#[derive(Debug)]
+pub struct FlapFilter {
+    threshold: u32,
+    consecutive_failures: u32,
+    state: Health,
+}
+
+impl FlapFilter {
+    /// Starts out healthy.
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            consecutive_failures: 0,
+            state: Health::Healthy,
+        }
+    }
+
+    pub fn state(&self) -> Health {
+        self.state
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Records one check, returning the new state if it changed.
+    pub fn observe(&mut self, passed: bool) -> Option<Health> {
+        let next = if passed {
+            self.consecutive_failures = 0;
+            Health::Healthy
+        } else {
+            self.consecutive_failures += 1;
+            if self.consecutive_failures < self.threshold {
+                return None;
+            }
+            Health::Failing
+        };
+        (next != self.state).then(|| {
+            self.state = next;
+            next
+        })
+    }
+}
+
+/// The result of running the check command once.
+#[derive(Debug)]
+struct CheckResult {
+    passed: bool,
+    /// Why it failed, e.g. `exit code 7`.
+    reason: String,
+    /// The last lines the command printed.
+    output: String,
+}
+
+async fn run_check(command_line: &str, timeout: Duration, units: Units) -> CheckResult {
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command_line)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = match tokio::time::timeout(timeout, child).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => {
+            return CheckResult {
+                passed: false,
+                reason: format!("failed to start: {}", e),
+                output: String::new(),
+            }
+        }
+        Err(_) => {
+            return CheckResult {
+                passed: false,
+                reason: format!("timed out after {}", units.duration(timeout)),
+                output: String::new(),
+            }
+        }
+    };
+    let text = [output.stdout, output.stderr]
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .join("");
+    let lines: Vec<&str> = text.lines().collect();
+    let tail = lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n");
+    CheckResult {
+        passed: output.status.success(),
+        reason: match output.status.code() {
+            Some(code) => format!("exit code {}", code),
+            None => "terminated by a signal".to_string(),
+        },
+        output: tail,
+    }
+}
+
+/// Runs `command` every `interval` until `count` checks have run (or forever),
+/// notifying when it starts failing `failures` times in a row and when it recovers.
+///
+/// With `count`, returns 1 if the last check failed.
+pub async fn run_health_checks(
+    context: &Arc<AppContext>,
+    command: &[String],
+    interval: Duration,
+    failures: u32,
+    count: Option<u32>,
+) -> Result<i32, AppError> {
+    let units = context.cli.units;
+    let command_line = shell_command_line(command);
+    eprintln!(
+        "[shell_hook] Checking `{}` every {}",
+        command_line,
+        units.duration(interval)
+    );
+
+    let mut filter = FlapFilter::new(failures);
+    let mut failing_since = None;
+    let mut checks = 0;
+    loop {
+        let started = Instant::now();
+        let result = run_check(&command_line, interval, units).await;
+        checks += 1;
+        if !result.passed {
+            failing_since.get_or_insert(started);
+        }
+
+        let message = match filter.observe(result.passed) {
+            Some(Health::Failing) => {
+                let mut message = format!(
+                    "🔴 Health check failing: `{}` ({}, {} in a row)",
+                    command_line,
+                    result.reason,
+                    units.count(filter.consecutive_failures().into(), "check")
+                );
+                if !result.output.is_empty() {
+                    message.push_str(&format!("\n```\n{}\n```", result.output));
+                }
+                Some(message)
+            }
+            Some(Health::Healthy) => Some(format!(
+                "✅ Health check recovered: `{}` after {}",
+                command_line,
+                units.duration(failing_since.map_or(Duration::ZERO, |since| since.elapsed()))
+            )),
+            None => None,
+        };
+        if result.passed {
+            failing_since = None;
+        }
+        if let Some(message) = message {
+            let message = format_with_title(&context.cli, &message);
+            echo_status(&context.cli, &message, !result.passed);
+            if let Err(e) = send_message(context, &message).await {
+                context
+                    .warn(format!("Failed to send health check notice: {}", e))
+                    .await;
+            }
+        }
+
+        if count.is_some_and(|count| checks >= count) {
+            return Ok(if result.passed { 0 } else { 1 });
+        }
+        tokio::time::sleep(interval.saturating_sub(started.elapsed())).await;
+    }
+}
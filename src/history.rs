@@ -0,0 +1,185 @@
+use crate::cli::{Cli, ExportFormat, RunArgs};
+use crate::files;
+use crate::runs::default_state_dir;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// One JSON line per finished run, appended so concurrent runs don't overwrite each other.
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// The outcome of one run, recorded with `--history`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct HistoryEntry {
+    /// The run's ID, as in its report and `SHELL_HOOK_RESULT` line; empty in entries
+    /// recorded before runs had IDs.
+    #[serde(default)]
+    pub run_id: String,
+    /// What the run is grouped by in the history: its title, or its command without one.
+    pub job: String,
+    pub command: String,
+    pub exit_code: i32,
+    pub started_at_ms: u64,
+    pub finished_at_ms: u64,
+    /// Webhook delivery attempts made during the run.
+    #[serde(default)]
+    pub deliveries: usize,
+    /// How many of those attempts failed.
+    #[serde(default)]
+    pub failed_deliveries: usize,
+}
+
+impl HistoryEntry {
+    pub fn duration_ms(&self) -> u64 {
+        self.finished_at_ms.saturating_sub(self.started_at_ms)
+    }
+}
+
+/// The job a run belongs to for history purposes.
+pub fn job_name(cli: &Cli, run_args: &RunArgs) -> String {
+    cli.title.clone().unwrap_or_else(|| run_args.command_line())
+}
+
+/// Append-only record of finished runs, kept in the state directory.
+pub struct HistoryStore {
+    path: PathBuf,
+}
+
+impl HistoryStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Uses the same state directory as detached runs.
+    pub fn from_cli(cli: &Cli) -> Self {
+        let base = cli.state_dir.clone().unwrap_or_else(default_state_dir);
+        Self::new(base.join(HISTORY_FILE))
+    }
+
+    pub fn append(&self, entry: &HistoryEntry) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            files::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        files::append(&self.path)?.write_all(&line)
+    }
+
+    /// Every recorded run, oldest first. Lines that can't be parsed are skipped.
+    pub fn load(&self) -> io::Result<Vec<HistoryEntry>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// The last `count` runs of `job`, oldest first.
+    pub fn recent(&self, job: &str, count: usize) -> io::Result<Vec<HistoryEntry>> {
+        let mut runs: Vec<_> = self
+            .load()?
+            .into_iter()
+            .filter(|entry| entry.job == job)
+            .collect();
+        let skip = runs.len().saturating_sub(count);
+        runs.drain(..skip);
+        Ok(runs)
+    }
+}
+
+/// One mark per run, oldest first, ending with the current run's `exit_code`: `✅✅❌✅`.
+pub fn outcome_strip(previous: &[HistoryEntry], exit_code: i32) -> String {
+    previous
+        .iter()
+        .map(|run| run.exit_code)
+        .chain([exit_code])
+        .map(|code| if code == 0 { '✅' } else { '❌' })
+        .collect()
+}
+
+/// Writes the whole history to `out`, or stdout, in `format`.
+pub fn export_history(cli: &Cli, format: ExportFormat, out: Option<&Path>) -> io::Result<()> {
+    let entries = HistoryStore::from_cli(cli).load()?;
+    let mut writer: Box<dyn Write> = match out {
+        Some(path) => Box::new(BufWriter::new(files::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    match format {
+        ExportFormat::Csv => write_csv(&entries, &mut writer)?,
+        ExportFormat::Jsonl => write_jsonl(&entries, &mut writer)?,
+    }
+    writer.flush()
+}
+
+/// Writes `entries` as CSV with a header row, one row per run.
+pub fn write_csv(entries: &[HistoryEntry], out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "run_id,job,command,exit_code,started_at_ms,finished_at_ms,duration_ms,deliveries,failed_deliveries"
+    )?;
+    for entry in entries {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{}",
+            csv_field(&entry.run_id),
+            csv_field(&entry.job),
+            csv_field(&entry.command),
+            entry.exit_code,
+            entry.started_at_ms,
+            entry.finished_at_ms,
+            entry.duration_ms(),
+            entry.deliveries,
+            entry.failed_deliveries
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes `entries` as JSON lines, with `duration_ms` added to each.
+pub fn write_jsonl(entries: &[HistoryEntry], out: &mut impl Write) -> io::Result<()> {
+    for entry in entries {
+        let mut value = serde_json::to_value(entry)?;
+        value["duration_ms"] = entry.duration_ms().into();
+        serde_json::to_writer(&mut *out, &value)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break (RFC 4180).
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// How often a job failed recently, for the `--flaky-threshold` annotation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Flakiness {
+    pub failed: usize,
+    pub runs: usize,
+}
+
+impl Flakiness {
+    /// Counts failures over `previous` runs plus the failing run being reported.
+    ///
+    /// Returns `None` unless the failure rate reaches `threshold` and the job also
+    /// succeeded in between: a job that has only ever failed is broken, not flaky.
+    pub fn of_failure(previous: &[HistoryEntry], threshold: f64) -> Option<Self> {
+        let failed = previous.iter().filter(|run| run.exit_code != 0).count() + 1;
+        let runs = previous.len() + 1;
+        let succeeded = failed < runs;
+        (succeeded && failed as f64 >= threshold * runs as f64).then_some(Self { failed, runs })
+    }
+
+    pub fn describe(&self) -> String {
+        format!("flaky: failed {} of last {} runs", self.failed, self.runs)
+    }
+}
@@ -0,0 +1,145 @@
+use crate::app::{run_controlled_command, AppContext};
+use crate::cli::RunArgs;
+use crate::command::RunControl;
+use crate::error::AppError;
+use crate::variables::SessionVariables;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+/// A command started in the background from the interactive shell.
+struct Job {
+    id: usize,
+    command: String,
+    control: Arc<RunControl>,
+    handle: JoinHandle<Result<i32, AppError>>,
+}
+
+/// A snapshot of a background job, as listed by `:jobs`.
+#[derive(Debug, PartialEq)]
+pub struct JobStatus {
+    pub id: usize,
+    pub command: String,
+    pub finished: bool,
+}
+
+/// The background jobs of one interactive session.
+///
+/// A backgrounded job keeps streaming to the webhook but stops echoing to
+/// the terminal; attaching to it turns the local echo back on and waits for
+/// it to finish.
+#[derive(Default)]
+pub struct JobTable {
+    next_id: usize,
+    jobs: Vec<Job>,
+}
+
+impl JobTable {
+    /// Starts `command` in the background and returns its job ID.
+    pub fn spawn(
+        &mut self,
+        context: &Arc<AppContext>,
+        command: &str,
+        variables: &SessionVariables,
+    ) -> usize {
+        self.next_id += 1;
+        let control = Arc::new(RunControl::default());
+        control.set_local_echo(false);
+
+        let run_args = RunArgs {
+            command: vec![command.to_string()],
+            env: variables.env(),
+            ..Default::default()
+        };
+        let task_context = context.clone();
+        let task_control = control.clone();
+        let handle = tokio::spawn(async move {
+            run_controlled_command(&task_context, &run_args, task_control).await
+        });
+
+        self.jobs.push(Job {
+            id: self.next_id,
+            command: command.to_string(),
+            control,
+            handle,
+        });
+        self.next_id
+    }
+
+    pub fn list(&self) -> Vec<JobStatus> {
+        self.jobs
+            .iter()
+            .map(|job| JobStatus {
+                id: job.id,
+                command: job.command.clone(),
+                finished: job.handle.is_finished(),
+            })
+            .collect()
+    }
+
+    /// Brings a job to the foreground and waits for its exit code.
+    ///
+    /// Returns `None` if there is no job with that ID.
+    pub async fn attach(&mut self, id: usize) -> Option<Result<i32, AppError>> {
+        let index = self.jobs.iter().position(|job| job.id == id)?;
+        let job = self.jobs.remove(index);
+        job.control.set_local_echo(true);
+        Some(job.handle.await.map_err(AppError::from).and_then(|r| r))
+    }
+
+    /// Waits for every remaining job, so their final messages are delivered.
+    pub async fn wait_all(&mut self) {
+        for job in self.jobs.drain(..) {
+            let _ = job.handle.await;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Handles `<command> &`, `:jobs` and `:attach <id>`.
+    ///
+    /// Returns `false` if the line is not a job-control command.
+    pub async fn handle_line(
+        &mut self,
+        context: &Arc<AppContext>,
+        line: &str,
+        variables: &SessionVariables,
+    ) -> bool {
+        if let Some(command) = strip_background(line) {
+            let id = self.spawn(context, command, variables);
+            println!("[{}] {}", id, command);
+            return true;
+        }
+
+        let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match word {
+            ":jobs" => {
+                for job in self.list() {
+                    let state = if job.finished { "Done" } else { "Running" };
+                    println!("[{}] {:<8} {}", job.id, state, job.command);
+                }
+            }
+            ":attach" => match rest.trim().parse::<usize>() {
+                Ok(id) => match self.attach(id).await {
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => eprintln!("[shell_hook] Error executing command: {}", e),
+                    None => eprintln!("[shell_hook] No such job: {}", id),
+                },
+                Err(_) => eprintln!("[shell_hook] Usage: :attach <job id>"),
+            },
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Returns the command without its trailing `&` if the line asks to run in the background.
+pub fn strip_background(line: &str) -> Option<&str> {
+    let command = line.strip_suffix('&')?;
+    if command.ends_with('&') {
+        return None; // `a &&` is an incomplete list, not a background job
+    }
+    let command = command.trim_end();
+    (!command.is_empty()).then_some(command)
+}
@@ -0,0 +1,103 @@
+use crate::command::RunControl;
+use std::io::{self, IsTerminal};
+use std::os::fd::AsRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// The key that toggles webhook streaming with `--pause-key`.
+pub const PAUSE_KEY: u8 = b'p';
+
+/// Watches the terminal for the pause key while a run is in flight.
+///
+/// The terminal is switched to unbuffered, no-echo input for the listener's
+/// lifetime and restored when it is dropped.
+pub struct PauseKeyListener {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+    saved: libc::termios,
+}
+
+impl PauseKeyListener {
+    /// Starts listening on stdin, or returns `None` when stdin is not a terminal.
+    ///
+    /// Every toggle flips [`RunControl::streaming_paused`] and reports the new state on `toggles`.
+    pub fn start(
+        control: Arc<RunControl>,
+        toggles: UnboundedSender<bool>,
+    ) -> io::Result<Option<Self>> {
+        let stdin = io::stdin();
+        if !stdin.is_terminal() {
+            return Ok(None);
+        }
+        let fd = stdin.as_raw_fd();
+
+        // SAFETY: termios is plain data and `fd` is a valid terminal descriptor.
+        let saved = unsafe {
+            let mut termios = std::mem::zeroed::<libc::termios>();
+            if libc::tcgetattr(fd, &mut termios) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let saved = termios;
+            termios.c_lflag &= !(libc::ICANON | libc::ECHO);
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            saved
+        };
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let handle = std::thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                if !wait_readable(fd, 100) {
+                    continue;
+                }
+                let mut byte = 0u8;
+                // SAFETY: reads one byte into a stack buffer of that size.
+                let read = unsafe { libc::read(fd, (&mut byte as *mut u8).cast(), 1) };
+                if read <= 0 {
+                    break;
+                }
+                if byte.to_ascii_lowercase() == PAUSE_KEY {
+                    let paused = !control.streaming_paused();
+                    control.set_streaming_paused(paused);
+                    let _ = toggles.send(paused);
+                }
+            }
+        });
+
+        Ok(Some(Self {
+            stop,
+            handle: Some(handle),
+            saved,
+        }))
+    }
+}
+
+impl Drop for PauseKeyListener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        // SAFETY: restores the settings read from the same descriptor in `start`.
+        unsafe {
+            libc::tcsetattr(io::stdin().as_raw_fd(), libc::TCSANOW, &self.saved);
+        }
+    }
+}
+
+/// Waits up to `timeout_ms` for `fd` to become readable.
+fn wait_readable(fd: i32, timeout_ms: i32) -> bool {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // SAFETY: polls a single, initialised pollfd.
+    unsafe { libc::poll(&mut pollfd, 1, timeout_ms) > 0 }
+}
@@ -0,0 +1,96 @@
+use regex::Regex;
+use std::fmt;
+use std::sync::LazyLock;
+
+/// Share of non-empty lines that must look like a language for a batch to count as it.
+const PREDOMINANT: f64 = 0.8;
+
+static YAML_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s*(-( |$)|[\w.\-/"']+:( |$)|---$|\.\.\.$|#)"#).unwrap());
+static YAML_KEY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s*(- )?[\w.\-/"']+:( |$)"#).unwrap());
+
+/// A structured format that output can be recognized as, for tagging code blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    Json,
+    Yaml,
+    Diff,
+}
+
+impl Language {
+    /// The tag used after the opening fence, e.g. ```` ```json ````.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Language::Json => "json",
+            Language::Yaml => "yaml",
+            Language::Diff => "diff",
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.tag())
+    }
+}
+
+/// Guesses whether a batch of output lines is predominantly JSON, YAML or a unified diff.
+///
+/// Batches are cut at arbitrary lines, so this looks at the shape of each line
+/// rather than requiring a complete document.
+pub fn detect(lines: &[String]) -> Option<Language> {
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(String::as_str)
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    let share = |matches: fn(&str) -> bool| {
+        lines.iter().filter(|line| matches(line)).count() as f64 / lines.len() as f64
+    };
+
+    if lines.iter().any(|line| is_diff_header(line)) && share(is_diff_line) >= PREDOMINANT {
+        return Some(Language::Diff);
+    }
+    if share(is_json_line) >= PREDOMINANT {
+        return Some(Language::Json);
+    }
+    if lines.iter().any(|line| YAML_KEY.is_match(line))
+        && share(|line| YAML_LINE.is_match(line)) >= PREDOMINANT
+    {
+        return Some(Language::Yaml);
+    }
+    None
+}
+
+fn is_diff_header(line: &str) -> bool {
+    line.starts_with("@@ ") || line.starts_with("diff --git ")
+}
+
+fn is_diff_line(line: &str) -> bool {
+    [
+        "+",
+        "-",
+        " ",
+        "@@ ",
+        "diff ",
+        "index ",
+        "new file",
+        "deleted file",
+        "\\ No newline",
+    ]
+    .iter()
+    .any(|prefix| line.starts_with(prefix))
+}
+
+/// Lines of pretty-printed or newline-delimited JSON, including array elements like `1,`.
+fn is_json_line(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with(['{', '}', '[', ']', '"'])
+        || line
+            .strip_suffix(',')
+            .is_some_and(|value| serde_json::from_str::<serde_json::Value>(value).is_ok())
+}
@@ -1,7 +1,52 @@
+// Process groups, signals, pipes and file modes come straight from the Unix APIs.
+#[cfg(not(unix))]
+compile_error!("shell_hook supports Unix-like systems only");
+
 pub mod app;
+pub mod batching;
+pub mod ci;
+pub mod cleanup;
 pub mod cli;
+pub mod clipboard;
 pub mod command;
+pub mod crash;
+pub mod diagnostics;
+pub mod digest;
+pub mod directives;
 
+pub mod encryption;
 pub mod error;
+pub mod files;
+pub mod fixtures;
+pub mod git;
+pub mod groups;
+pub mod health;
+pub mod history;
+#[cfg(feature = "shell")]
+pub mod jobs;
+pub mod keys;
+pub mod language;
+pub mod links;
+pub mod maintenance;
 pub mod message;
+pub mod noise;
+pub mod notify;
+pub mod oauth;
+#[cfg(feature = "shell")]
+pub mod prompt;
+pub mod render;
+pub mod report;
+pub mod rewrite;
+pub mod runs;
+pub mod script;
+pub mod session;
+pub mod severity;
+pub mod sigv4;
+pub mod table;
+pub mod telemetry;
+pub mod transport;
+pub mod units;
+#[cfg(feature = "shell")]
+pub mod variables;
+pub mod watermark;
 pub mod webhook;
@@ -0,0 +1,76 @@
+use crate::cli::Cli;
+use crate::git::head_commit;
+use crate::render::Renderer;
+use regex::{Captures, Regex};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// `path/to/file.ext:line`, optionally followed by `:column`. An extension is
+/// required so times such as `12:30` are left alone.
+static FILE_REFERENCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:\./)?((?:/?[\w.-]+/)*[\w-][\w.-]*\.[A-Za-z0-9]+):(\d+)(?::\d+)?").unwrap()
+});
+
+/// Turns `file:line` references in output into links built from `--link-template`.
+pub struct Linker {
+    template: String,
+    sha: String,
+    /// Absolute paths under this directory are linked relative to it.
+    root: Option<PathBuf>,
+}
+
+impl Linker {
+    pub fn new(template: impl Into<String>, sha: impl Into<String>, root: Option<PathBuf>) -> Self {
+        Self {
+            template: template.into(),
+            sha: sha.into(),
+            root,
+        }
+    }
+
+    /// Builds a linker for `--link-template`, taking `{sha}` from the current checkout.
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        let template = cli.link_template.as_ref()?;
+        let root = std::env::current_dir().ok();
+        let sha = root
+            .as_deref()
+            .and_then(head_commit)
+            .unwrap_or_else(|| "HEAD".to_string());
+        Some(Self::new(template, sha, root))
+    }
+
+    pub fn url(&self, path: &str, line: &str) -> String {
+        self.template
+            .replace("{sha}", &self.sha)
+            .replace("{path}", path)
+            .replace("{line}", line)
+    }
+
+    /// Replaces every recognised reference in `line` with a link.
+    pub fn link_references(&self, renderer: &dyn Renderer, line: &str) -> String {
+        FILE_REFERENCE
+            .replace_all(line, |caps: &Captures| match self.relative_path(&caps[1]) {
+                Some(path) => renderer.link(&self.url(&path, &caps[2]), &caps[0]),
+                None => caps[0].to_string(),
+            })
+            .into_owned()
+    }
+
+    /// Absolute paths outside the root can't be linked into the repository.
+    fn relative_path(&self, path: &str) -> Option<String> {
+        if !path.starts_with('/') {
+            return Some(path.to_string());
+        }
+        let relative = Path::new(path).strip_prefix(self.root.as_ref()?).ok()?;
+        Some(relative.to_string_lossy().into_owned())
+    }
+}
+
+/// Checks that a link template says where the path goes.
+pub fn parse_link_template(s: &str) -> Result<String, String> {
+    if s.contains("{path}") {
+        Ok(s.to_string())
+    } else {
+        Err("link template must contain {path}".to_string())
+    }
+}
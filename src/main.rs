@@ -1,4 +1,5 @@
-#[tokio::main]
+#[cfg_attr(feature = "multi-thread", tokio::main)]
+#[cfg_attr(not(feature = "multi-thread"), tokio::main(flavor = "current_thread"))]
 async fn main() {
     let result = shell_hook::app::run().await;
 
@@ -0,0 +1,135 @@
+use crate::error::AppError;
+use serde::Deserialize;
+use std::path::Path;
+
+/// A period during which scheduled runs should not start.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaintenanceWindow {
+    pub name: String,
+    /// Unix seconds, inclusive.
+    pub start: u64,
+    /// Unix seconds, exclusive.
+    pub end: u64,
+}
+
+#[derive(Deserialize)]
+struct JsonWindow {
+    #[serde(default)]
+    name: String,
+    start: String,
+    end: String,
+}
+
+/// Loads maintenance windows from a JSON array or an iCalendar file.
+///
+/// JSON entries look like `{"name": "db upgrade", "start": "2026-10-14T02:00:00Z",
+/// "end": "2026-10-14T04:00:00Z"}`. iCalendar events use `SUMMARY`, `DTSTART`
+/// and `DTEND` in UTC.
+pub fn load_calendar(path: &Path) -> Result<Vec<MaintenanceWindow>, AppError> {
+    let text = std::fs::read_to_string(path)?;
+    if text.trim_start().starts_with("BEGIN:VCALENDAR") {
+        parse_ical(&text)
+    } else {
+        parse_json(&text)
+    }
+    .map_err(|e| AppError::InvalidCalendar(format!("{}: {}", path.display(), e)))
+}
+
+pub fn parse_json(text: &str) -> Result<Vec<MaintenanceWindow>, String> {
+    let entries: Vec<JsonWindow> = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    entries
+        .into_iter()
+        .map(|entry| window(entry.name, &entry.start, &entry.end))
+        .collect()
+}
+
+pub fn parse_ical(text: &str) -> Result<Vec<MaintenanceWindow>, String> {
+    let mut windows = Vec::new();
+    let (mut name, mut start, mut end) = (String::new(), None, None);
+    for line in text.lines().map(str::trim_end) {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        match key {
+            "BEGIN" if value == "VEVENT" => (name, start, end) = (String::new(), None, None),
+            "SUMMARY" => name = value.to_string(),
+            "DTSTART" => start = Some(value.to_string()),
+            "DTEND" => end = Some(value.to_string()),
+            "END" if value == "VEVENT" => match (&start, &end) {
+                (Some(start), Some(end)) => windows.push(window(name.clone(), start, end)?),
+                _ => return Err("event without DTSTART and DTEND".to_string()),
+            },
+            _ if key.starts_with("DTSTART;") || key.starts_with("DTEND;") => {
+                return Err(format!("only UTC times are supported, found '{}'", line));
+            }
+            _ => {}
+        }
+    }
+    Ok(windows)
+}
+
+fn window(name: String, start: &str, end: &str) -> Result<MaintenanceWindow, String> {
+    let parse = |s: &str| parse_timestamp(s).ok_or_else(|| format!("invalid time '{}'", s));
+    let window = MaintenanceWindow {
+        name,
+        start: parse(start)?,
+        end: parse(end)?,
+    };
+    if window.end <= window.start {
+        return Err(format!("window '{}' ends before it starts", window.name));
+    }
+    Ok(window)
+}
+
+/// Returns the window covering `now`, if any.
+pub fn active_window(windows: &[MaintenanceWindow], now: u64) -> Option<&MaintenanceWindow> {
+    windows.iter().find(|w| w.start <= now && now < w.end)
+}
+
+/// Parses an RFC 3339 (`2026-10-14T02:00:00+02:00`) or iCalendar UTC
+/// (`20261014T020000Z`) timestamp into Unix seconds.
+pub fn parse_timestamp(s: &str) -> Option<u64> {
+    // Everything below slices by byte offset.
+    if !s.is_ascii() {
+        return None;
+    }
+    let (local, offset_secs) = if let Some(local) = s.strip_suffix('Z') {
+        (local, 0)
+    } else {
+        let (local, offset) = s.split_at(s.len().checked_sub(6)?);
+        let sign = match offset.as_bytes()[0] {
+            b'+' => 1,
+            b'-' => -1,
+            _ => return None,
+        };
+        let (hours, minutes) = offset[1..].split_once(':')?;
+        let offset = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+        (local, sign * offset)
+    };
+
+    let local = local.split('.').next()?; // fractional seconds
+    let digits: String = local.chars().filter(|c| !matches!(c, '-' | ':')).collect();
+    let (date, time) = digits.split_once('T')?;
+    if date.len() != 8 || time.len() != 6 {
+        return None;
+    }
+    let field = |s: &str| s.parse::<i64>().ok();
+    let (year, month, day) = (field(&date[..4])?, field(&date[4..6])?, field(&date[6..])?);
+    let (hour, minute, second) = (field(&time[..2])?, field(&time[2..4])?, field(&time[4..])?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let seconds = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+    u64::try_from(seconds - offset_secs).ok()
+}
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
@@ -1,7 +1,33 @@
+use std::time::Instant;
+
 /// An enum to pass messages from the command runners to the webhook sender.
 #[derive(Clone, Debug)]
 pub enum StreamMessage {
+    /// A line without a read time, such as a notice shell_hook adds itself.
     Line(String),
+    /// A line of the command's output and when it was read, for measuring lag.
+    Output {
+        line: String,
+        read_at: Instant,
+    },
     Flush,
     CommandFinished,
 }
+
+impl StreamMessage {
+    /// The text of a `Line` or `Output` and when it was read; a `Line` counts as read now.
+    pub fn into_line(self) -> Option<(String, Instant)> {
+        match self {
+            StreamMessage::Line(line) => Some((line, Instant::now())),
+            StreamMessage::Output { line, read_at } => Some((line, read_at)),
+            StreamMessage::Flush | StreamMessage::CommandFinished => None,
+        }
+    }
+}
+
+/// Which of the child's output streams a line came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
@@ -0,0 +1,64 @@
+//! Well-known useless output lines, dropped before batching with `--noise-preset`.
+
+use clap::ValueEnum;
+use regex::RegexSet;
+
+/// A tool whose routine chatter (progress ticks, deprecation spam) is left out.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoisePreset {
+    /// npm spinners, deprecation warnings, notices and funding appeals.
+    Npm,
+    /// pip download progress, cached or satisfied requirements and upgrade notices.
+    Pip,
+    /// apt-get index fetches and package database progress.
+    Apt,
+}
+
+impl NoisePreset {
+    /// Patterns for the lines this preset drops.
+    pub fn patterns(self) -> &'static [&'static str] {
+        match self {
+            NoisePreset::Npm => &[
+                r"^npm (WARN|warn) deprecated ",
+                r"^npm (notice|timing|http fetch|verb|sill) ",
+                r"^\s*[\u{2800}-\u{28FF}]",
+                r"^\d+ packages? (are|is) looking for funding$",
+                r"^\s*run `npm fund` for details$",
+            ],
+            NoisePreset::Pip => &[
+                r"^\s*(Downloading|Using cached) \S+",
+                r"^\s*[━╸╺╶╴─]+\s",
+                r"^\s*Requirement already satisfied: ",
+                r"^DEPRECATION: ",
+                r"^\[notice\] (A new release of pip|To update, run:)",
+            ],
+            NoisePreset::Apt => &[
+                r"^(Get|Hit|Ign):\d+ ",
+                r"^Reading (package lists|state information)\.\.\.",
+                r"^Building dependency tree",
+                r"^\(Reading database \.\.\. ",
+            ],
+        }
+    }
+}
+
+/// Matches lines from any of the selected presets.
+#[derive(Debug)]
+pub struct NoiseFilter(RegexSet);
+
+impl NoiseFilter {
+    /// `None` when no presets are selected.
+    pub fn new(presets: &[NoisePreset]) -> Option<Self> {
+        if presets.is_empty() {
+            return None;
+        }
+        let patterns = presets.iter().flat_map(|preset| preset.patterns());
+        Some(Self(
+            RegexSet::new(patterns).expect("noise preset patterns are valid"),
+        ))
+    }
+
+    pub fn is_noise(&self, line: &str) -> bool {
+        self.0.is_match(line)
+    }
+}
@@ -0,0 +1,80 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// The pause between bells, so a failure's rings are heard as separate beeps.
+const BELL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Builds the command that shows a desktop notification on this platform.
+///
+/// Linux and the BSDs use `notify-send`; macOS uses `osascript`.
+pub fn notification_command(title: &str, body: &str, is_error: bool) -> Command {
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            applescript_string(body),
+            applescript_string(title)
+        ));
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command
+            .arg("--app-name=shell_hook")
+            .arg(if is_error {
+                "--urgency=critical"
+            } else {
+                "--urgency=normal"
+            })
+            .arg(title)
+            .arg(body);
+        command
+    }
+}
+
+/// Shows a desktop notification and waits for the notifier to exit.
+pub fn notify_desktop(title: &str, body: &str, is_error: bool) -> io::Result<()> {
+    let status = notification_command(title, body, is_error)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("notifier exited with {}", status)))
+    }
+}
+
+/// Quotes `s` as an AppleScript string literal.
+fn applescript_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// How many times `--bell` rings: once for success, three times for failure.
+pub fn bell_count(is_error: bool) -> usize {
+    if is_error {
+        3
+    } else {
+        1
+    }
+}
+
+/// Rings the terminal bell to signal the outcome of a run.
+///
+/// The bell goes to the controlling terminal so it isn't mixed into redirected output,
+/// falling back to stderr when there is none.
+pub async fn ring_bell(is_error: bool) -> io::Result<()> {
+    let mut out: Box<dyn Write + Send> = match OpenOptions::new().write(true).open("/dev/tty") {
+        Ok(tty) => Box::new(tty),
+        Err(_) => Box::new(io::stderr()),
+    };
+    for i in 0..bell_count(is_error) {
+        if i > 0 {
+            tokio::time::sleep(BELL_INTERVAL).await;
+        }
+        out.write_all(b"\x07")?;
+        out.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Tokens are refreshed this long before they expire, so one isn't sent as it lapses.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// The `--oauth-client-secret`, kept out of `Debug` output.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ClientSecret(String);
+
+impl ClientSecret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for ClientSecret {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl fmt::Debug for ClientSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ClientSecret(..)")
+    }
+}
+
+/// Fetches and caches access tokens with the OAuth2 client-credentials grant.
+pub struct OAuthTokenSource {
+    client: Client,
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+struct CachedToken {
+    access_token: String,
+    /// `None` when the server didn't say; the token is then kept until it is rejected.
+    expires_at: Option<Instant>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+impl OAuthTokenSource {
+    pub fn new(
+        client: Client,
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+        scope: Option<String>,
+    ) -> Self {
+        Self {
+            client,
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scope,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Returns a valid access token, requesting a new one if the cached token is
+    /// missing, about to expire, or `force_refresh` is set (e.g. after a 401).
+    pub async fn token(&self, force_refresh: bool) -> Result<String> {
+        let mut cached = self.cached.lock().await;
+        if let Some(token) = cached.as_ref() {
+            let fresh = token
+                .expires_at
+                .is_none_or(|at| Instant::now() + EXPIRY_MARGIN < at);
+            if fresh && !force_refresh {
+                return Ok(token.access_token.clone());
+            }
+        }
+
+        let token = self.request_token().await?;
+        let access_token = token.access_token.clone();
+        *cached = Some(token);
+        Ok(access_token)
+    }
+
+    async fn request_token(&self) -> Result<CachedToken> {
+        let mut form = vec![("grant_type", "client_credentials")];
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope));
+        }
+        let requested_at = Instant::now();
+        let response = self
+            .client
+            .post(&self.token_url)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&form)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "token request to {} failed with {}",
+                self.token_url,
+                response.status()
+            ));
+        }
+        let token: TokenResponse = response.json().await?;
+        Ok(CachedToken {
+            access_token: token.access_token,
+            expires_at: token
+                .expires_in
+                .map(|secs| requested_at + Duration::from_secs(secs)),
+        })
+    }
+}
@@ -0,0 +1,86 @@
+use crate::error::AppError;
+
+#[cfg(feature = "readline")]
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+#[cfg(feature = "readline")]
+const HISTORY_FILE: &str = ".shell_hook_history";
+
+/// The outcome of reading one line at the interactive prompt.
+pub enum PromptInput {
+    Line(String),
+    Interrupted,
+    Eof,
+}
+
+/// Reads lines for the interactive shell.
+///
+/// With the `readline` feature this is a rustyline editor with persistent
+/// history; without it, lines are read from stdin as-is.
+pub struct Prompt {
+    #[cfg(feature = "readline")]
+    editor: DefaultEditor,
+    #[cfg(feature = "readline")]
+    history_path: Option<std::path::PathBuf>,
+}
+
+#[cfg(feature = "readline")]
+impl Prompt {
+    pub fn new() -> Result<Self, AppError> {
+        let mut editor = DefaultEditor::new()?;
+        let history_path = dirs::home_dir().map(|p| p.join(HISTORY_FILE));
+        if let Some(ref path) = history_path {
+            if let Err(e) = editor.load_history(path) {
+                eprintln!("[shell_hook] Warning: Could not load history file: {}", e);
+            }
+        }
+        Ok(Self {
+            editor,
+            history_path,
+        })
+    }
+
+    pub fn read_line(&mut self, prompt: &str) -> Result<PromptInput, AppError> {
+        match self.editor.readline(prompt) {
+            Ok(line) => {
+                if let Err(e) = self.editor.add_history_entry(line.as_str()) {
+                    eprintln!("[shell_hook] Warning: Could not add to history: {}", e);
+                }
+                Ok(PromptInput::Line(line))
+            }
+            Err(ReadlineError::Interrupted) => Ok(PromptInput::Interrupted),
+            Err(ReadlineError::Eof) => Ok(PromptInput::Eof),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Persists the session history, if there is a place to keep it.
+    pub fn save_history(&mut self) {
+        if let Some(ref path) = self.history_path {
+            if let Err(e) = self.editor.save_history(path) {
+                eprintln!("[shell_hook] Warning: Could not save history file: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "readline"))]
+impl Prompt {
+    pub fn new() -> Result<Self, AppError> {
+        Ok(Self {})
+    }
+
+    pub fn read_line(&mut self, prompt: &str) -> Result<PromptInput, AppError> {
+        use std::io::Write;
+
+        print!("{}", prompt);
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            return Ok(PromptInput::Eof);
+        }
+        Ok(PromptInput::Line(line))
+    }
+
+    pub fn save_history(&mut self) {}
+}
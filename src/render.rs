@@ -0,0 +1,117 @@
+use crate::cli::WebhookFormat;
+use crate::language::Language;
+use regex::{Captures, Regex};
+use serde_json::{json, Value};
+
+/// Turns a plain-text message into the payload expected by a webhook format.
+///
+/// Renderers never touch the network, so formats can be tested in isolation
+/// and combined with any [`Transport`](crate::transport::Transport).
+pub trait Renderer: Send + Sync {
+    fn render(&self, message: &str) -> Value;
+
+    /// Marks `text` as important using the format's inline markup.
+    fn emphasize(&self, text: &str) -> String;
+
+    /// Makes `text` a clickable link to `url`.
+    fn link(&self, url: &str, text: &str) -> String;
+
+    /// The largest message text the format accepts, in bytes.
+    fn max_message_bytes(&self) -> usize;
+
+    /// Wraps preformatted text so it is shown in a monospace block.
+    fn code_block(&self, text: &str) -> String {
+        format!("```\n{}\n```", text)
+    }
+
+    /// Like [`code_block`](Self::code_block), tagged with a language for syntax highlighting.
+    fn tagged_code_block(&self, language: Language, text: &str) -> String {
+        format!("```{}\n{}\n```", language, text)
+    }
+}
+
+/// Renders messages for Google Chat incoming webhooks.
+pub struct GoogleChatRenderer;
+
+impl Renderer for GoogleChatRenderer {
+    fn render(&self, message: &str) -> Value {
+        json!({ "text": message })
+    }
+
+    fn emphasize(&self, text: &str) -> String {
+        format!("*{}*", text)
+    }
+
+    fn max_message_bytes(&self) -> usize {
+        4_096
+    }
+
+    fn link(&self, url: &str, text: &str) -> String {
+        format!("<{}|{}>", url, text)
+    }
+}
+
+/// Renders messages for Slack incoming webhooks.
+pub struct SlackRenderer;
+
+impl Renderer for SlackRenderer {
+    fn render(&self, message: &str) -> Value {
+        json!({ "text": message })
+    }
+
+    fn emphasize(&self, text: &str) -> String {
+        format!("*{}*", text)
+    }
+
+    fn link(&self, url: &str, text: &str) -> String {
+        format!("<{}|{}>", url, text)
+    }
+
+    fn max_message_bytes(&self) -> usize {
+        40_000
+    }
+
+    // Slack shows a language tag as the block's first line rather than highlighting.
+    fn tagged_code_block(&self, _language: Language, text: &str) -> String {
+        self.code_block(text)
+    }
+}
+
+/// Returns the renderer for the selected webhook format.
+pub fn renderer_for(format: &WebhookFormat) -> Box<dyn Renderer> {
+    match format {
+        WebhookFormat::GoogleChat => Box::new(GoogleChatRenderer),
+        WebhookFormat::Slack => Box::new(SlackRenderer),
+    }
+}
+
+/// Emphasizes every non-empty match of `pattern` in `line`.
+pub fn highlight_matches(renderer: &dyn Renderer, pattern: &Regex, line: &str) -> String {
+    pattern
+        .replace_all(line, |caps: &Captures| {
+            if caps[0].is_empty() {
+                String::new()
+            } else {
+                renderer.emphasize(&caps[0])
+            }
+        })
+        .into_owned()
+}
+
+/// Prefixes added and removed lines of a unified diff with 🟩 and 🟥, so changes
+/// stand out in chat clients that can't color code blocks. Other lines are indented
+/// to match.
+pub fn mark_diff(lines: &[String]) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| {
+            let is_file_header = line.starts_with("+++ ") || line.starts_with("--- ");
+            let marker = match line.chars().next() {
+                Some('+') if !is_file_header => "🟩",
+                Some('-') if !is_file_header => "🟥",
+                _ => "  ",
+            };
+            format!("{} {}", marker, line)
+        })
+        .collect()
+}
@@ -0,0 +1,196 @@
+use crate::cli::RunArgs;
+use crate::files;
+use crate::git::head_commit;
+use crate::telemetry::LagSummary;
+use ring::digest::{digest, SHA256};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One attempt to deliver a payload to a webhook endpoint.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct DeliveryAttempt {
+    /// Milliseconds since the Unix epoch when the request was sent.
+    pub timestamp_ms: u64,
+    /// Scheme and host of the endpoint; paths often carry secrets and are left out.
+    pub destination: String,
+    /// HTTP status of the response, if one was received.
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    /// Hex SHA-256 of the request body, for matching against what the receiver logged.
+    pub payload_sha256: String,
+    pub error: Option<String>,
+}
+
+/// Delivery attempts shared between the transports and whoever writes the report.
+#[derive(Clone, Default, Debug)]
+pub struct DeliveryLog(Arc<Mutex<Vec<DeliveryAttempt>>>);
+
+impl DeliveryLog {
+    pub fn record(&self, attempt: DeliveryAttempt) {
+        self.0.lock().unwrap().push(attempt);
+    }
+
+    /// How many attempts have been recorded and not yet taken.
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    /// How many of the recorded attempts failed.
+    pub fn failures(&self) -> usize {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|attempt| attempt.error.is_some())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes and returns everything recorded so far.
+    pub fn take(&self) -> Vec<DeliveryAttempt> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+/// The summary written by `run --report`.
+#[derive(Serialize, Debug)]
+pub struct RunReport {
+    /// The same ID as the run's history entry and `SHELL_HOOK_RESULT` line.
+    pub run_id: String,
+    pub command: String,
+    pub exit_code: i32,
+    pub started_at_ms: u64,
+    pub finished_at_ms: u64,
+    pub deliveries: Vec<DeliveryAttempt>,
+    /// How long streamed lines took from being read to being delivered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_lag: Option<LagSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<CapturedEnvironment>,
+}
+
+/// Enough about where a run happened to reproduce it later.
+#[derive(Serialize, Debug)]
+pub struct CapturedEnvironment {
+    pub cwd: Option<PathBuf>,
+    pub git_commit: Option<String>,
+    /// Sorted by name. Values of secret-looking variables are replaced with `<redacted>`.
+    pub variables: BTreeMap<String, String>,
+    /// First line of `<program> --version`, or `None` if it could not be run.
+    pub tool_versions: BTreeMap<String, Option<String>>,
+    pub shell_hook_version: String,
+}
+
+const REDACTED: &str = "<redacted>";
+const SECRET_NAME_PARTS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "KEY",
+    "AUTH",
+    "CREDENTIAL",
+    "WEBHOOK",
+];
+
+impl CapturedEnvironment {
+    /// Captures the current process environment according to the `run` options.
+    pub fn capture(run_args: &RunArgs) -> Self {
+        let cwd = std::env::current_dir().ok();
+        let variables = std::env::vars_os()
+            .map(|(name, value)| {
+                (
+                    name.to_string_lossy().into_owned(),
+                    value.to_string_lossy().into_owned(),
+                )
+            })
+            .filter(|(name, _)| {
+                run_args
+                    .capture_env_filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.is_match(name))
+            })
+            .map(|(name, value)| {
+                let value = if is_secret_name(&name) {
+                    REDACTED.to_string()
+                } else {
+                    value
+                };
+                (name, value)
+            })
+            .collect();
+        let tool_versions = run_args
+            .capture_tools
+            .iter()
+            .map(|tool| (tool.clone(), tool_version(tool)))
+            .collect();
+
+        Self {
+            git_commit: cwd.as_deref().and_then(head_commit),
+            cwd,
+            variables,
+            tool_versions,
+            shell_hook_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// Whether a variable name suggests its value is a credential.
+pub fn is_secret_name(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SECRET_NAME_PARTS.iter().any(|part| name.contains(part))
+}
+
+fn tool_version(program: &str) -> Option<String> {
+    let output = Command::new(program)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    // Some tools print their version on stderr.
+    let text = if output.stdout.is_empty() {
+        output.stderr
+    } else {
+        output.stdout
+    };
+    let text = String::from_utf8_lossy(&text);
+    text.lines().next().map(|line| line.trim().to_string())
+}
+
+impl RunReport {
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        files::write(path, serde_json::to_vec_pretty(self)?)
+    }
+}
+
+pub fn payload_sha256(body: &[u8]) -> String {
+    digest(&SHA256, body)
+        .as_ref()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+/// Reduces a URL to its scheme, host and port.
+pub fn redact_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(url) => url.origin().ascii_serialization(),
+        Err(_) => "<invalid url>".to_string(),
+    }
+}
+
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
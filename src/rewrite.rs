@@ -0,0 +1,111 @@
+use regex::{Regex, RegexBuilder};
+use std::str::FromStr;
+
+/// A sed-style substitution (`s/PATTERN/REPLACEMENT/FLAGS`) applied to output lines.
+///
+/// Any character may follow the `s` as the delimiter. `\1`..`\9` in the
+/// replacement refer to capture groups, as do regex-crate forms like `${1}`.
+/// Supported flags are `g` (replace every match) and `i` (ignore case).
+#[derive(Clone, Debug)]
+pub struct RewriteRule {
+    regex: Regex,
+    replacement: String,
+    global: bool,
+}
+
+impl RewriteRule {
+    /// Applies the substitution to a single line.
+    pub fn apply(&self, line: &str) -> String {
+        if self.global {
+            self.regex.replace_all(line, self.replacement.as_str())
+        } else {
+            self.regex.replace(line, self.replacement.as_str())
+        }
+        .into_owned()
+    }
+}
+
+impl FromStr for RewriteRule {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let mut chars = rule.chars();
+        if chars.next() != Some('s') {
+            return Err("rewrite rules must look like s/PATTERN/REPLACEMENT/".to_string());
+        }
+        let delimiter = chars
+            .next()
+            .ok_or_else(|| "missing delimiter after 's'".to_string())?;
+
+        let parts = split_unescaped(chars.as_str(), delimiter);
+        let [pattern, replacement, flags] = parts.as_slice() else {
+            return Err(format!(
+                "expected s{d}PATTERN{d}REPLACEMENT{d}[FLAGS]",
+                d = delimiter
+            ));
+        };
+
+        let mut global = false;
+        let mut case_insensitive = false;
+        for flag in flags.chars() {
+            match flag {
+                'g' => global = true,
+                'i' => case_insensitive = true,
+                other => return Err(format!("unsupported rewrite flag '{}'", other)),
+            }
+        }
+
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            regex,
+            replacement: translate_backreferences(replacement),
+            global,
+        })
+    }
+}
+
+/// Applies each rule in order to a line.
+pub fn apply_rewrites(rules: &[RewriteRule], line: String) -> String {
+    rules.iter().fold(line, |line, rule| rule.apply(&line))
+}
+
+/// Splits on `delimiter`, treating `\<delimiter>` as a literal delimiter.
+fn split_unescaped(input: &str, delimiter: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.peek() == Some(&delimiter) {
+            parts.last_mut().unwrap().push(delimiter);
+            chars.next();
+        } else if c == delimiter {
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c);
+        }
+    }
+    parts
+}
+
+/// Rewrites sed's `\N` back-references as `${N}` for the regex crate.
+fn translate_backreferences(replacement: &str) -> String {
+    let mut out = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(d)) if d.is_ascii_digit() => {
+                out.push_str(&format!("${{{}}}", d));
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                out.push('\\');
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
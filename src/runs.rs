@@ -0,0 +1,586 @@
+use crate::app::{format_with_title, run_controlled_command, AppContext};
+use crate::cli::{Cli, RunArgs};
+use crate::command::RunControl;
+use crate::error::AppError;
+use crate::files;
+use crate::message::StreamMessage;
+use crate::session::SessionEvent;
+use crate::webhook::{run_webhook_sender, send_message};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+const STATUS_FILE: &str = "status.json";
+const LOG_FILE: &str = "output.log";
+/// Just the lines the command printed, without shell_hook's own messages.
+const COMMAND_LOG_FILE: &str = "command.log";
+/// Written by `shell_hook kill`, so that only the background half ever writes `status.json`.
+const CANCEL_FILE: &str = "cancel";
+/// How many taken run IDs [`RunStore::create`] skips before giving up.
+const CREATE_ATTEMPTS: u32 = 16;
+/// How long `run --handoff` waits for the background half to start the command.
+const HANDOFF_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often the background half of a run checks for `shell_hook kill`.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long `shell_hook kill` waits for the run to finish.
+const KILL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The lifecycle of a detached run.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunState {
+    Starting,
+    Running,
+    Finished,
+    /// The host went down before the run finished.
+    Interrupted,
+}
+
+/// What is known about a detached run, persisted as `status.json`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RunRecord {
+    pub id: String,
+    pub command: String,
+    pub state: RunState,
+    /// PID of the background shell_hook process streaming the run.
+    pub pid: Option<u32>,
+    /// PID of the command itself, once started.
+    #[serde(default)]
+    pub child_pid: Option<u32>,
+    pub exit_code: Option<i32>,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    /// The kernel boot ID when the run was started, used to detect reboots.
+    #[serde(default)]
+    pub boot_id: Option<String>,
+    /// The reason given to `shell_hook kill`, recorded once the background
+    /// process has cancelled the run.
+    #[serde(default)]
+    pub cancel_reason: Option<String>,
+}
+
+impl RunRecord {
+    /// Whether the run was still going when the host it ran on was rebooted.
+    ///
+    /// Without a boot ID to compare, a run whose process is gone counts as
+    /// interrupted too.
+    pub fn is_interrupted(&self, current_boot_id: Option<&str>) -> bool {
+        if !matches!(self.state, RunState::Starting | RunState::Running) {
+            return false;
+        }
+        match (self.boot_id.as_deref(), current_boot_id) {
+            (Some(recorded), Some(current)) => recorded != current,
+            _ => self.pid.is_some_and(|pid| !process_alive(pid)),
+        }
+    }
+
+    /// Whether the run is still marked as going but its background process is
+    /// gone, on this boot or an earlier one.
+    pub fn is_orphaned(&self) -> bool {
+        matches!(self.state, RunState::Starting | RunState::Running)
+            && self.pid.is_some_and(|pid| !process_alive(pid))
+    }
+}
+
+/// On-disk directory of detached runs, one subdirectory per run ID.
+pub struct RunStore {
+    root: PathBuf,
+}
+
+impl RunStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Uses `--state-dir`, then `$XDG_STATE_HOME/shell_hook`, then `~/.local/state/shell_hook`.
+    pub fn from_cli(cli: &Cli) -> Self {
+        let base = cli.state_dir.clone().unwrap_or_else(default_state_dir);
+        Self::new(base.join("runs"))
+    }
+
+    /// The directory holding one subdirectory per run.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn run_dir(&self, id: &str) -> PathBuf {
+        self.root.join(id)
+    }
+
+    pub fn log_path(&self, id: &str) -> PathBuf {
+        self.run_dir(id).join(LOG_FILE)
+    }
+
+    /// The command's own output, which `history forward` resends.
+    pub fn command_log_path(&self, id: &str) -> PathBuf {
+        self.run_dir(id).join(COMMAND_LOG_FILE)
+    }
+
+    /// Registers a new run and returns its record.
+    ///
+    /// A run ID that is already taken, however unlikely, is replaced by a fresh one
+    /// rather than overwriting the other run.
+    pub fn create(&self, command: &str) -> io::Result<RunRecord> {
+        files::create_dir_all(&self.root)?;
+        let mut attempts = 0;
+        let id = loop {
+            let id = new_run_id();
+            match files::create_dir(&self.run_dir(&id)) {
+                Ok(()) => break id,
+                Err(e)
+                    if e.kind() == io::ErrorKind::AlreadyExists && attempts < CREATE_ATTEMPTS =>
+                {
+                    attempts += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        let record = RunRecord {
+            id,
+            command: command.to_string(),
+            state: RunState::Starting,
+            pid: None,
+            child_pid: None,
+            exit_code: None,
+            started_at: unix_now(),
+            finished_at: None,
+            boot_id: current_boot_id(),
+            cancel_reason: None,
+        };
+        self.save(&record)?;
+        Ok(record)
+    }
+
+    pub fn save(&self, record: &RunRecord) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(record)?;
+        // Write then rename so readers never see a half-written file.
+        let tmp = self
+            .run_dir(&record.id)
+            .join(format!("{}.tmp", STATUS_FILE));
+        files::write(&tmp, json)?;
+        fs::rename(tmp, self.run_dir(&record.id).join(STATUS_FILE))
+    }
+
+    /// Asks the background half of run `id` to cancel it.
+    pub fn request_cancel(&self, id: &str, reason: &str) -> io::Result<()> {
+        // Each killer writes its own temporary file, so concurrent kills never
+        // rename a half-written one into place.
+        let tmp = self
+            .run_dir(id)
+            .join(format!("{}.{}.tmp", CANCEL_FILE, std::process::id()));
+        files::write(&tmp, reason)?;
+        fs::rename(tmp, self.run_dir(id).join(CANCEL_FILE))
+    }
+
+    /// The reason passed to [`RunStore::request_cancel`], if it has been called.
+    pub fn cancel_request(&self, id: &str) -> io::Result<Option<String>> {
+        match fs::read_to_string(self.run_dir(id).join(CANCEL_FILE)) {
+            Ok(reason) => Ok(Some(reason)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn load(&self, id: &str) -> Result<RunRecord, AppError> {
+        let path = self.run_dir(id).join(STATUS_FILE);
+        let json = match fs::read(&path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(AppError::UnknownRun(id.to_string()))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_slice(&json).map_err(|e| AppError::Io(e.into()))
+    }
+
+    /// Returns every run with a readable record, oldest first.
+    pub fn list(&self) -> io::Result<Vec<RunRecord>> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut records: Vec<RunRecord> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| self.load(&entry.file_name().to_string_lossy()).ok())
+            .collect();
+        records.sort_by_key(|r| r.started_at);
+        Ok(records)
+    }
+}
+
+/// Starts a copy of this invocation in the background and returns its run ID.
+///
+/// The copy runs the same arguments minus `--detach` or `--handoff`, in its own session,
+/// with its output written to the run's log file.
+pub fn spawn_detached(cli: &Cli, run_args: &RunArgs) -> Result<String, AppError> {
+    if cli.argv.is_empty() {
+        return Err(AppError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "detaching needs the arguments the options were parsed from; use Cli::try_parse_args",
+        )));
+    }
+    let store = RunStore::from_cli(cli);
+    let record = store.create(&run_args.command_line())?;
+    let log = files::create(&store.log_path(&record.id))?;
+
+    // `--run-id` is global, so it goes first where trailing command args cannot swallow it.
+    let mut args: Vec<OsString> = vec!["--run-id".into(), record.id.clone().into()];
+    args.extend(strip_detach_flags(cli.argv.iter().skip(1).cloned()));
+
+    let mut command = std::process::Command::new(std::env::current_exe()?);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    detach_from_terminal(&mut command);
+    command.spawn()?;
+
+    Ok(record.id)
+}
+
+/// Waits until the background half of a run has started its command and returns the command's PID.
+pub async fn wait_for_child_pid(cli: &Cli, id: &str) -> Result<u32, AppError> {
+    let store = RunStore::from_cli(cli);
+    let deadline = Instant::now() + HANDOFF_TIMEOUT;
+    loop {
+        let record = store.load(id)?;
+        if let Some(pid) = record.child_pid {
+            return Ok(pid);
+        }
+        if record.state == RunState::Finished || Instant::now() >= deadline {
+            return Err(AppError::Io(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "run {} did not start its command; see `shell_hook logs {}`",
+                    id, id
+                ),
+            )));
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+/// Runs the command as the background half of `run --detach`, keeping its record current.
+pub async fn run_detached_worker(
+    cli: Arc<Cli>,
+    run_args: &RunArgs,
+    id: &str,
+) -> Result<i32, AppError> {
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    let mut context = AppContext::new(cli)?;
+    context.events = Some(events_tx);
+    let context = &Arc::new(context);
+    let store = RunStore::from_cli(&context.cli);
+    let mut command_log = files::create(&store.command_log_path(id))?;
+    let mut record = store.load(id)?;
+    record.state = RunState::Running;
+    record.pid = Some(std::process::id());
+    store.save(&record)?;
+
+    let control = Arc::new(RunControl::default());
+    let mut child_pid = control.watch_child_pid();
+    let run = run_controlled_command(context, run_args, control.clone());
+    tokio::pin!(run);
+    let mut cancel_poll = tokio::time::interval(CANCEL_POLL_INTERVAL);
+    let result = loop {
+        tokio::select! {
+            result = &mut run => break result,
+            Some(event) = events.recv() => log_command_output(context, &mut command_log, event).await,
+            Ok(()) = child_pid.changed() => {
+                record.child_pid = *child_pid.borrow_and_update();
+                save_or_warn(context, &store, &record).await;
+            }
+            _ = cancel_poll.tick(), if record.cancel_reason.is_none() => {
+                match store.cancel_request(id) {
+                    Ok(Some(reason)) => {
+                        control.cancel(&reason);
+                        record.cancel_reason = Some(reason);
+                        save_or_warn(context, &store, &record).await;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        let warning = format!("Failed to check run {} for cancellation: {}", id, e);
+                        context.warn(warning).await;
+                    }
+                }
+            }
+        }
+    };
+    while let Ok(event) = events.try_recv() {
+        log_command_output(context, &mut command_log, event).await;
+    }
+
+    record.state = RunState::Finished;
+    record.exit_code = Some(*result.as_ref().unwrap_or(&1));
+    record.finished_at = Some(unix_now());
+    save_or_warn(context, &store, &record).await;
+    result
+}
+
+async fn log_command_output(context: &AppContext, log: &mut File, event: SessionEvent) {
+    if let SessionEvent::Output { line, .. } = event {
+        if let Err(e) = writeln!(log, "{}", line) {
+            context
+                .warn(format!("Failed to record command output: {}", e))
+                .await;
+        }
+    }
+}
+
+/// Saves a record from the middle of a run, where giving up would abandon the
+/// command and its final message over a status file.
+async fn save_or_warn(context: &AppContext, store: &RunStore, record: &RunRecord) {
+    if let Err(e) = store.save(record) {
+        context
+            .warn(format!("Failed to update run {}: {}", record.id, e))
+            .await;
+    }
+}
+
+/// Sends a notification for every detached run cut short by a reboot and marks it interrupted.
+///
+/// Returns the number of interrupted runs found.
+pub async fn notify_interrupted_runs(context: &Arc<AppContext>) -> Result<usize, AppError> {
+    let store = RunStore::from_cli(&context.cli);
+    let boot_id = current_boot_id();
+    let mut count = 0;
+    for mut record in store.list()? {
+        if !record.is_interrupted(boot_id.as_deref()) {
+            continue;
+        }
+        let message = format_with_title(
+            &context.cli,
+            &format!(
+                "⚠️ Run {} (`{}`) never completed (host rebooted).",
+                record.id, record.command
+            ),
+        );
+        eprintln!("{}", message);
+        if let Err(e) = send_message(context, &message).await {
+            context
+                .warn(format!("Failed to send reboot notice: {}", e))
+                .await;
+            continue; // leave the record as is so the next check retries
+        }
+        record.state = RunState::Interrupted;
+        store.save(&record)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Asks the background half of a detached run to cancel it and waits for it to finish.
+pub async fn kill_run(cli: &Cli, id: &str, reason: Option<&str>) -> Result<i32, AppError> {
+    let store = RunStore::from_cli(cli);
+    let record = store.load(id)?;
+    let alive = record.pid.is_none_or(process_alive);
+    if !matches!(record.state, RunState::Starting | RunState::Running) || !alive {
+        eprintln!("[shell_hook] Run {} is not running.", id);
+        return Ok(1);
+    }
+    store.request_cancel(id, reason.unwrap_or_default())?;
+
+    let deadline = Instant::now() + KILL_TIMEOUT;
+    loop {
+        let record = store.load(id)?;
+        if record.state == RunState::Finished {
+            println!("Cancelled run {}.", id);
+            return Ok(0);
+        }
+        if Instant::now() >= deadline {
+            return Err(AppError::Io(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("run {} did not stop; see `shell_hook status {}`", id, id),
+            )));
+        }
+        tokio::time::sleep(CANCEL_POLL_INTERVAL).await;
+    }
+}
+
+/// Prints one run's status, or a table of all runs when `id` is `None`.
+pub fn print_status(cli: &Cli, id: Option<&str>) -> Result<i32, AppError> {
+    let store = RunStore::from_cli(cli);
+    let Some(id) = id else {
+        for record in store.list()? {
+            println!(
+                "{}  {:<9} {:<5} {}",
+                record.id,
+                state_label(record.state),
+                record.exit_code.map_or("-".to_string(), |c| c.to_string()),
+                record.command
+            );
+        }
+        return Ok(0);
+    };
+
+    let record = store.load(id)?;
+    println!("Run:      {}", record.id);
+    println!("Command:  {}", record.command);
+    match (record.state, record.pid) {
+        (RunState::Running, Some(pid)) => match record.child_pid {
+            Some(child) => println!("State:    running (pid {}, command pid {})", pid, child),
+            None => println!("State:    running (pid {})", pid),
+        },
+        (state, _) => println!("State:    {}", state_label(state)),
+    }
+    println!(
+        "Started:  {} ago",
+        cli.units
+            .seconds(unix_now().saturating_sub(record.started_at))
+    );
+    if let Some(code) = record.exit_code {
+        println!("Exit:     {}", code);
+    }
+    Ok(0)
+}
+
+/// Copies a run's captured output to stdout.
+pub fn print_logs(cli: &Cli, id: &str) -> Result<i32, AppError> {
+    let store = RunStore::from_cli(cli);
+    store.load(id)?;
+    let mut log = File::open(store.log_path(id))?;
+    io::copy(&mut log, &mut io::stdout())?;
+    Ok(0)
+}
+
+/// Sends a detached run's output, opened and closed by a summary, to the webhook
+/// and format this invocation is configured with. The run itself is not touched.
+///
+/// Only the command's own lines are resent; the run's start and final messages
+/// are replaced by the summary.
+pub async fn forward_run(context: &Arc<AppContext>, id: &str) -> Result<i32, AppError> {
+    let store = RunStore::from_cli(&context.cli);
+    let record = store.load(id)?;
+    let log = fs::read(store.command_log_path(id))?;
+    let units = context.cli.units;
+
+    let header = format_with_title(
+        &context.cli,
+        &format!(
+            "⏩ Forwarding run {} (`{}`), started {} ago:",
+            record.id,
+            record.command,
+            units.seconds(unix_now().saturating_sub(record.started_at))
+        ),
+    );
+    let footer = match (record.state, record.exit_code) {
+        (RunState::Finished, Some(0)) => format!("✅ Run {} finished successfully.", record.id),
+        (RunState::Finished, Some(code)) => {
+            format!("❌ Run {} failed with exit code {}.", record.id, code)
+        }
+        (state, _) => format!("⏳ Run {} is {}.", record.id, state_label(state)),
+    };
+
+    if let Err(e) = send_message(context, &header).await {
+        context.warn(format!("Failed to forward run: {}", e)).await;
+        return Ok(1);
+    }
+    let (tx, rx) = mpsc::channel(100);
+    let sender = tokio::spawn(run_webhook_sender(context.clone(), rx));
+    for line in String::from_utf8_lossy(&log).lines() {
+        if tx
+            .send(StreamMessage::Line(line.to_string()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+    let _ = tx.send(StreamMessage::CommandFinished).await;
+    drop(tx);
+    let streamed = sender.await.map_err(io::Error::other)?;
+    let finished = send_message(context, &format_with_title(&context.cli, &footer)).await;
+    if let Err(e) = streamed.and(finished) {
+        context.warn(format!("Failed to forward run: {}", e)).await;
+        return Ok(1);
+    }
+    Ok(0)
+}
+
+fn state_label(state: RunState) -> &'static str {
+    match state {
+        RunState::Starting => "starting",
+        RunState::Running => "running",
+        RunState::Finished => "finished",
+        RunState::Interrupted => "interrupted",
+    }
+}
+
+/// Removes `--detach` and `--handoff` before the `--` separator.
+fn strip_detach_flags(args: impl Iterator<Item = OsString>) -> Vec<OsString> {
+    let mut after_separator = false;
+    args.filter(|arg| {
+        if after_separator {
+            return true;
+        }
+        if arg == "--" {
+            after_separator = true;
+            return true;
+        }
+        arg != "--detach" && arg != "--handoff"
+    })
+    .collect()
+}
+
+fn detach_from_terminal(command: &mut std::process::Command) {
+    use std::os::unix::process::CommandExt;
+    // SAFETY: setsid is async-signal-safe and touches no memory of the parent.
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Returns an identifier that changes every time the host boots, where the platform has one.
+pub fn current_boot_id() -> Option<String> {
+    let id = fs::read_to_string("/proc/sys/kernel/random/boot_id").ok()?;
+    Some(id.trim().to_string()).filter(|id| !id.is_empty())
+}
+
+fn process_alive(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks that the process exists and may be signalled.
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+pub(crate) fn default_state_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_STATE_HOME").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir).join("shell_hook");
+    }
+    let home = std::env::var_os("HOME").map_or_else(std::env::temp_dir, PathBuf::from);
+    home.join(".local/state/shell_hook")
+}
+
+/// A random 64-bit ID, unguessable and unlikely to collide across a fleet.
+pub(crate) fn new_run_id() -> String {
+    let mut bytes = [0u8; 8];
+    if SystemRandom::new().fill(&mut bytes).is_err() {
+        // Still unique per process and moment, just not unguessable.
+        let mut hasher = DefaultHasher::new();
+        SystemTime::now().hash(&mut hasher);
+        std::process::id().hash(&mut hasher);
+        bytes = hasher.finish().to_le_bytes();
+    }
+    format!("{:016x}", u64::from_le_bytes(bytes))
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
@@ -0,0 +1,33 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Builds the argv that runs `script` with `args` the way its shebang asks.
+///
+/// The interpreter is read from the `#!` line ourselves rather than left to the
+/// kernel, so scripts work in crontabs even without the executable bit. Like
+/// Linux, everything after the interpreter is passed as a single argument.
+/// Files without a shebang are run with `sh`.
+pub fn script_argv(script: &str, args: &[String]) -> io::Result<Vec<String>> {
+    let mut first_line = String::new();
+    BufReader::new(File::open(script)?).read_line(&mut first_line)?;
+
+    let mut argv = match first_line.strip_prefix("#!").map(str::trim) {
+        Some(shebang) if !shebang.is_empty() => match shebang.split_once(char::is_whitespace) {
+            Some((interpreter, arg)) => vec![interpreter.to_string(), arg.trim().to_string()],
+            None => vec![shebang.to_string()],
+        },
+        _ => vec!["sh".to_string()],
+    };
+    argv.push(script.to_string());
+    argv.extend(args.iter().cloned());
+    Ok(argv)
+}
+
+/// The script's file name, used as the default `--title` for `run-script`.
+pub fn script_title(script: &str) -> String {
+    Path::new(script).file_name().map_or_else(
+        || script.to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
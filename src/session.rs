@@ -0,0 +1,53 @@
+use crate::app::{run_single_command, AppContext};
+use crate::cli::{Cli, RunArgs};
+use crate::error::AppError;
+use crate::message::OutputStream;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// A structured notification about a command run by a [`Session`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum SessionEvent {
+    /// The command was spawned and the start message sent.
+    Started { command: String },
+    /// One line of output, emitted whether or not it is streamed to the webhook.
+    Output { line: String, stream: OutputStream },
+    /// The command exited; `message` is the final message sent to the webhook.
+    Finished { exit_code: i32, message: String },
+    /// Something went wrong in shell_hook rather than in the command, such as a
+    /// message that could not be delivered or needed retrying.
+    Warning { message: String },
+}
+
+/// An embeddable equivalent of the interactive shell.
+///
+/// Each submitted line runs as a plain `sh -c` command with the webhook options
+/// from the given [`Cli`], while progress is reported as [`SessionEvent`]s on
+/// the returned receiver. The `shell` prompt's builtins (`:note`, `:set`,
+/// `:jobs`, `<command> &` and the like) are not available.
+pub struct Session {
+    context: Arc<AppContext>,
+}
+
+impl Session {
+    pub fn new(cli: Cli) -> Result<(Self, mpsc::UnboundedReceiver<SessionEvent>), AppError> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut context = AppContext::new(Arc::new(cli))?;
+        context.events = Some(tx);
+        Ok((
+            Self {
+                context: Arc::new(context),
+            },
+            rx,
+        ))
+    }
+
+    /// Runs a command line to completion and returns its exit code.
+    pub async fn submit(&self, line: &str) -> Result<i32, AppError> {
+        let run_args = RunArgs {
+            command: vec![line.to_string()],
+            ..Default::default()
+        };
+        run_single_command(&self.context, &run_args).await
+    }
+}
@@ -0,0 +1,116 @@
+use clap::ValueEnum;
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+/// How urgent a finished run is, lowest first.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        })
+    }
+}
+
+impl Severity {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Severity::Info,
+            1 => Severity::Low,
+            2 => Severity::Medium,
+            3 => Severity::High,
+            _ => Severity::Critical,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum SeverityCondition {
+    /// The command exited with this code.
+    Exit(i32),
+    /// A line of output matched this pattern.
+    Match(Regex),
+}
+
+/// Assigns a severity when its condition holds, written `exit=CODE:SEVERITY`
+/// or `match=REGEX:SEVERITY`.
+///
+/// An exit rule replaces the default severity for that code, so `exit=1:low`
+/// can calm a routine failure. Match rules only ever raise the severity.
+#[derive(Clone, Debug)]
+pub struct SeverityRule {
+    pub condition: SeverityCondition,
+    pub severity: Severity,
+}
+
+impl FromStr for SeverityRule {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let usage = || {
+            format!(
+                "expected exit=CODE:SEVERITY or match=REGEX:SEVERITY, got '{}'",
+                rule
+            )
+        };
+        let (condition, severity) = rule.rsplit_once(':').ok_or_else(usage)?;
+        let severity = Severity::from_str(severity, true)?;
+        let condition = match condition.split_once('=').ok_or_else(usage)? {
+            ("exit", code) => SeverityCondition::Exit(
+                code.parse()
+                    .map_err(|_| format!("invalid exit code '{}'", code))?,
+            ),
+            ("match", pattern) => {
+                SeverityCondition::Match(Regex::new(pattern).map_err(|e| e.to_string())?)
+            }
+            _ => return Err(usage()),
+        };
+        Ok(Self {
+            condition,
+            severity,
+        })
+    }
+}
+
+/// The highest severity among match rules whose pattern occurs in `line`.
+pub fn line_severity(rules: &[SeverityRule], line: &str) -> Option<Severity> {
+    rules
+        .iter()
+        .filter(|rule| matches!(&rule.condition, SeverityCondition::Match(re) if re.is_match(line)))
+        .map(|rule| rule.severity)
+        .max()
+}
+
+/// Scores a finished run from its exit code and the worst output line seen.
+///
+/// Without an exit rule, success is `info`, a failure exit `medium` and a
+/// signal or failure to start `high`.
+pub fn score(
+    rules: &[SeverityRule],
+    exit_code: Option<i32>,
+    matched: Option<Severity>,
+) -> Severity {
+    let exit_rule = rules.iter().find_map(|rule| match rule.condition {
+        SeverityCondition::Exit(code) if Some(code) == exit_code => Some(rule.severity),
+        _ => None,
+    });
+    let base = exit_rule.unwrap_or(match exit_code {
+        Some(0) => Severity::Info,
+        Some(_) => Severity::Medium,
+        None => Severity::High,
+    });
+    base.max(matched.unwrap_or(Severity::Info))
+}
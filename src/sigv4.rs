@@ -0,0 +1,270 @@
+use crate::report::payload_sha256;
+use anyhow::{anyhow, Context, Result};
+use reqwest::Url;
+use ring::hmac;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// The region and service requests are signed for, given as `region/service`,
+/// e.g. `eu-west-1/execute-api` for API Gateway or `us-east-1/lambda` for function URLs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SigV4Scope {
+    pub region: String,
+    pub service: String,
+}
+
+impl FromStr for SigV4Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((region, service)) if !region.is_empty() && !service.is_empty() => Ok(Self {
+                region: region.to_string(),
+                service: service.to_string(),
+            }),
+            _ => Err(format!("expected REGION/SERVICE, got '{}'", s)),
+        }
+    }
+}
+
+/// AWS credentials used to sign requests.
+#[derive(Clone, Debug)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    /// Looks up credentials the way the AWS CLI does for static keys: the
+    /// `AWS_ACCESS_KEY_ID`/`AWS_SECRET_ACCESS_KEY`/`AWS_SESSION_TOKEN` variables, then the
+    /// `AWS_PROFILE` (or `default`) profile in the shared credentials file.
+    pub fn load() -> Result<Self> {
+        if let (Ok(access_key_id), Ok(secret_access_key)) = (
+            std::env::var("AWS_ACCESS_KEY_ID"),
+            std::env::var("AWS_SECRET_ACCESS_KEY"),
+        ) {
+            return Ok(Self {
+                access_key_id,
+                secret_access_key,
+                session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+            });
+        }
+
+        let path = credentials_file().ok_or_else(|| anyhow!("no AWS credentials found"))?;
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("no AWS credentials found (reading {})", path.display()))?;
+        let profile = std::env::var("AWS_PROFILE").unwrap_or_else(|_| "default".to_string());
+        Self::from_profile(&contents, &profile).ok_or_else(|| {
+            anyhow!(
+                "profile '{}' in {} has no access keys",
+                profile,
+                path.display()
+            )
+        })
+    }
+
+    /// Reads a profile's keys from the contents of a shared credentials file.
+    pub fn from_profile(contents: &str, profile: &str) -> Option<Self> {
+        let mut in_profile = false;
+        let (mut access_key_id, mut secret_access_key, mut session_token) = (None, None, None);
+        for line in contents.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                in_profile = name.trim() == profile;
+            } else if let (true, Some((key, value))) = (in_profile, line.split_once('=')) {
+                let value = Some(value.trim().to_string());
+                match key.trim() {
+                    "aws_access_key_id" => access_key_id = value,
+                    "aws_secret_access_key" => secret_access_key = value,
+                    "aws_session_token" => session_token = value,
+                    _ => {}
+                }
+            }
+        }
+        Some(Self {
+            access_key_id: access_key_id?,
+            secret_access_key: secret_access_key?,
+            session_token,
+        })
+    }
+}
+
+fn credentials_file() -> Option<PathBuf> {
+    std::env::var_os("AWS_SHARED_CREDENTIALS_FILE")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".aws/credentials"))
+        })
+}
+
+/// Computes the headers that sign a request with AWS Signature Version 4.
+///
+/// `headers` are other headers the request sends that should be covered by the signature,
+/// with lowercase names. Returns `x-amz-date`, `x-amz-security-token` (for temporary
+/// credentials) and `authorization`, to be added to the request as-is. `host` is signed
+/// but left for the HTTP client to send.
+pub fn sign(
+    credentials: &AwsCredentials,
+    scope: &SigV4Scope,
+    method: &str,
+    url: &Url,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    unix_seconds: u64,
+) -> Result<Vec<(String, String)>> {
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err(anyhow!("URL has no host: {}", url)),
+    };
+    let amz_date = format_amz_date(unix_seconds);
+    let date = &amz_date[..8];
+
+    let mut headers: Vec<(&str, String)> = headers
+        .iter()
+        .map(|(name, value)| (*name, value.to_string()))
+        .chain([("host", host), ("x-amz-date", amz_date.clone())])
+        .collect();
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    headers.sort();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        canonical_uri(url),
+        canonical_query(url),
+        canonical_headers,
+        signed_headers,
+        payload_sha256(body)
+    );
+    let credential_scope = format!("{}/{}/{}/aws4_request", date, scope.region, scope.service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        credential_scope,
+        payload_sha256(canonical_request.as_bytes())
+    );
+
+    let mut key = hmac_sha256(
+        format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    for part in [
+        scope.region.as_str(),
+        scope.service.as_str(),
+        "aws4_request",
+    ] {
+        key = hmac_sha256(&key, part.as_bytes());
+    }
+    let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    let mut signing_headers = vec![("x-amz-date".to_string(), amz_date)];
+    if let Some(token) = &credentials.session_token {
+        signing_headers.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    signing_headers.push((
+        "authorization".to_string(),
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, credential_scope, signed_headers, signature
+        ),
+    ));
+    Ok(signing_headers)
+}
+
+/// The path with each segment encoded again, as SigV4 requires for services other than S3.
+fn canonical_uri(url: &Url) -> String {
+    let path = url.path();
+    if path.is_empty() {
+        return "/".to_string();
+    }
+    path.split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn canonical_query(url: &Url) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| (uri_encode(&key), uri_encode(&value)))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Percent-encodes everything except the RFC 3986 unreserved characters.
+fn uri_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => {
+                let _ = write!(encoded, "%{:02X}", byte);
+            }
+        }
+    }
+    encoded
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data)
+        .as_ref()
+        .to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut out, b| {
+        let _ = write!(out, "{:02x}", b);
+        out
+    })
+}
+
+/// Formats a Unix time as `YYYYMMDD'T'HHMMSS'Z'`.
+fn format_amz_date(unix_seconds: u64) -> String {
+    let days = (unix_seconds / 86_400) as i64;
+    let seconds_of_day = unix_seconds % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    )
+}
+
+/// The proleptic Gregorian date `days` after 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,101 @@
+use crate::cli::TableMode;
+
+/// A run of batched output lines, either plain text or a detected table.
+#[derive(Debug, PartialEq)]
+pub enum Segment {
+    Text(String),
+    Table(Vec<Vec<String>>),
+}
+
+/// Splits a line into fields, or returns `None` if it has fewer than two.
+pub fn split_row(line: &str, mode: TableMode) -> Option<Vec<String>> {
+    let fields: Vec<String> = match mode {
+        TableMode::Tab => line.split('\t').map(|f| f.trim().to_string()).collect(),
+        TableMode::Csv => split_csv(line),
+    };
+    (fields.len() >= 2).then_some(fields)
+}
+
+/// Groups consecutive lines with the same number of fields into tables.
+///
+/// A single delimited line is not treated as a table.
+pub fn segment_lines(lines: &[String], mode: TableMode) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut pending: Vec<&String> = Vec::new();
+
+    fn flush(segments: &mut Vec<Segment>, rows: &mut Vec<Vec<String>>, pending: &mut Vec<&String>) {
+        if rows.len() >= 2 {
+            segments.push(Segment::Table(std::mem::take(rows)));
+        } else {
+            segments.extend(pending.iter().map(|l| Segment::Text((*l).clone())));
+            rows.clear();
+        }
+        pending.clear();
+    }
+
+    for line in lines {
+        match split_row(line, mode) {
+            Some(fields) if rows.first().is_none_or(|r| r.len() == fields.len()) => {
+                rows.push(fields);
+                pending.push(line);
+            }
+            Some(fields) => {
+                flush(&mut segments, &mut rows, &mut pending);
+                rows.push(fields);
+                pending.push(line);
+            }
+            None => {
+                flush(&mut segments, &mut rows, &mut pending);
+                segments.push(Segment::Text(line.clone()));
+            }
+        }
+    }
+    flush(&mut segments, &mut rows, &mut pending);
+    segments
+}
+
+/// Pads every column to its widest cell so rows line up in a monospace font.
+pub fn align_rows(rows: &[Vec<String>]) -> Vec<String> {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .map(|(i, cell)| format!("{:<width$}", cell, width = widths[i]))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+/// Splits a CSV record, honouring double-quoted fields and `""` escapes.
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                fields.last_mut().unwrap().push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
@@ -0,0 +1,167 @@
+use crate::units::Units;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Upper bounds of the send latency histogram in the batch summary.
+const LATENCY_BUCKETS: [Duration; 3] = [
+    Duration::from_millis(100),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+];
+
+/// Measurements of one batch of output sent to the webhook.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchSample {
+    pub lines: usize,
+    /// Size of the rendered payload.
+    pub bytes: usize,
+    /// Time spent formatting and rendering the payload.
+    pub render: Duration,
+    /// Time from handing the payload to the transport until it was accepted or given up on.
+    pub latency: Duration,
+    /// Delivery attempts beyond the first, including fallback and shard endpoints.
+    pub retries: usize,
+}
+
+impl BatchSample {
+    /// The per-batch line printed at `-vv`.
+    pub fn describe(&self, index: usize, units: Units) -> String {
+        format!(
+            "[shell_hook] Batch {}: {}, {}, rendered in {:.1}ms, sent in {}, retries {}",
+            index,
+            units.count(self.lines as u64, "line"),
+            units.bytes(self.bytes as u64),
+            self.render.as_secs_f64() * 1000.0,
+            units.duration(self.latency),
+            units.number(self.retries as u64),
+        )
+    }
+}
+
+/// Percentiles of the time lines took from being read to being delivered.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LagSummary {
+    pub lines: u64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LagSummary {
+    pub fn describe(&self, units: Units) -> String {
+        let ms = |ms| units.duration(Duration::from_millis(ms));
+        format!(
+            "p50 {}, p95 {}, max {} ({})",
+            ms(self.p50_ms),
+            ms(self.p95_ms),
+            ms(self.max_ms),
+            units.count(self.lines, "line")
+        )
+    }
+}
+
+/// Batch measurements collected during a run for `-v`, and line lag for `-v`
+/// and `run --report`.
+#[derive(Clone, Default, Debug)]
+pub struct BatchStats {
+    samples: Arc<Mutex<Vec<BatchSample>>>,
+    /// Lines by their lag in whole milliseconds.
+    lags: Arc<Mutex<BTreeMap<u64, u64>>>,
+}
+
+impl BatchStats {
+    /// Records `sample` and returns its 1-based index.
+    pub fn record(&self, sample: BatchSample) -> usize {
+        let mut samples = self.samples.lock().unwrap();
+        samples.push(sample);
+        samples.len()
+    }
+
+    /// Records the lag of one line, from being read to being delivered.
+    pub fn record_lag(&self, lag: Duration) {
+        *self
+            .lags
+            .lock()
+            .unwrap()
+            .entry(lag.as_millis() as u64)
+            .or_default() += 1;
+    }
+
+    /// `None` if no lines were sent.
+    pub fn lag(&self) -> Option<LagSummary> {
+        let lags = self.lags.lock().unwrap();
+        let lines: u64 = lags.values().sum();
+        let percentile = |p: u64| {
+            // The lag of the line at rank ceil(p% of lines).
+            let rank = (lines * p).div_ceil(100).max(1);
+            let mut seen = 0;
+            lags.iter()
+                .find(|(_, &count)| {
+                    seen += count;
+                    seen >= rank
+                })
+                .map_or(0, |(&ms, _)| ms)
+        };
+        (lines > 0).then(|| LagSummary {
+            lines,
+            p50_ms: percentile(50),
+            p95_ms: percentile(95),
+            max_ms: lags.keys().next_back().copied().unwrap_or(0),
+        })
+    }
+
+    /// The end-of-run summary: totals, lines per batch against `buffer_size`,
+    /// a histogram of send latency, and line lag. `None` if nothing was sent.
+    pub fn summary(&self, units: Units, buffer_size: usize) -> Option<String> {
+        let samples = self.samples.lock().unwrap();
+        if samples.is_empty() {
+            return None;
+        }
+        let lines: usize = samples.iter().map(|s| s.lines).sum();
+        let bytes: usize = samples.iter().map(|s| s.bytes).sum();
+        let retries: usize = samples.iter().map(|s| s.retries).sum();
+        let min_lines = samples.iter().map(|s| s.lines).min().unwrap_or(0);
+        let max_lines = samples.iter().map(|s| s.lines).max().unwrap_or(0);
+
+        let mut buckets = [0usize; LATENCY_BUCKETS.len() + 1];
+        for sample in samples.iter() {
+            let bucket = LATENCY_BUCKETS
+                .iter()
+                .position(|&bound| sample.latency < bound)
+                .unwrap_or(LATENCY_BUCKETS.len());
+            buckets[bucket] += 1;
+        }
+        let mut histogram: Vec<String> = LATENCY_BUCKETS
+            .iter()
+            .zip(buckets)
+            .map(|(&bound, count)| format!("<{} {}", units.duration(bound), count))
+            .collect();
+        histogram.push(format!(
+            ">={} {}",
+            units.duration(LATENCY_BUCKETS[LATENCY_BUCKETS.len() - 1]),
+            buckets[LATENCY_BUCKETS.len()]
+        ));
+
+        let lag = self
+            .lag()
+            .map(|lag| format!("\n[shell_hook]   line lag: {}", lag.describe(units)))
+            .unwrap_or_default();
+        Some(format!(
+            "[shell_hook] Batch summary: batches {}, lines {}, payload {}, retries {}\n\
+             [shell_hook]   lines per batch: min {}, avg {:.1}, max {} (buffer size {})\n\
+             [shell_hook]   send latency: {}{}",
+            units.number(samples.len() as u64),
+            units.number(lines as u64),
+            units.bytes(bytes as u64),
+            units.number(retries as u64),
+            min_lines,
+            lines as f64 / samples.len() as f64,
+            max_lines,
+            buffer_size,
+            histogram.join(" | "),
+            lag,
+        ))
+    }
+}
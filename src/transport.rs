@@ -0,0 +1,501 @@
+use crate::cli::{Cli, ShardStrategy};
+use crate::encryption::EncryptionKey;
+use crate::oauth::OAuthTokenSource;
+use crate::report::{payload_sha256, redact_url, unix_millis, DeliveryAttempt, DeliveryLog};
+use crate::sigv4::{sign, AwsCredentials, SigV4Scope};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, Response, StatusCode, Url};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Delivers an already-rendered payload to its destination.
+///
+/// Transports only move bytes; they know nothing about webhook formats.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn deliver(&self, body: &[u8]) -> Result<()>;
+}
+
+/// POSTs payloads as JSON to a webhook URL.
+pub struct HttpTransport {
+    client: Client,
+    url: String,
+    log: Option<DeliveryLog>,
+    oauth: Option<Arc<OAuthTokenSource>>,
+    sigv4: Option<SigV4Scope>,
+}
+
+impl HttpTransport {
+    pub fn new(client: Client, url: impl Into<String>) -> Self {
+        Self {
+            client,
+            url: url.into(),
+            log: None,
+            oauth: None,
+            sigv4: None,
+        }
+    }
+
+    /// Records every delivery attempt in `log`.
+    pub fn with_log(mut self, log: DeliveryLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    /// Sends a Bearer token from `oauth` with each request, refreshing it once on a 401.
+    pub fn with_oauth(mut self, oauth: Arc<OAuthTokenSource>) -> Self {
+        self.oauth = Some(oauth);
+        self
+    }
+
+    /// Signs each request with AWS SigV4 for `scope`, using [`AwsCredentials::load`].
+    pub fn with_sigv4(mut self, scope: SigV4Scope) -> Self {
+        self.sigv4 = Some(scope);
+        self
+    }
+
+    async fn attempt(&self, body: &[u8], refresh_token: bool) -> Result<Response> {
+        let timestamp_ms = unix_millis();
+        let started = Instant::now();
+        let result = self.post(body, refresh_token).await;
+
+        if let Some(log) = &self.log {
+            log.record(DeliveryAttempt {
+                timestamp_ms,
+                destination: redact_url(&self.url),
+                status: result.as_ref().ok().map(|r| r.status().as_u16()),
+                latency_ms: started.elapsed().as_millis() as u64,
+                payload_sha256: payload_sha256(body),
+                error: match &result {
+                    Ok(response) => response.error_for_status_ref().err().map(|e| e.to_string()),
+                    Err(e) => Some(e.to_string()),
+                },
+            });
+        }
+        result
+    }
+
+    async fn post(&self, body: &[u8], refresh_token: bool) -> Result<Response> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_vec());
+        if let Some(oauth) = &self.oauth {
+            request = request.bearer_auth(oauth.token(refresh_token).await?);
+        }
+        if let Some(scope) = &self.sigv4 {
+            let url = Url::parse(&self.url)?;
+            let credentials = AwsCredentials::load()?;
+            let now = unix_millis() / 1000;
+            let signed = [("content-type", "application/json")];
+            for (name, value) in sign(&credentials, scope, "POST", &url, &signed, body, now)? {
+                request = request.header(name, value);
+            }
+        }
+        Ok(request.send().await?)
+    }
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn deliver(&self, body: &[u8]) -> Result<()> {
+        let mut response = self.attempt(body, false).await?;
+        if self.oauth.is_some() && response.status() == StatusCode::UNAUTHORIZED {
+            // The token may have been revoked or expired early; try once with a new one.
+            response = self.attempt(body, true).await?;
+        }
+        response.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Prints payloads instead of sending them.
+pub struct DryRunTransport;
+
+#[async_trait]
+impl Transport for DryRunTransport {
+    async fn deliver(&self, body: &[u8]) -> Result<()> {
+        println!(
+            "[shell_hook] Dry run: Would send payload: {}",
+            String::from_utf8_lossy(body)
+        );
+        Ok(())
+    }
+}
+
+/// Discards payloads, for runs that only notify locally or are silenced by `--nested`.
+pub struct NullTransport;
+
+#[async_trait]
+impl Transport for NullTransport {
+    async fn deliver(&self, _body: &[u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Encrypts payloads before handing them to another transport.
+pub struct EncryptingTransport {
+    key: EncryptionKey,
+    inner: Box<dyn Transport>,
+}
+
+impl EncryptingTransport {
+    pub fn new(key: EncryptionKey, inner: Box<dyn Transport>) -> Self {
+        Self { key, inner }
+    }
+}
+
+#[async_trait]
+impl Transport for EncryptingTransport {
+    async fn deliver(&self, body: &[u8]) -> Result<()> {
+        self.inner.deliver(&self.key.seal(body)?).await
+    }
+}
+
+/// Injects failures and latency into another transport, for `--simulate-*` testing.
+pub struct ChaosTransport {
+    inner: Box<dyn Transport>,
+    failure_rate: f64,
+    latency: Option<Duration>,
+    /// Where simulated failures are recorded, and the endpoint they are recorded against.
+    log: Option<(DeliveryLog, String)>,
+}
+
+impl ChaosTransport {
+    /// Fails `failure_rate` (0.0 to 1.0) of deliveries and delays each one by `latency`.
+    pub fn new(inner: Box<dyn Transport>, failure_rate: f64, latency: Option<Duration>) -> Self {
+        Self {
+            inner,
+            failure_rate,
+            latency,
+            log: None,
+        }
+    }
+
+    /// Records every simulated failure in `log` as an attempt on `url`, so they
+    /// show up in `--report` and `-vv` like real ones.
+    pub fn with_log(mut self, log: DeliveryLog, url: &str) -> Self {
+        self.log = Some((log, redact_url(url)));
+        self
+    }
+
+    fn should_fail(&self) -> bool {
+        let mut bytes = [0u8; 8];
+        SystemRandom::new().fill(&mut bytes).is_ok()
+            && (u64::from_le_bytes(bytes) as f64) < self.failure_rate * u64::MAX as f64
+    }
+}
+
+#[async_trait]
+impl Transport for ChaosTransport {
+    async fn deliver(&self, body: &[u8]) -> Result<()> {
+        let timestamp_ms = unix_millis();
+        let started = Instant::now();
+        if let Some(latency) = self.latency {
+            tokio::time::sleep(latency).await;
+        }
+        if self.should_fail() {
+            let error = "simulated webhook failure";
+            if let Some((log, destination)) = &self.log {
+                log.record(DeliveryAttempt {
+                    timestamp_ms,
+                    destination: destination.clone(),
+                    status: None,
+                    latency_ms: started.elapsed().as_millis() as u64,
+                    payload_sha256: payload_sha256(body),
+                    error: Some(error.to_string()),
+                });
+            }
+            return Err(anyhow!(error));
+        }
+        self.inner.deliver(body).await
+    }
+}
+
+/// Attempts a destination gets per delivery before a fallback is tried.
+const FALLBACK_ATTEMPTS: u32 = 3;
+/// The wait before the first retry, doubled before each one after it.
+const FALLBACK_BACKOFF: Duration = Duration::from_millis(250);
+/// How long a destination that used up its attempts is skipped before it is tried again.
+const CIRCUIT_COOLDOWN: Duration = Duration::from_secs(30);
+
+struct Destination {
+    transport: Box<dyn Transport>,
+    /// When the destination's circuit opened; `None` while it is healthy.
+    opened_at: Mutex<Option<Instant>>,
+}
+
+/// Tries each destination in order until one accepts the payload.
+///
+/// A destination is retried with backoff before the next one is used, so a
+/// transient 429 or 5xx never reaches a fallback such as an email bridge. Once a
+/// destination has failed permanently its circuit opens: deliveries go straight
+/// to the fallbacks, and after a cooldown a single probe checks whether it has
+/// recovered.
+pub struct FallbackTransport {
+    destinations: Vec<Destination>,
+    attempts: u32,
+    backoff: Duration,
+    cooldown: Duration,
+}
+
+impl FallbackTransport {
+    pub fn new(destinations: Vec<Box<dyn Transport>>) -> Self {
+        Self {
+            destinations: destinations
+                .into_iter()
+                .map(|transport| Destination {
+                    transport,
+                    opened_at: Mutex::new(None),
+                })
+                .collect(),
+            attempts: FALLBACK_ATTEMPTS,
+            backoff: FALLBACK_BACKOFF,
+            cooldown: CIRCUIT_COOLDOWN,
+        }
+    }
+
+    /// Sets the attempts per destination, the first retry's backoff and how long an
+    /// open circuit is kept before probing.
+    pub fn with_policy(mut self, attempts: u32, backoff: Duration, cooldown: Duration) -> Self {
+        self.attempts = attempts.max(1);
+        self.backoff = backoff;
+        self.cooldown = cooldown;
+        self
+    }
+
+    async fn deliver_with_retries(
+        &self,
+        destination: &Destination,
+        body: &[u8],
+        attempts: u32,
+    ) -> Result<()> {
+        let mut backoff = self.backoff;
+        let mut attempt = 1;
+        loop {
+            match destination.transport.deliver(body).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= attempts || !is_transient(&e) => return Err(e),
+                Err(_) => {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Whether a failed delivery may succeed if repeated: anything but a 4xx other than 429.
+fn is_transient(error: &anyhow::Error) -> bool {
+    match error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+    {
+        Some(status) => status == StatusCode::TOO_MANY_REQUESTS || !status.is_client_error(),
+        None => true,
+    }
+}
+
+#[async_trait]
+impl Transport for FallbackTransport {
+    async fn deliver(&self, body: &[u8]) -> Result<()> {
+        let mut failures = Vec::new();
+        let last = self.destinations.len().saturating_sub(1);
+        for (i, destination) in self.destinations.iter().enumerate() {
+            let opened_at = *destination.opened_at.lock().unwrap();
+            let attempts = match opened_at {
+                // Nothing to fall back to, so the last destination is always tried in full.
+                _ if i == last => self.attempts,
+                Some(at) if at.elapsed() < self.cooldown => {
+                    failures.push(format!("destination {}: circuit open", i + 1));
+                    continue;
+                }
+                Some(_) => 1,
+                None => self.attempts,
+            };
+            match self.deliver_with_retries(destination, body, attempts).await {
+                Ok(()) => {
+                    *destination.opened_at.lock().unwrap() = None;
+                    return Ok(());
+                }
+                Err(e) => {
+                    if i < last {
+                        *destination.opened_at.lock().unwrap() = Some(Instant::now());
+                    }
+                    failures.push(format!("destination {}: {}", i + 1, e));
+                }
+            }
+        }
+        Err(anyhow!("all destinations failed ({})", failures.join("; ")))
+    }
+}
+
+/// Consecutive failures after which an endpoint is skipped while others are healthy.
+const UNHEALTHY_AFTER: u32 = 3;
+
+struct Endpoint {
+    transport: Box<dyn Transport>,
+    consecutive_failures: AtomicU32,
+}
+
+/// Spreads deliveries over several equivalent endpoints.
+///
+/// A failed delivery moves on to the next endpoint, and endpoints that keep
+/// failing are skipped until every endpoint is in the same state.
+pub struct ShardedTransport {
+    endpoints: Vec<Endpoint>,
+    strategy: ShardStrategy,
+    /// Which endpoint a run is pinned to under [`ShardStrategy::RunId`].
+    run_key: u64,
+    next: AtomicUsize,
+}
+
+impl ShardedTransport {
+    pub fn new(endpoints: Vec<Box<dyn Transport>>, strategy: ShardStrategy, run_id: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        run_id.hash(&mut hasher);
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|transport| Endpoint {
+                    transport,
+                    consecutive_failures: AtomicU32::new(0),
+                })
+                .collect(),
+            strategy,
+            run_key: hasher.finish(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Endpoint indices in the order they should be tried for one delivery.
+    fn attempt_order(&self) -> Vec<usize> {
+        let count = self.endpoints.len();
+        let start = match self.strategy {
+            ShardStrategy::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed),
+            ShardStrategy::RunId => self.run_key as usize,
+        } % count;
+        let (healthy, unhealthy): (Vec<usize>, Vec<usize>) = (0..count)
+            .map(|offset| (start + offset) % count)
+            .partition(|&i| {
+                self.endpoints[i]
+                    .consecutive_failures
+                    .load(Ordering::Relaxed)
+                    < UNHEALTHY_AFTER
+            });
+        healthy.into_iter().chain(unhealthy).collect()
+    }
+}
+
+#[async_trait]
+impl Transport for ShardedTransport {
+    async fn deliver(&self, body: &[u8]) -> Result<()> {
+        let mut last_error = None;
+        for i in self.attempt_order() {
+            let endpoint = &self.endpoints[i];
+            match endpoint.transport.deliver(body).await {
+                Ok(()) => {
+                    endpoint.consecutive_failures.store(0, Ordering::Relaxed);
+                    return Ok(());
+                }
+                Err(e) => {
+                    endpoint
+                        .consecutive_failures
+                        .fetch_add(1, Ordering::Relaxed);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow!("no endpoints configured")))
+    }
+}
+
+/// Builds the transport described by the command-line options.
+///
+/// Callers must have checked that a webhook URL is present unless this is a dry run
+/// or `--notify` leaves the webhook out.
+/// Every HTTP delivery attempt is recorded in `log`, and payloads are encrypted when
+/// `--encrypt-key` is set.
+pub fn transport_for(
+    cli: &Cli,
+    client: &Client,
+    log: &DeliveryLog,
+    run_id: &str,
+) -> Box<dyn Transport> {
+    let transport = plain_transport_for(cli, client, log, run_id);
+    match &cli.encrypt_key {
+        Some(key) => Box::new(EncryptingTransport::new(key.clone(), transport)),
+        None => transport,
+    }
+}
+
+fn plain_transport_for(
+    cli: &Cli,
+    client: &Client,
+    log: &DeliveryLog,
+    run_id: &str,
+) -> Box<dyn Transport> {
+    if !cli.notifies_webhook() && (!cli.dry_run || cli.nesting().is_some()) {
+        return Box::new(NullTransport);
+    }
+    let url = match (&cli.webhook_url, cli.dry_run) {
+        (Some(url), false) => url,
+        _ => return Box::new(DryRunTransport),
+    };
+    let oauth = cli.oauth_token_url.as_ref().map(|token_url| {
+        Arc::new(OAuthTokenSource::new(
+            client.clone(),
+            token_url,
+            cli.oauth_client_id.clone().unwrap_or_default(),
+            cli.oauth_client_secret
+                .as_ref()
+                .map_or_else(String::new, |secret| secret.expose().to_string()),
+            cli.oauth_scope.clone(),
+        ))
+    });
+    let http = |url: &String| {
+        let mut transport = HttpTransport::new(client.clone(), url).with_log(log.clone());
+        if let Some(oauth) = &oauth {
+            transport = transport.with_oauth(oauth.clone());
+        }
+        if let Some(scope) = &cli.aws_sigv4 {
+            transport = transport.with_sigv4(scope.clone());
+        }
+        if cli.simulate_webhook_failure_rate.is_some() || cli.simulate_latency.is_some() {
+            return Box::new(
+                ChaosTransport::new(
+                    Box::new(transport),
+                    cli.simulate_webhook_failure_rate.unwrap_or(0.0),
+                    cli.simulate_latency,
+                )
+                .with_log(log.clone(), url),
+            ) as Box<dyn Transport>;
+        }
+        Box::new(transport) as Box<dyn Transport>
+    };
+
+    let primary = if cli.shard_urls.is_empty() {
+        http(url)
+    } else {
+        let endpoints = std::iter::once(url)
+            .chain(&cli.shard_urls)
+            .map(http)
+            .collect();
+        Box::new(ShardedTransport::new(endpoints, cli.shard_by, run_id))
+    };
+
+    if cli.fallback_webhook_urls.is_empty() {
+        return primary;
+    }
+    let mut destinations = vec![primary];
+    destinations.extend(cli.fallback_webhook_urls.iter().map(http));
+    Box::new(FallbackTransport::new(destinations))
+}
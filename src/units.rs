@@ -0,0 +1,74 @@
+use clap::ValueEnum;
+use std::time::Duration;
+
+/// How durations, byte counts and line counts are written in messages.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Units {
+    /// `1h 02m`, `3.4 MB`, `12,345 lines`.
+    #[default]
+    Human,
+    /// Plain numbers for scripts that parse messages: `3720s`, `3565158B`, `12345 lines`.
+    Raw,
+}
+
+impl Units {
+    /// Formats a duration, e.g. `850ms`, `12s`, `3m 05s` or `1h 02m`.
+    pub fn duration(self, duration: Duration) -> String {
+        let secs = duration.as_secs();
+        match self {
+            Units::Raw => format!("{}s", secs),
+            Units::Human if secs == 0 && duration.as_millis() > 0 => {
+                format!("{}ms", duration.as_millis())
+            }
+            Units::Human if secs < 60 => format!("{}s", secs),
+            Units::Human if secs < 3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+            Units::Human => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+        }
+    }
+
+    /// Formats a duration given in whole seconds.
+    pub fn seconds(self, secs: u64) -> String {
+        self.duration(Duration::from_secs(secs))
+    }
+
+    /// Formats a byte count with the binary multiples `parse_size` reads, e.g. `3.4 MB`.
+    pub fn bytes(self, bytes: u64) -> String {
+        if self == Units::Raw {
+            return format!("{}B", bytes);
+        }
+        let Some((unit, size)) = [("GB", 1u64 << 30), ("MB", 1 << 20), ("KB", 1 << 10)]
+            .into_iter()
+            .find(|&(_, size)| bytes >= size)
+        else {
+            return format!("{} B", bytes);
+        };
+        let value = format!("{:.1}", bytes as f64 / size as f64);
+        format!("{} {}", value.strip_suffix(".0").unwrap_or(&value), unit)
+    }
+
+    /// Formats a plain number, e.g. `12,345`.
+    pub fn number(self, n: u64) -> String {
+        match self {
+            Units::Raw => n.to_string(),
+            Units::Human => group_thousands(n),
+        }
+    }
+
+    /// Formats a count followed by `noun`, pluralized with a trailing `s`, e.g. `12,345 lines`.
+    pub fn count(self, count: u64, noun: &str) -> String {
+        let plural = if count == 1 { "" } else { "s" };
+        format!("{} {}{}", self.number(count), noun, plural)
+    }
+}
+
+fn group_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
@@ -0,0 +1,112 @@
+use crate::app::{echo_status, format_with_title, AppContext};
+use crate::webhook::send_message;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// How a secret variable's value is shown in the session's messages.
+pub const REDACTED: &str = "<redacted>";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Variable {
+    value: String,
+    secret: bool,
+}
+
+/// Variables set with `:set` and `:secret` in an interactive session.
+///
+/// They reach commands as environment variables, so `sh -c` expands `$NAME`
+/// with its usual quoting rules and secrets never appear in the command line
+/// that start messages echo.
+#[derive(Clone, Debug, Default)]
+pub struct SessionVariables {
+    vars: BTreeMap<String, Variable>,
+}
+
+impl SessionVariables {
+    pub fn set(&mut self, name: &str, value: &str, secret: bool) {
+        let variable = Variable {
+            value: value.to_string(),
+            secret,
+        };
+        self.vars.insert(name.to_string(), variable);
+    }
+
+    /// Returns `false` if no such variable was set.
+    pub fn unset(&mut self, name: &str) -> bool {
+        self.vars.remove(name).is_some()
+    }
+
+    /// The variables to add to a command's environment.
+    pub fn env(&self) -> Vec<(String, String)> {
+        self.vars
+            .iter()
+            .map(|(name, var)| (name.clone(), var.value.clone()))
+            .collect()
+    }
+
+    /// `NAME=value`, with the value of a secret replaced by [`REDACTED`].
+    pub fn describe(&self, name: &str) -> Option<String> {
+        let var = self.vars.get(name)?;
+        let value = if var.secret { REDACTED } else { &var.value };
+        Some(format!("{}={}", name, value))
+    }
+
+    /// Handles `:set NAME=value`, `:secret NAME=value`, `:unset NAME` and a bare
+    /// `:set` that lists the variables. Changes are posted to the webhook so the
+    /// session's messages show what later commands ran with.
+    ///
+    /// Returns `false` if the line is not a variable command.
+    pub async fn handle_line(&mut self, context: &Arc<AppContext>, line: &str) -> bool {
+        let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let message = match word {
+            ":set" if rest.is_empty() => {
+                for name in self.vars.keys() {
+                    println!("{}", self.describe(name).unwrap_or_default());
+                }
+                return true;
+            }
+            ":set" | ":secret" => match parse_assignment(rest) {
+                Ok((name, value)) => {
+                    self.set(name, value, word == ":secret");
+                    format!("🔧 Set {}", self.describe(name).unwrap_or_default())
+                }
+                Err(e) => {
+                    eprintln!("[shell_hook] {}", e);
+                    return true;
+                }
+            },
+            ":unset" if self.unset(rest) => format!("🔧 Unset {}", rest),
+            ":unset" => {
+                eprintln!("[shell_hook] No such variable: {}", rest);
+                return true;
+            }
+            _ => return false,
+        };
+
+        let message = format_with_title(&context.cli, &message);
+        echo_status(&context.cli, &message, false);
+        if let Err(e) = send_message(context, &message).await {
+            context
+                .warn(format!("Failed to send variable change: {}", e))
+                .await;
+        }
+        true
+    }
+}
+
+/// Splits `NAME=value`, where NAME is a valid shell variable name.
+pub fn parse_assignment(s: &str) -> Result<(&str, &str), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| "Usage: :set NAME=value".to_string())?;
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("Invalid variable name: '{}'", name));
+    }
+    Ok((name, value))
+}
@@ -0,0 +1,117 @@
+use crate::runs::current_boot_id;
+use ring::hmac;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Name of the object `--watermark` adds to every payload.
+pub const WATERMARK_FIELD: &str = "shell_hook";
+const HOST_ID_KEY: &[u8] = b"shell_hook watermark";
+
+/// Identifies where a payload came from, so a collector fed by many hosts can
+/// detect gaps, duplicates and reordering.
+#[derive(Debug)]
+pub struct Watermark {
+    host: Option<String>,
+    host_id: Option<String>,
+    boot_id: Option<String>,
+    run_id: String,
+    next_seq: AtomicU64,
+}
+
+#[derive(Serialize)]
+struct Stamp<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    boot_id: Option<&'a str>,
+    run_id: &'a str,
+    seq: u64,
+}
+
+impl Watermark {
+    /// Watermarks payloads of run `run_id` on this host, numbering them from 1.
+    pub fn new(run_id: String) -> Self {
+        Self::with_host(host_name(), machine_id(), current_boot_id(), run_id)
+    }
+
+    pub fn with_host(
+        host: Option<String>,
+        host_id: Option<String>,
+        boot_id: Option<String>,
+        run_id: String,
+    ) -> Self {
+        Self {
+            host,
+            host_id,
+            boot_id,
+            run_id,
+            next_seq: AtomicU64::new(1),
+        }
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Adds the watermark to a rendered payload and advances the sequence.
+    ///
+    /// Retries resend the stamped body, so a repeated `seq` marks a duplicate
+    /// delivery rather than a new message.
+    pub fn stamp(&self, payload: &mut Value) {
+        let Value::Object(fields) = payload else {
+            return;
+        };
+        let stamp = Stamp {
+            host: self.host.as_deref(),
+            host_id: self.host_id.as_deref(),
+            boot_id: self.boot_id.as_deref(),
+            run_id: &self.run_id,
+            seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+        };
+        if let Ok(stamp) = serde_json::to_value(stamp) {
+            fields.insert(WATERMARK_FIELD.to_string(), stamp);
+        }
+    }
+}
+
+/// Returns this machine's host name.
+pub fn host_name() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    // SAFETY: the buffer is valid for its whole length, and one byte is held back
+    // so the result is always NUL-terminated.
+    let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len() - 1) };
+    if result != 0 {
+        return None;
+    }
+    let end = buffer.iter().position(|&b| b == 0)?;
+    Some(String::from_utf8_lossy(&buffer[..end]).into_owned()).filter(|name| !name.is_empty())
+}
+
+/// Returns an identifier derived from the one systemd and D-Bus give this
+/// installation, which unlike the host name survives renames and is unique
+/// across a fleet.
+fn machine_id() -> Option<String> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .find(|id| !id.is_empty())
+        .map(|id| host_id(&id))
+}
+
+/// Hashes a machine ID for sending: machine-id(5) asks that the raw ID never
+/// leaves the host, only an application-specific keyed hash of it.
+pub fn host_id(machine_id: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, HOST_ID_KEY);
+    let tag = hmac::sign(&key, machine_id.as_bytes());
+    tag.as_ref()[..16]
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
@@ -1,44 +1,155 @@
-use crate::app::AppContext;
-use crate::cli::WebhookFormat;
+use crate::app::{format_with_title, AppContext};
+use crate::batching::{Batching, FlushTrigger};
+use crate::cli::Cli;
+use crate::command::RunControl;
+use crate::language::{detect, Language};
 use crate::message::StreamMessage;
+use crate::render::{highlight_matches, mark_diff};
+use crate::runs::RunStore;
+use crate::table::{align_rows, segment_lines, Segment};
+use crate::telemetry::BatchSample;
 use anyhow::Result;
-use reqwest::Client;
-use serde_json::{json, Value};
+use serde_json::Value;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc::Receiver;
+use tokio::time::Instant;
+
+/// Field `--forward-warnings` adds to warning payloads.
+pub const SOURCE_FIELD: &str = "source";
 
 pub async fn run_webhook_sender(
     context: Arc<AppContext>,
-    mut rx: Receiver<StreamMessage>,
+    rx: Receiver<StreamMessage>,
 ) -> Result<()> {
-    let mut buffer: Vec<String> = Vec::new();
-    let buffer_timeout = Duration::from_secs_f64(context.cli.buffer_timeout);
+    send_stream(context, rx, Vec::new(), Vec::new()).await
+}
 
+/// Like [`run_webhook_sender`], but holds the start message and output back for
+/// `window`. If the command finishes within it, everything is left in `control`
+/// to go out with the final message; otherwise sending continues as usual.
+pub async fn run_coalescing_sender(
+    context: Arc<AppContext>,
+    mut rx: Receiver<StreamMessage>,
+    start_message: String,
+    window: Duration,
+    control: Arc<RunControl>,
+) -> Result<()> {
+    let deadline = Instant::now() + window;
+    let mut held = Vec::new();
+    let mut read_times = Vec::new();
     loop {
-        match tokio::time::timeout(buffer_timeout, rx.recv()).await {
-            Ok(Some(StreamMessage::Line(line))) => {
-                buffer.push(line);
-                if buffer.len() >= context.cli.buffer_size {
-                    send_buffered_lines(&context, &mut buffer).await?;
+        match tokio::time::timeout_at(deadline, rx.recv()).await {
+            Ok(Some(StreamMessage::Flush)) => {}
+            Ok(Some(StreamMessage::CommandFinished)) | Ok(None) => {
+                let mut text = start_message;
+                if !held.is_empty() {
+                    text.push('\n');
+                    text.push_str(&format_batch(&context, &held));
+                }
+                control.coalesce(text);
+                return Ok(());
+            }
+            Ok(Some(message)) => {
+                if let Some((line, read_at)) = message.into_line() {
+                    held.push(line);
+                    read_times.push(read_at);
                 }
             }
+            Err(_) => {
+                if let Err(e) = send_message(&context, &start_message).await {
+                    context
+                        .warn(format!("Failed to send start message: {}", e))
+                        .await;
+                }
+                return send_stream(context, rx, held, read_times).await;
+            }
+        }
+    }
+}
+
+/// Batches lines from `rx`, starting with any already in `buffer`, which were
+/// read at `read_times`.
+async fn send_stream(
+    context: Arc<AppContext>,
+    mut rx: Receiver<StreamMessage>,
+    mut buffer: Vec<String>,
+    mut read_times: Vec<std::time::Instant>,
+) -> Result<()> {
+    let buffer_timeout = Duration::from_secs_f64(context.cli.buffer_timeout);
+    let mut batching = if context.cli.adaptive_batching {
+        // Leave room for markup and the title added around the lines.
+        let max_bytes = context.renderer.max_message_bytes() * 9 / 10;
+        Batching::adaptive(context.cli.buffer_size, buffer_timeout, max_bytes)
+    } else {
+        Batching::fixed(context.cli.buffer_size, buffer_timeout)
+    };
+    let mut budget = ByteBudget::new(context.cli.max_total_webhook_bytes);
+    let mut buffered_bytes: usize = buffer.iter().map(|line| line.len() + 1).sum();
+
+    loop {
+        match tokio::time::timeout(batching.interval(), rx.recv()).await {
             Ok(Some(StreamMessage::Flush)) => {
-                send_buffered_lines(&context, &mut buffer).await?;
+                send_within_budget(&context, &mut buffer, &mut budget).await?;
+                record_lag(&context, &mut read_times);
+                buffered_bytes = 0;
             }
             Ok(Some(StreamMessage::CommandFinished)) => {
-                send_buffered_lines(&context, &mut buffer).await?;
+                send_within_budget(&context, &mut buffer, &mut budget).await?;
+                record_lag(&context, &mut read_times);
                 break;
             }
+            Ok(Some(message)) => {
+                let Some((line, read_at)) = message.into_line() else {
+                    continue;
+                };
+                if !buffer.is_empty() && !batching.fits(buffered_bytes, line.len()) {
+                    send_batch(
+                        &context,
+                        &mut buffer,
+                        &mut budget,
+                        &mut batching,
+                        FlushTrigger::Full,
+                    )
+                    .await?;
+                    record_lag(&context, &mut read_times);
+                    buffered_bytes = 0;
+                }
+                buffered_bytes += line.len() + 1;
+                buffer.push(line);
+                read_times.push(read_at);
+                if buffer.len() >= batching.lines() {
+                    send_batch(
+                        &context,
+                        &mut buffer,
+                        &mut budget,
+                        &mut batching,
+                        FlushTrigger::Full,
+                    )
+                    .await?;
+                    record_lag(&context, &mut read_times);
+                    buffered_bytes = 0;
+                }
+            }
             Ok(None) => {
                 // Channel closed, send any remaining lines
-                send_buffered_lines(&context, &mut buffer).await?;
+                send_within_budget(&context, &mut buffer, &mut budget).await?;
+                record_lag(&context, &mut read_times);
                 break;
             }
             Err(_) => {
                 // Timeout elapsed, send buffered lines
                 if !buffer.is_empty() {
-                    send_buffered_lines(&context, &mut buffer).await?;
+                    send_batch(
+                        &context,
+                        &mut buffer,
+                        &mut budget,
+                        &mut batching,
+                        FlushTrigger::Timeout,
+                    )
+                    .await?;
+                    record_lag(&context, &mut read_times);
+                    buffered_bytes = 0;
                 }
             }
         }
@@ -47,6 +158,99 @@ pub async fn run_webhook_sender(
     Ok(())
 }
 
+/// Records how long each line of the batch just sent took from being read to
+/// being delivered (or given up on).
+fn record_lag(context: &AppContext, read_times: &mut Vec<std::time::Instant>) {
+    for read_at in read_times.drain(..) {
+        context.batches.record_lag(read_at.elapsed());
+    }
+}
+
+/// Sends the buffer and lets `batching` adapt to how long that took.
+async fn send_batch(
+    context: &Arc<AppContext>,
+    buffer: &mut Vec<String>,
+    budget: &mut ByteBudget,
+    batching: &mut Batching,
+    trigger: FlushTrigger,
+) -> Result<()> {
+    let lines = buffer.len();
+    let started = Instant::now();
+    let result = send_within_budget(context, buffer, budget).await;
+    batching.observe(trigger, lines, started.elapsed());
+    result
+}
+
+/// How much streamed output may still be sent under `--max-total-webhook-bytes`.
+struct ByteBudget {
+    limit: Option<u64>,
+    used: u64,
+    exhausted: bool,
+}
+
+impl ByteBudget {
+    fn new(limit: Option<u64>) -> Self {
+        Self {
+            limit,
+            used: 0,
+            exhausted: false,
+        }
+    }
+}
+
+/// Sends the buffered lines that fit in the budget. The first time the budget
+/// runs out, the lines that fit are followed by a single truncation notice and
+/// everything after that is dropped.
+async fn send_within_budget(
+    context: &Arc<AppContext>,
+    buffer: &mut Vec<String>,
+    budget: &mut ByteBudget,
+) -> Result<()> {
+    let Some(limit) = budget.limit else {
+        return send_buffered_lines(context, buffer).await;
+    };
+    if budget.exhausted {
+        buffer.clear();
+        return Ok(());
+    }
+
+    let mut fitting = 0;
+    for line in buffer.iter() {
+        let cost = line.len() as u64 + 1;
+        if budget.used + cost > limit {
+            budget.exhausted = true;
+            break;
+        }
+        budget.used += cost;
+        fitting += 1;
+    }
+    buffer.truncate(fitting);
+    send_buffered_lines(context, buffer).await?;
+
+    if budget.exhausted {
+        let notice = format_with_title(
+            &context.cli,
+            &format!(
+                "✂️ Output truncated after {}; {}",
+                context.cli.units.bytes(limit),
+                full_output_hint(&context.cli)
+            ),
+        );
+        send_message(context, &notice).await?;
+    }
+    Ok(())
+}
+
+fn full_output_hint(cli: &Cli) -> String {
+    match &cli.run_id {
+        Some(id) => format!(
+            "see log file {}.",
+            RunStore::from_cli(cli).log_path(id).display()
+        ),
+        None => "the command keeps running and its local output is complete.".to_string(),
+    }
+}
+
 pub async fn send_buffered_lines(
     context: &Arc<AppContext>,
     buffer: &mut Vec<String>,
@@ -54,47 +258,114 @@ pub async fn send_buffered_lines(
     if buffer.is_empty() {
         return Ok(());
     }
-    let message = buffer.join("\n");
-    let result = send_message(context, &message).await;
+    let lines = buffer.len();
+    let render_started = Instant::now();
+    let body = render_body(context, &format_batch(context, buffer));
     buffer.clear();
+    let body = body?;
+    let render = render_started.elapsed();
+
+    let attempts_before = context.deliveries.len();
+    let sent = Instant::now();
+    let result = deliver(context, &body).await;
+    if context.cli.verbose > 0 {
+        let sample = BatchSample {
+            lines,
+            bytes: body.len(),
+            render,
+            latency: sent.elapsed(),
+            retries: context
+                .deliveries
+                .len()
+                .saturating_sub(attempts_before)
+                .saturating_sub(1),
+        };
+        let index = context.batches.record(sample.clone());
+        if context.cli.verbose > 1 {
+            eprintln!("{}", sample.describe(index, context.cli.units));
+        }
+    }
     result
 }
 
+/// Joins a batch of output lines into one message, applying format-specific markup.
+pub fn format_batch(context: &AppContext, lines: &[String]) -> String {
+    let renderer = context.renderer.as_ref();
+    if context.cli.diff_markup && detect(lines) == Some(Language::Diff) {
+        return renderer.code_block(&mark_diff(lines).join("\n"));
+    }
+    if context.cli.code_fence {
+        let text = lines.join("\n");
+        return match detect(lines) {
+            Some(language) => renderer.tagged_code_block(language, &text),
+            None => renderer.code_block(&text),
+        };
+    }
+    let format_line = |line: &String| {
+        let line = match &context.linker {
+            Some(linker) => linker.link_references(renderer, line),
+            None => line.clone(),
+        };
+        match &context.cli.highlight {
+            Some(pattern) => highlight_matches(renderer, pattern, &line),
+            None => line,
+        }
+    };
+
+    let Some(mode) = context.cli.table else {
+        return lines.iter().map(format_line).collect::<Vec<_>>().join("\n");
+    };
+    segment_lines(lines, mode)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(line) => format_line(&line),
+            Segment::Table(rows) => renderer.code_block(&align_rows(&rows).join("\n")),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders a message for the configured format and hands it to the transport.
 pub async fn send_message(context: &Arc<AppContext>, message: &str) -> Result<()> {
-    let payload = create_payload(message, &context.cli.format);
-    send_payload(
-        &context.client,
-        context.cli.webhook_url.as_deref(),
-        &payload,
-        context.cli.dry_run,
-    )
-    .await
+    let body = render_body(context, message)?;
+    deliver(context, &body).await
 }
 
-pub fn create_payload(message: &str, format: &WebhookFormat) -> Value {
-    match format {
-        WebhookFormat::Slack | WebhookFormat::GoogleChat => json!({ "text": message }),
+/// Delivers a rendered payload, warning if it took retries to get through.
+async fn deliver(context: &AppContext, body: &[u8]) -> Result<()> {
+    // Counts failures of concurrent deliveries too; close enough for a health signal.
+    let failures = context.deliveries.failures();
+    let result = context.transport.deliver(body).await;
+    let retried = context.deliveries.failures().saturating_sub(failures);
+    if result.is_ok() && retried > 0 {
+        let warning = format!("Message delivered after {} failed attempts", retried);
+        context.warn(warning).await;
     }
+    result
 }
 
-pub async fn send_payload(
-    client: &Client,
-    webhook_url: Option<&str>,
-    payload: &Value,
-    dry_run: bool,
-) -> Result<()> {
-    if dry_run {
-        println!("[shell_hook] Dry run: Would send payload: {}", payload);
-        return Ok(());
+/// Sends a `--forward-warnings` warning, tagged with [`SOURCE_FIELD`] so a
+/// collector can tell it apart from the command's output.
+pub async fn send_warning(context: &AppContext, warning: &str) -> Result<()> {
+    let message = format_with_title(&context.cli, &format!("⚠️ shell_hook: {}", warning));
+    let mut payload = context.renderer.render(&message);
+    if let Value::Object(fields) = &mut payload {
+        fields.insert(SOURCE_FIELD.to_string(), Value::from("shell_hook"));
+    }
+    if let Some(watermark) = &context.watermark {
+        watermark.stamp(&mut payload);
     }
+    // Straight to the transport: a retry warning about this one would loop.
+    context
+        .transport
+        .deliver(&serde_json::to_vec(&payload)?)
+        .await
+}
 
-    if let Some(url) = webhook_url {
-        client
-            .post(url)
-            .json(payload)
-            .send()
-            .await?
-            .error_for_status()?;
+fn render_body(context: &AppContext, message: &str) -> Result<Vec<u8>> {
+    let mut payload = context.renderer.render(message);
+    if let Some(watermark) = &context.watermark {
+        watermark.stamp(&mut payload);
     }
-    Ok(())
+    Ok(serde_json::to_vec(&payload)?)
 }
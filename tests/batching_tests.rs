@@ -0,0 +1,54 @@
+use shell_hook::batching::{Batching, FlushTrigger};
+use std::time::Duration;
+
+const FAST: Duration = Duration::from_millis(10);
+
+#[test]
+fn test_fixed_batching_never_changes() {
+    let mut batching = Batching::fixed(10, Duration::from_secs(2));
+    batching.observe(FlushTrigger::Full, 10, Duration::from_secs(5));
+    assert_eq!(batching.lines(), 10);
+    assert_eq!(batching.interval(), Duration::from_secs(2));
+    assert!(batching.fits(usize::MAX - 1, 100));
+}
+
+#[test]
+fn test_adaptive_batching_grows_with_fast_output() {
+    let mut batching = Batching::adaptive(10, Duration::from_secs(2), 4096);
+    batching.observe(FlushTrigger::Full, 10, FAST);
+    assert_eq!(batching.lines(), 20);
+    assert_eq!(batching.interval(), Duration::from_secs(2));
+    for _ in 0..10 {
+        batching.observe(FlushTrigger::Full, batching.lines(), FAST);
+    }
+    assert_eq!(batching.lines(), 500);
+}
+
+#[test]
+fn test_adaptive_batching_backs_off_for_slow_endpoint() {
+    let mut batching = Batching::adaptive(10, Duration::from_secs(2), 4096);
+    batching.observe(FlushTrigger::Timeout, 3, Duration::from_millis(1500));
+    assert_eq!(batching.lines(), 20);
+    assert_eq!(batching.interval(), Duration::from_secs(4));
+    for _ in 0..10 {
+        batching.observe(FlushTrigger::Timeout, 1, Duration::from_secs(60));
+    }
+    assert_eq!(batching.interval(), Duration::from_secs(30));
+}
+
+#[test]
+fn test_adaptive_batching_shrinks_back_to_configured_values() {
+    let mut batching = Batching::adaptive(10, Duration::from_secs(2), 4096);
+    batching.observe(FlushTrigger::Timeout, 3, Duration::from_millis(1500));
+    batching.observe(FlushTrigger::Timeout, 1, FAST);
+    batching.observe(FlushTrigger::Timeout, 1, FAST);
+    assert_eq!(batching.lines(), 10);
+    assert_eq!(batching.interval(), Duration::from_secs(2));
+}
+
+#[test]
+fn test_adaptive_batching_respects_message_size_limit() {
+    let batching = Batching::adaptive(10, Duration::from_secs(2), 4096);
+    assert!(batching.fits(3000, 1000));
+    assert!(!batching.fits(3500, 1000));
+}
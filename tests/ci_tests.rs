@@ -0,0 +1,63 @@
+use shell_hook::ci::CiContext;
+use shell_hook::render::SlackRenderer;
+use std::collections::HashMap;
+
+fn detect(vars: &[(&str, &str)]) -> Option<CiContext> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    CiContext::detect_with(|name| vars.get(name).cloned())
+}
+
+#[test]
+fn test_detect_github_actions() {
+    let ci = detect(&[
+        ("GITHUB_ACTIONS", "true"),
+        ("GITHUB_SERVER_URL", "https://github.com"),
+        ("GITHUB_REPOSITORY", "r0king/shell_hook"),
+        ("GITHUB_RUN_ID", "42"),
+        ("GITHUB_WORKFLOW", "CI"),
+        ("GITHUB_JOB", "test"),
+        ("GITHUB_ACTOR", "octocat"),
+    ])
+    .unwrap();
+    assert_eq!(
+        ci.describe(&SlackRenderer),
+        "GitHub Actions <https://github.com/r0king/shell_hook/actions/runs/42|CI / test>, by octocat"
+    );
+}
+
+#[test]
+fn test_detect_gitlab_buildkite_and_jenkins() {
+    let gitlab = detect(&[
+        ("GITLAB_CI", "true"),
+        ("CI_JOB_NAME", "build"),
+        ("CI_JOB_URL", "https://gitlab.example.com/-/jobs/7"),
+        ("GITLAB_USER_LOGIN", "dev"),
+    ])
+    .unwrap();
+    assert_eq!(gitlab.provider, "GitLab CI");
+    assert_eq!(gitlab.job.as_deref(), Some("build"));
+    assert_eq!(gitlab.actor.as_deref(), Some("dev"));
+
+    let buildkite = detect(&[
+        ("BUILDKITE", "true"),
+        ("BUILDKITE_PIPELINE_SLUG", "deploy"),
+        (
+            "BUILDKITE_BUILD_URL",
+            "https://buildkite.com/org/deploy/builds/3",
+        ),
+    ])
+    .unwrap();
+    assert_eq!(buildkite.job.as_deref(), Some("deploy"));
+
+    let jenkins = detect(&[("JENKINS_URL", "https://ci/"), ("JOB_NAME", "nightly")]).unwrap();
+    assert_eq!(jenkins.describe(&SlackRenderer), "Jenkins nightly");
+}
+
+#[test]
+fn test_no_ci_detected() {
+    assert_eq!(detect(&[]), None);
+    assert_eq!(detect(&[("GITHUB_ACTIONS", "false")]), None);
+}
@@ -0,0 +1,131 @@
+use shell_hook::cleanup::Sweep;
+use shell_hook::groups::GroupStore;
+use shell_hook::runs::{current_boot_id, unix_now, RunState, RunStore};
+use std::path::PathBuf;
+use std::process::Command;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "shell_hook_cleanup_{}_{}",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_sweep_finds_leftovers_but_not_live_runs() {
+    let dir = temp_dir("sweep");
+    let runs = RunStore::new(dir.join("runs"));
+    let mut finished = runs.create("make backup").unwrap();
+    finished.state = RunState::Finished;
+    finished.finished_at = Some(unix_now());
+    runs.save(&finished).unwrap();
+    let mut running = runs.create("sleep 600").unwrap();
+    running.state = RunState::Running;
+    running.pid = Some(std::process::id());
+    running.boot_id = None;
+    runs.save(&running).unwrap();
+    std::fs::create_dir_all(runs.run_dir("half-created")).unwrap();
+
+    let groups = GroupStore::new(dir.join("groups"));
+    let group = groups.create("Release 1.2").unwrap();
+
+    let temp = dir.join("tmp");
+    std::fs::create_dir_all(temp.join("shell_hook_run_0badf00d")).unwrap();
+    std::fs::create_dir_all(temp.join("someone_else")).unwrap();
+
+    let sweep = |cutoff| {
+        Sweep::new(
+            RunStore::new(dir.join("runs")),
+            GroupStore::new(dir.join("groups")),
+            temp.clone(),
+            cutoff,
+        )
+        .find()
+        .unwrap()
+    };
+
+    let found = sweep(unix_now() + 60);
+    let mut paths: Vec<PathBuf> = found.iter().map(|leftover| leftover.path.clone()).collect();
+    paths.sort();
+    let mut expected = vec![
+        runs.run_dir(&finished.id),
+        runs.run_dir("half-created"),
+        groups.group_dir(&group.id),
+        temp.join("shell_hook_run_0badf00d"),
+    ];
+    expected.sort();
+    assert_eq!(paths, expected);
+    assert!(found
+        .iter()
+        .any(|leftover| leftover.description == format!("run {} (make backup)", finished.id)));
+
+    assert_eq!(sweep(0), vec![]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_sweep_finds_run_whose_worker_died_on_this_boot() {
+    let dir = temp_dir("orphaned");
+    let runs = RunStore::new(dir.join("runs"));
+    let mut child = Command::new("true").spawn().unwrap();
+    let dead_pid = child.id();
+    child.wait().unwrap();
+
+    let mut crashed = runs.create("make deploy").unwrap();
+    crashed.state = RunState::Running;
+    crashed.pid = Some(dead_pid);
+    crashed.boot_id = current_boot_id();
+    runs.save(&crashed).unwrap();
+    let mut live = runs.create("sleep 600").unwrap();
+    live.state = RunState::Running;
+    live.pid = Some(std::process::id());
+    live.boot_id = current_boot_id();
+    runs.save(&live).unwrap();
+
+    let found = Sweep::new(
+        RunStore::new(dir.join("runs")),
+        GroupStore::new(dir.join("groups")),
+        dir.join("tmp"),
+        unix_now() + 60,
+    )
+    .find()
+    .unwrap();
+    let paths: Vec<PathBuf> = found.into_iter().map(|leftover| leftover.path).collect();
+    assert_eq!(paths, vec![runs.run_dir(&crashed.id)]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cleanup_dry_run_lists_without_removing() {
+    let dir = temp_dir("dry_run");
+    let runs = RunStore::new(dir.join("runs"));
+    let mut record = runs.create("make backup").unwrap();
+    record.state = RunState::Finished;
+    record.finished_at = Some(1);
+    runs.save(&record).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .arg("--dry-run")
+        .arg("--state-dir")
+        .arg(&dir)
+        .args(["cleanup", "--older-than", "1d"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!("Would remove run {} (make backup)", record.id)),
+        "{}",
+        stdout
+    );
+    assert!(runs.run_dir(&record.id).exists());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("older than 1d"), "{}", stderr);
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -1,7 +1,16 @@
+// Without the `shell` feature `Command::Run` is the only subcommand.
+#![cfg_attr(
+    not(feature = "shell"),
+    allow(irrefutable_let_patterns, unreachable_patterns)
+)]
+
 use clap::Parser;
-use shell_hook::cli::{Cli, Command, RunArgs, WebhookFormat};
+use shell_hook::cli::{
+    parse_duration, parse_rate, parse_size, Cli, Command, InputEncoding, RunArgs, WebhookFormat,
+};
 use std::env;
 use std::sync::Mutex;
+use std::time::Duration;
 
 lazy_static::lazy_static! {
     static ref ENV_LOCK: Mutex<()> = Mutex::new(());
@@ -23,6 +32,13 @@ fn test_cli_args_parsing() {
         "20",
         "--buffer-timeout",
         "5.0",
+        "--input-encoding",
+        "shift-jis",
+        "--merge-streams",
+        "--rewrite",
+        "s/^x//",
+        "--rewrite",
+        "s/y/z/g",
         "run",
         "--on-success",
         "Success!",
@@ -40,6 +56,9 @@ fn test_cli_args_parsing() {
     assert!(matches!(cli.format, WebhookFormat::Slack));
     assert_eq!(cli.buffer_size, 20);
     assert_eq!(cli.buffer_timeout, 5.0);
+    assert_eq!(cli.input_encoding, InputEncoding::ShiftJis);
+    assert!(cli.merge_streams);
+    assert_eq!(cli.rewrites.len(), 2);
 
     if let Command::Run(run_args) = cli.command {
         assert_eq!(run_args.on_success, Some("Success!".to_string()));
@@ -71,6 +90,8 @@ fn test_cli_args_defaults() {
     assert!(matches!(cli.format, WebhookFormat::GoogleChat));
     assert_eq!(cli.buffer_size, 10);
     assert_eq!(cli.buffer_timeout, 2.0);
+    assert_eq!(cli.input_encoding, InputEncoding::Utf8);
+    assert!(!cli.merge_streams);
 
     if let Command::Run(run_args) = cli.command {
         assert_eq!(run_args.on_success, None);
@@ -82,6 +103,7 @@ fn test_cli_args_defaults() {
     }
 }
 
+#[cfg(feature = "shell")]
 #[test]
 fn test_shell_subcommand() {
     let _lock = ENV_LOCK.lock().unwrap();
@@ -128,6 +150,49 @@ fn test_webhook_format_enum() {
     assert!(matches!(cli_slack.format, WebhookFormat::Slack));
 }
 
+#[test]
+fn test_invalid_rewrite_rule_is_rejected() {
+    let result = Cli::try_parse_from(vec![
+        "shell_hook",
+        "--rewrite",
+        "not-a-rule",
+        "run",
+        "--",
+        "true",
+    ]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+    assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+    assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+    assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+    assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+    assert!(parse_duration("5d").is_err());
+    assert!(parse_duration("m").is_err());
+}
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("512"), Ok(512));
+    assert_eq!(parse_size("64KB"), Ok(64 * 1024));
+    assert_eq!(parse_size("1MB"), Ok(1 << 20));
+    assert_eq!(parse_size("2GiB"), Ok(2 << 30));
+    assert!(parse_size("1TB").is_err());
+    assert!(parse_size("MB").is_err());
+}
+
+#[test]
+fn test_run_splay_option() {
+    let cli = Cli::parse_from(vec!["shell_hook", "run", "--splay", "5m", "--", "true"]);
+    match cli.command {
+        Command::Run(args) => assert_eq!(args.splay, Some(Duration::from_secs(300))),
+        _ => panic!("Expected Run command"),
+    }
+}
+
 #[test]
 fn test_derived_traits() {
     // Test Debug trait
@@ -136,18 +201,13 @@ fn test_derived_traits() {
         on_failure: Some("Failure".to_string()),
         quiet: true,
         command: vec!["ls".to_string()],
+        ..Default::default()
     };
-    let cli = Cli {
-        command: Command::Run(run_args.clone()),
-        webhook_url: Some("http://localhost".to_string()),
-        title: Some("Title".to_string()),
-        format: WebhookFormat::Slack,
-        buffer_size: 20,
-        buffer_timeout: 5.0,
-        dry_run: true,
-    };
+    let mut cli = Cli::parse_from(vec!["shell_hook", "--format", "slack", "run", "--", "true"]);
+    cli.command = Command::Run(run_args.clone());
     println!("{:?}", cli);
     println!("{:?}", run_args);
+    #[cfg(feature = "shell")]
     println!("{:?}", Command::Shell);
 
     // Test Clone trait for RunArgs
@@ -177,3 +237,90 @@ fn test_run_subcommand_help() {
     assert!(help_text.contains("--on-failure"));
     assert!(help_text.contains("--quiet"));
 }
+
+#[test]
+fn test_notify_sinks() {
+    let cli = Cli::parse_from(vec!["shell_hook", "run", "--", "true"]);
+    assert!(cli.notifies_webhook());
+    assert!(!cli.notifies_locally());
+
+    let cli = Cli::parse_from(vec!["shell_hook", "--notify", "local", "run", "--", "true"]);
+    assert!(!cli.notifies_webhook());
+    assert!(cli.notifies_locally());
+
+    let cli = Cli::parse_from(vec![
+        "shell_hook",
+        "--notify",
+        "local",
+        "--notify",
+        "webhook",
+        "run",
+        "--",
+        "true",
+    ]);
+    assert!(cli.notifies_webhook());
+    assert!(cli.notifies_locally());
+}
+
+#[test]
+fn test_run_args_command_line_quotes_arguments() {
+    let args = |command: &[&str]| RunArgs {
+        command: command.iter().map(|arg| arg.to_string()).collect(),
+        ..Default::default()
+    };
+    assert_eq!(
+        args(&["make && make test"]).command_line(),
+        "make && make test"
+    );
+    assert_eq!(args(&["ls", "-la"]).command_line(), "ls -la");
+    assert_eq!(
+        args(&["grep", "a b", "it's"]).command_line(),
+        r#"grep 'a b' "it's""#
+    );
+}
+
+#[test]
+fn test_parse_rate() {
+    assert_eq!(parse_rate("0.3"), Ok(0.3));
+    assert_eq!(parse_rate("1"), Ok(1.0));
+    assert!(parse_rate("1.5").is_err());
+    assert!(parse_rate("-0.1").is_err());
+    assert!(parse_rate("often").is_err());
+}
+
+#[test]
+fn test_oauth_client_secret_is_redacted_and_excludes_sigv4() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let oauth = [
+        "shell_hook",
+        "--oauth-token-url",
+        "https://auth.example/token",
+        "--oauth-client-id",
+        "id",
+        "--oauth-client-secret",
+        "hunter2",
+    ];
+    let cli = Cli::try_parse_from(oauth.iter().chain(&["run", "--", "true"])).unwrap();
+    assert_eq!(
+        cli.oauth_client_secret.as_ref().unwrap().expose(),
+        "hunter2"
+    );
+    assert!(!format!("{:?}", cli).contains("hunter2"));
+
+    let both = Cli::try_parse_from(oauth.iter().chain(&[
+        "--aws-sigv4",
+        "us-east-1/lambda",
+        "run",
+        "--",
+        "true",
+    ]));
+    assert!(both.is_err());
+}
+
+#[test]
+fn test_try_parse_args_keeps_argv() {
+    let args = ["shell_hook", "--dry-run", "run", "--detach", "--", "make"];
+    let cli = Cli::try_parse_args(args).unwrap();
+    assert_eq!(cli.argv, args.map(std::ffi::OsString::from));
+    assert!(Cli::parse_from(args).argv.is_empty());
+}
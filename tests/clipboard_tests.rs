@@ -0,0 +1,14 @@
+use shell_hook::clipboard::osc52_sequence;
+
+#[test]
+fn test_osc52_sequence() {
+    assert_eq!(osc52_sequence("hi", false), "\x1b]52;c;aGk=\x07");
+}
+
+#[test]
+fn test_osc52_sequence_in_tmux() {
+    assert_eq!(
+        osc52_sequence("hi", true),
+        "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\"
+    );
+}
@@ -1,25 +1,15 @@
-use reqwest::Client;
+use clap::Parser;
 use shell_hook::app::AppContext;
-use shell_hook::cli::{Cli, Command, RunArgs, WebhookFormat};
-use shell_hook::command::run_command_and_stream;
+use shell_hook::cli::{Cli, Command, InputEncoding, RunArgs};
+use shell_hook::command::{decode_line, looks_binary, run_command_and_stream, RunControl};
 use shell_hook::message::StreamMessage;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
 fn create_test_context(run_args: RunArgs) -> (Arc<AppContext>, RunArgs) {
-    let cli = Cli {
-        command: Command::Run(run_args.clone()),
-        webhook_url: None,
-        title: None,
-        dry_run: false,
-        format: WebhookFormat::GoogleChat,
-        buffer_size: 10,
-        buffer_timeout: 2.0,
-    };
-    let context = Arc::new(AppContext {
-        cli: Arc::new(cli),
-        client: Client::new(),
-    });
+    let mut cli = Cli::parse_from(["shell_hook", "--dry-run", "run", "--", "true"]);
+    cli.command = Command::Run(run_args.clone());
+    let context = Arc::new(AppContext::new(Arc::new(cli)).unwrap());
     (context, run_args)
 }
 
@@ -41,21 +31,22 @@ async fn test_run_command_success() {
         quiet: false,
         on_success: None,
         on_failure: None,
+        ..Default::default()
     };
     let (context, run_args) = create_test_context(run_args);
     let (tx, rx) = mpsc::channel(10);
 
-    let status_result = run_command_and_stream(context, tx, &run_args).await;
+    let status_result = run_command_and_stream(context, tx, &run_args, Default::default()).await;
     assert!(status_result.is_ok());
     let status = status_result.unwrap();
     assert!(status.success());
 
     let messages = collect_messages(rx).await;
     assert_eq!(messages.len(), 1);
-    if let Some(StreamMessage::Line(line)) = messages.get(0) {
+    if let Some(StreamMessage::Output { line, .. }) = messages.first() {
         assert_eq!(line, "hello world");
     } else {
-        panic!("Expected a Line message");
+        panic!("Expected an Output message");
     }
 }
 
@@ -66,19 +57,20 @@ async fn test_run_command_with_stderr() {
         quiet: false,
         on_success: None,
         on_failure: None,
+        ..Default::default()
     };
     let (context, run_args) = create_test_context(run_args);
     let (tx, rx) = mpsc::channel(10);
-    let status_result = run_command_and_stream(context, tx, &run_args).await;
+    let status_result = run_command_and_stream(context, tx, &run_args, Default::default()).await;
     assert!(status_result.is_ok());
     let status = status_result.unwrap();
     assert!(status.success());
     let messages = collect_messages(rx).await;
     assert_eq!(messages.len(), 1);
-    if let Some(StreamMessage::Line(line)) = messages.get(0) {
+    if let Some(StreamMessage::Output { line, .. }) = messages.first() {
         assert_eq!(line, "error message");
     } else {
-        panic!("Expected a Line message with stderr content");
+        panic!("Expected an Output message with stderr content");
     }
 }
 
@@ -89,11 +81,12 @@ async fn test_run_command_failure() {
         quiet: false,
         on_success: None,
         on_failure: None,
+        ..Default::default()
     };
     let (context, run_args) = create_test_context(run_args);
     let (tx, rx) = mpsc::channel(10);
 
-    let status_result = run_command_and_stream(context, tx, &run_args).await;
+    let status_result = run_command_and_stream(context, tx, &run_args, Default::default()).await;
     assert!(status_result.is_ok());
     let status = status_result.unwrap();
     assert_eq!(status.code(), Some(1));
@@ -109,13 +102,159 @@ async fn test_run_command_quiet_mode() {
         quiet: true,
         on_success: None,
         on_failure: None,
+        ..Default::default()
     };
     let (context, run_args) = create_test_context(run_args);
     let (tx, rx) = mpsc::channel(10);
 
-    let status_result = run_command_and_stream(context, tx, &run_args).await;
+    let status_result = run_command_and_stream(context, tx, &run_args, Default::default()).await;
     assert!(status_result.is_ok());
 
     let messages = collect_messages(rx).await;
     assert!(messages.is_empty());
 }
+
+#[tokio::test]
+async fn test_run_command_invalid_utf8_does_not_stop_stream() {
+    let run_args = RunArgs {
+        command: vec!["printf 'caf\\351\\nafter\\n'".to_string()],
+        quiet: false,
+        on_success: None,
+        on_failure: None,
+        ..Default::default()
+    };
+    let (context, run_args) = create_test_context(run_args);
+    let (tx, rx) = mpsc::channel(10);
+
+    let status_result = run_command_and_stream(context, tx, &run_args, Default::default()).await;
+    assert!(status_result.unwrap().success());
+
+    let messages = collect_messages(rx).await;
+    assert_eq!(messages.len(), 2);
+    if let Some(StreamMessage::Output { line, .. }) = messages.last() {
+        assert_eq!(line, "after");
+    } else {
+        panic!("Expected an Output message");
+    }
+}
+
+#[test]
+fn test_decode_line_encodings() {
+    assert_eq!(decode_line(b"hello\r\n", InputEncoding::Utf8), "hello");
+    assert_eq!(
+        decode_line(b"caf\xe9\n", InputEncoding::Utf8),
+        "caf\u{fffd}"
+    );
+    assert_eq!(
+        decode_line(b"caf\xe9\n", InputEncoding::Latin1),
+        "caf\u{e9}"
+    );
+    assert_eq!(
+        decode_line(
+            b"\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd",
+            InputEncoding::ShiftJis
+        ),
+        "\u{3053}\u{3093}\u{306b}\u{3061}\u{306f}"
+    );
+    assert_eq!(
+        decode_line("caf\u{e9}".as_bytes(), InputEncoding::Auto),
+        "caf\u{e9}"
+    );
+    assert_eq!(decode_line(b"caf\xe9", InputEncoding::Auto), "caf\u{e9}");
+}
+
+#[tokio::test]
+async fn test_run_command_merge_streams_preserves_order() {
+    let run_args = RunArgs {
+        command: vec!["echo one; echo two >&2; echo three".to_string()],
+        ..Default::default()
+    };
+    let mut cli = Cli::parse_from([
+        "shell_hook",
+        "--dry-run",
+        "--merge-streams",
+        "run",
+        "--",
+        "true",
+    ]);
+    cli.command = Command::Run(run_args.clone());
+    let context = Arc::new(AppContext::new(Arc::new(cli)).unwrap());
+    let (tx, rx) = mpsc::channel(10);
+
+    let status_result = run_command_and_stream(context, tx, &run_args, Default::default()).await;
+    assert!(status_result.unwrap().success());
+
+    let lines: Vec<String> = collect_messages(rx)
+        .await
+        .into_iter()
+        .filter_map(|msg| match msg {
+            StreamMessage::Output { line, .. } => Some(line),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(lines, vec!["one", "two", "three"]);
+}
+
+#[tokio::test]
+async fn test_run_command_line_numbers() {
+    let run_args = RunArgs {
+        command: vec!["printf 'a\\nb\\nc\\n'".to_string()],
+        ..Default::default()
+    };
+    let mut cli = Cli::parse_from([
+        "shell_hook",
+        "--dry-run",
+        "--line-numbers",
+        "run",
+        "--",
+        "true",
+    ]);
+    cli.command = Command::Run(run_args.clone());
+    let context = Arc::new(AppContext::new(Arc::new(cli)).unwrap());
+    let (tx, rx) = mpsc::channel(10);
+
+    let status_result = run_command_and_stream(context, tx, &run_args, Default::default()).await;
+    assert!(status_result.unwrap().success());
+
+    let lines: Vec<String> = collect_messages(rx)
+        .await
+        .into_iter()
+        .filter_map(|msg| match msg {
+            StreamMessage::Output { line, .. } => Some(line),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(lines, vec!["0001 | a", "0002 | b", "0003 | c"]);
+}
+
+#[tokio::test]
+async fn test_run_command_paused_streaming_withholds_lines() {
+    let run_args = RunArgs {
+        command: vec!["printf 'a\\nb\\n'".to_string()],
+        ..Default::default()
+    };
+    let (context, run_args) = create_test_context(run_args);
+    let (tx, rx) = mpsc::channel(10);
+    let control = Arc::new(RunControl::default());
+    control.set_streaming_paused(true);
+
+    let status_result = run_command_and_stream(context, tx, &run_args, control.clone()).await;
+    assert!(status_result.unwrap().success());
+
+    let messages = collect_messages(rx).await;
+    assert!(!messages
+        .iter()
+        .any(|msg| matches!(msg, StreamMessage::Line(_) | StreamMessage::Output { .. })));
+    assert_eq!(control.take_withheld_lines(), 2);
+    assert_eq!(control.take_withheld_lines(), 0);
+}
+
+#[test]
+fn test_looks_binary() {
+    assert!(!looks_binary(b"hello world\n"));
+    assert!(!looks_binary("naïve café\tok\r\n".as_bytes()));
+    assert!(!looks_binary(b"\x1b[31mred\x1b[0m\n"));
+    assert!(looks_binary(b"PK\x03\x04\x00\x00"));
+    assert!(looks_binary(b"\x01\x02\x03abc\x05\x06"));
+    assert!(!looks_binary(b""));
+}
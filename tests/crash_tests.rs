@@ -0,0 +1,60 @@
+use shell_hook::crash::{crash_kind, signal_message, signal_name};
+use std::process::Command;
+
+#[test]
+fn test_signal_names() {
+    assert_eq!(signal_name(libc::SIGSEGV), "SIGSEGV");
+    assert_eq!(signal_name(libc::SIGTERM), "SIGTERM");
+    assert_eq!(signal_name(64), "signal 64");
+    assert_eq!(crash_kind(libc::SIGABRT), Some("aborted"));
+    assert_eq!(crash_kind(libc::SIGKILL), None);
+}
+
+#[test]
+fn test_signal_message_for_stop_signals_is_short() {
+    assert_eq!(
+        signal_message(libc::SIGTERM, false, &["ignored".to_string()]),
+        "❌ Command was terminated by SIGTERM."
+    );
+}
+
+#[test]
+fn test_signal_message_for_crashes_quotes_stderr() {
+    let tail = [
+        "loading model".to_string(),
+        "free(): invalid pointer".to_string(),
+    ];
+    let message = signal_message(libc::SIGABRT, false, &tail);
+    assert!(
+        message.starts_with("💥 Command crashed with SIGABRT (aborted), no core dump"),
+        "{}",
+        message
+    );
+    assert!(message.ends_with("```\nloading model\nfree(): invalid pointer\n```"));
+}
+
+#[test]
+fn test_crashed_command_reports_signal_and_last_stderr() {
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args([
+            "--dry-run",
+            "run",
+            "--",
+            "ulimit -c 0; echo 'about to crash' >&2; kill -SEGV $$",
+        ])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("💥 Command crashed with SIGSEGV (segmentation fault), no core dump"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("Last error output:\n```\nabout to crash\n```"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,121 @@
+use shell_hook::diagnostics::{parse_diagnostic, sarif_log, Diagnostic, DiagnosticLevel};
+use std::path::Path;
+use std::process::Command;
+
+fn diagnostic(
+    path: &str,
+    line: u64,
+    column: Option<u64>,
+    level: DiagnosticLevel,
+    message: &str,
+) -> Diagnostic {
+    Diagnostic {
+        path: path.to_string(),
+        line,
+        column,
+        level,
+        message: message.to_string(),
+    }
+}
+
+#[test]
+fn test_parse_diagnostic_formats() {
+    assert_eq!(
+        parse_diagnostic("src/main.c:12:5: error: expected ';' before '}' token"),
+        Some(diagnostic(
+            "src/main.c",
+            12,
+            Some(5),
+            DiagnosticLevel::Error,
+            "expected ';' before '}' token"
+        ))
+    );
+    assert_eq!(
+        parse_diagnostic("deploy.sh:3:1: note: Double quote to prevent globbing. [SC2086]"),
+        Some(diagnostic(
+            "deploy.sh",
+            3,
+            Some(1),
+            DiagnosticLevel::Note,
+            "Double quote to prevent globbing. [SC2086]"
+        ))
+    );
+    assert_eq!(
+        parse_diagnostic("./app/views.py:40:80: E501 line too long (88 > 79 characters)"),
+        Some(diagnostic(
+            "app/views.py",
+            40,
+            Some(80),
+            DiagnosticLevel::Warning,
+            "E501 line too long (88 > 79 characters)"
+        ))
+    );
+    assert_eq!(
+        parse_diagnostic("lib.rs:7: Warning: unused variable"),
+        Some(diagnostic(
+            "lib.rs",
+            7,
+            None,
+            DiagnosticLevel::Warning,
+            "unused variable"
+        ))
+    );
+    assert_eq!(parse_diagnostic("meeting at 12:30: bring notes"), None);
+    assert_eq!(parse_diagnostic("error: could not compile"), None);
+}
+
+#[test]
+fn test_sarif_log() {
+    let diagnostics = [
+        diagnostic(
+            "/repo/src/main.c",
+            12,
+            Some(5),
+            DiagnosticLevel::Error,
+            "expected ';'",
+        ),
+        diagnostic(
+            "/elsewhere/lib.c",
+            1,
+            None,
+            DiagnosticLevel::Warning,
+            "unused",
+        ),
+    ];
+    let sarif = sarif_log("gcc", &diagnostics, Some(Path::new("/repo")));
+
+    assert_eq!(sarif["version"], "2.1.0");
+    let run = &sarif["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "gcc");
+    let first = &run["results"][0];
+    assert_eq!(first["level"], "error");
+    assert_eq!(first["message"]["text"], "expected ';'");
+    let location = &first["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "src/main.c");
+    assert_eq!(location["region"]["startLine"], 12);
+    assert_eq!(location["region"]["startColumn"], 5);
+    let second = &run["results"][1]["locations"][0]["physicalLocation"];
+    assert_eq!(second["artifactLocation"]["uri"], "/elsewhere/lib.c");
+    assert!(second["region"].get("startColumn").is_none());
+}
+
+#[test]
+fn test_run_emits_sarif() {
+    let path = std::env::temp_dir().join(format!("shell_hook_sarif_{}.sarif", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "run", "--emit-sarif"])
+        .arg(&path)
+        .args([
+            "--",
+            "echo 'a.sh:1:2: warning: x'; echo 'progress 50%'; echo 'b.sh:9: error: y' >&2; exit 1",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let sarif: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    let results = sarif["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(sarif["runs"][0]["tool"]["driver"]["name"], "echo");
+    std::fs::remove_file(path).unwrap();
+}
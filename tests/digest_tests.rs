@@ -0,0 +1,233 @@
+use shell_hook::digest::{parse_period, parse_slo_target, Digest, SloReport, SloTarget};
+use shell_hook::history::{HistoryEntry, HistoryStore};
+use shell_hook::units::Units;
+use std::process::Command;
+use std::time::Duration;
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+const NOW_MS: u64 = 1_000 * HOUR_MS;
+
+fn entry(job: &str, exit_code: i32, hours_ago: u64, duration_secs: u64) -> HistoryEntry {
+    let finished_at_ms = NOW_MS - hours_ago * HOUR_MS;
+    HistoryEntry {
+        job: job.to_string(),
+        command: job.to_string(),
+        exit_code,
+        started_at_ms: finished_at_ms - duration_secs * 1000,
+        finished_at_ms,
+        ..Default::default()
+    }
+}
+
+fn sample_history() -> Vec<HistoryEntry> {
+    vec![
+        entry("backup", 0, 400, 600), // older than a week
+        entry("backup", 0, 30, 185),
+        entry("backup", 0, 6, 195),
+        entry("tests", 1, 20, 40),
+        entry("tests", 0, 10, 50),
+        entry("tests", 2, 2, 45),
+    ]
+}
+
+#[test]
+fn test_parse_period() {
+    assert_eq!(parse_period("7d"), Ok(Duration::from_secs(7 * 86_400)));
+    assert_eq!(parse_period("2w"), Ok(Duration::from_secs(14 * 86_400)));
+    assert_eq!(parse_period("12h"), Ok(Duration::from_secs(12 * 3_600)));
+    assert!(parse_period("xd").is_err());
+}
+
+#[test]
+fn test_digest_message() {
+    let digest = Digest::new(&sample_history(), Duration::from_secs(7 * 86_400), NOW_MS);
+    assert_eq!(
+        digest.to_message(Units::Human),
+        "📊 Digest: 5 runs of 2 jobs in the last 7d, 60% succeeded\n\
+         Success rates:\n\
+         ✅ backup: 2/2 (100%), avg 3m 10s\n\
+         ⚠️ tests: 1/3 (33%), avg 45s\n\
+         Slowest runs:\n\
+         • backup: 3m 15s\n\
+         • backup: 3m 05s\n\
+         • tests: 50s\n\
+         • tests: 45s\n\
+         • tests: 40s\n\
+         Top failures:\n\
+         • tests: 2 failures, last exit code 2"
+    );
+}
+
+#[test]
+fn test_digest_markdown() {
+    let digest = Digest::new(&sample_history(), Duration::from_secs(86_400), NOW_MS);
+    assert_eq!(
+        digest.to_markdown(Units::Human),
+        "# shell_hook digest\n\n\
+         4 runs of 2 jobs in the last 1d, 50% succeeded.\n\n\
+         ## Success rates\n\n\
+         | Job | Runs | Failed | Succeeded | Avg duration |\n|---|---|---|---|---|\n\
+         | backup | 1 | 0 | 100% | 3m 15s |\n\
+         | tests | 3 | 2 | 33% | 45s |\n\n\
+         ## Slowest runs\n\n\
+         | Job | Duration |\n|---|---|\n\
+         | backup | 3m 15s |\n\
+         | tests | 50s |\n\
+         | tests | 45s |\n\
+         | tests | 40s |\n\n\
+         ## Top failures\n\n\
+         | Job | Failures | Last exit code |\n|---|---|---|\n\
+         | tests | 2 | 2 |\n"
+    );
+}
+
+#[test]
+fn test_digest_without_history() {
+    let digest = Digest::new(&[], Duration::from_secs(7 * 86_400), NOW_MS);
+    assert_eq!(
+        digest.to_message(Units::Human),
+        "📊 Digest: No runs recorded in the last 7d. Record runs with --history."
+    );
+}
+
+#[test]
+fn test_digest_command_posts_history() {
+    let dir = std::env::temp_dir().join(format!("shell_hook_digest_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let store = HistoryStore::new(dir.join("history.jsonl"));
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    store
+        .append(&HistoryEntry {
+            job: "nightly".to_string(),
+            command: "make".to_string(),
+            exit_code: 0,
+            started_at_ms: now_ms - 5_000,
+            finished_at_ms: now_ms,
+            ..Default::default()
+        })
+        .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "--state-dir"])
+        .arg(&dir)
+        .args(["digest", "--post"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("1 run of 1 job in the last 7d, 100% succeeded"));
+    assert!(stdout.contains("Would send payload"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_parse_slo_target() {
+    assert_eq!(
+        parse_slo_target("99.5"),
+        Ok(SloTarget {
+            job: None,
+            percent: 99.5
+        })
+    );
+    assert_eq!(
+        parse_slo_target("nightly=95%"),
+        Ok(SloTarget {
+            job: Some("nightly".to_string()),
+            percent: 95.0
+        })
+    );
+    assert!(parse_slo_target("101").is_err());
+    assert!(parse_slo_target("nightly=").is_err());
+}
+
+#[test]
+fn test_slo_report_flags_jobs_below_target() {
+    let day = Duration::from_secs(86_400);
+    let targets = [
+        parse_slo_target("90").unwrap(),
+        parse_slo_target("tests=50").unwrap(),
+    ];
+    let report = SloReport::new(&sample_history(), &[day, 7 * day], &targets, NOW_MS);
+
+    assert_eq!(
+        report.to_message(Units::Human),
+        "📈 Availability over 1d / 7d:\n\
+         ⚠️ Below target: tests\n\
+         ✅ backup: 100% / 100% (target 90%)\n\
+         🔴 tests: 33.3% / 33.3% (target 50%)"
+    );
+    assert_eq!(
+        report.to_markdown(Units::Human),
+        "# shell_hook availability\n\nSuccess rate per job over 1d / 7d.\n\n\
+         | Job | 1d | 7d | Target |\n|---|---|---|---|\n\
+         | backup | 100% | 100% | 90% |\n\
+         | tests | 33.3% | 33.3% | 50% (missed) |\n"
+    );
+    let below: Vec<&str> = report
+        .below_target()
+        .iter()
+        .map(|job| job.job.as_str())
+        .collect();
+    assert_eq!(below, ["tests"]);
+}
+
+#[test]
+fn test_slo_report_without_runs() {
+    let hour = Duration::from_secs(3_600);
+    let report = SloReport::new(&sample_history(), &[hour], &[], NOW_MS);
+    assert!(report.jobs.is_empty());
+    assert_eq!(
+        report.to_message(Units::Human),
+        "📈 Availability over 1h 00m: no runs recorded. Record runs with --history."
+    );
+}
+
+#[test]
+fn test_digest_slo_exits_non_zero_below_target() {
+    let dir = std::env::temp_dir().join(format!("shell_hook_digest_slo_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let store = HistoryStore::new(dir.join("history.jsonl"));
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    for exit_code in [0, 0, 1] {
+        store
+            .append(&HistoryEntry {
+                job: "deploy".to_string(),
+                command: "make deploy".to_string(),
+                exit_code,
+                started_at_ms: now_ms - 5_000,
+                finished_at_ms: now_ms,
+                ..Default::default()
+            })
+            .unwrap();
+    }
+    let slo = |target: &str| {
+        Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+            .args(["--dry-run", "--state-dir"])
+            .arg(&dir)
+            .args([
+                "digest", "--slo", "--window", "30d", "--post", "--target", target,
+            ])
+            .output()
+            .unwrap()
+    };
+
+    let missed = slo("deploy=99");
+    assert_eq!(missed.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&missed.stdout);
+    assert!(
+        stdout.contains("🔴 deploy: 66.6% (target 99%)"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("Would send payload"));
+
+    assert!(slo("60").status.success());
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,41 @@
+use shell_hook::directives::{parse_directive, Directive};
+use shell_hook::message::StreamMessage;
+
+#[test]
+fn test_parse_directive_recognizes_control_lines() {
+    assert_eq!(
+        parse_directive("::shell_hook flush::"),
+        Some(Directive::Flush)
+    );
+    assert_eq!(
+        parse_directive("  ::shell_hook section Build stage::\r"),
+        Some(Directive::Section("Build stage".to_string()))
+    );
+    assert_eq!(
+        parse_directive("::shell_hook notice Deployed v1.2::"),
+        Some(Directive::Notice("Deployed v1.2".to_string()))
+    );
+}
+
+#[test]
+fn test_parse_directive_leaves_other_lines_alone() {
+    for line in [
+        "shell_hook flush",
+        "::shell_hook flush",
+        "::shell_hook flush now::",
+        "::shell_hook section::",
+        "::shell_hook frobnicate::",
+        "building ::shell_hook flush::",
+    ] {
+        assert_eq!(parse_directive(line), None, "{}", line);
+    }
+}
+
+#[test]
+fn test_section_starts_a_new_message() {
+    let messages = Directive::Section("Tests".to_string()).messages();
+    assert!(matches!(
+        messages.as_slice(),
+        [StreamMessage::Flush, StreamMessage::Line(header)] if header == "▶️ Tests"
+    ));
+}
@@ -0,0 +1,44 @@
+use shell_hook::encryption::{EncryptionKey, ALGORITHM};
+
+const KEY: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+
+#[test]
+fn test_seal_and_open_round_trip() {
+    let key: EncryptionKey = KEY.parse().unwrap();
+    let envelope = key.seal(br#"{"text":"secret"}"#).unwrap();
+
+    let json: serde_json::Value = serde_json::from_slice(&envelope).unwrap();
+    assert_eq!(json["alg"], ALGORITHM);
+    assert!(!String::from_utf8_lossy(&envelope).contains("secret"));
+    assert_eq!(key.open(&envelope).unwrap(), br#"{"text":"secret"}"#);
+}
+
+#[test]
+fn test_open_rejects_tampered_ciphertext() {
+    let key: EncryptionKey = KEY.parse().unwrap();
+    let envelope = key.seal(b"payload").unwrap();
+    let mut json: serde_json::Value = serde_json::from_slice(&envelope).unwrap();
+    json["ciphertext"] = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".into();
+
+    let tampered = serde_json::to_vec(&json).unwrap();
+    assert!(key.open(&tampered).is_err());
+}
+
+#[test]
+fn test_open_rejects_other_key() {
+    let key: EncryptionKey = KEY.parse().unwrap();
+    let other: EncryptionKey = "HxwdHhsaGRgXFhUUExIREA8ODQwLCgkIBwYFBAMCAQA="
+        .parse()
+        .unwrap();
+    assert!(other.open(&key.seal(b"payload").unwrap()).is_err());
+}
+
+#[test]
+fn test_parse_key_validates_length() {
+    assert!("c2hvcnQ=".parse::<EncryptionKey>().is_err());
+    assert!("not base64!".parse::<EncryptionKey>().is_err());
+    assert_eq!(
+        format!("{:?}", KEY.parse::<EncryptionKey>().unwrap()),
+        "EncryptionKey(..)"
+    );
+}
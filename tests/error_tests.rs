@@ -62,3 +62,24 @@ async fn test_webhook_error() {
     let app_error = AppError::from(reqwest_error);
     assert!(app_error.to_string().starts_with("Webhook request failed"));
 }
+
+#[test]
+fn test_invalid_calendar_error() {
+    let error = AppError::InvalidCalendar("cal.json: invalid time 'soon'".to_string());
+    assert_eq!(
+        error.to_string(),
+        "Invalid maintenance calendar: cal.json: invalid time 'soon'"
+    );
+}
+
+#[test]
+fn test_group_errors() {
+    assert_eq!(
+        AppError::UnknownGroup("abcd1234".to_string()).to_string(),
+        "No open group with ID 'abcd1234'. Start one with `group start`."
+    );
+    assert_eq!(
+        AppError::MissingGroup.to_string(),
+        "No group to end: set SHELL_HOOK_GROUP or --group."
+    );
+}
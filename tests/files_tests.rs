@@ -0,0 +1,63 @@
+use shell_hook::files::parse_umask;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("shell_hook_files_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn mode(path: &Path) -> u32 {
+    std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+fn run_with_history(dir: &Path, extra: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "--history"])
+        .arg("--state-dir")
+        .arg(dir.join("state"))
+        .args(extra)
+        .arg("run")
+        .arg("--report")
+        .arg(dir.join("report.json"))
+        .args(["--", "true"])
+        .env_remove("SHELL_HOOK_UMASK")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn test_created_files_are_private_by_default() {
+    let dir = temp_dir("default");
+    std::fs::create_dir_all(&dir).unwrap();
+    run_with_history(&dir, &[]);
+
+    assert_eq!(mode(&dir.join("state")), 0o700);
+    assert_eq!(mode(&dir.join("state/history.jsonl")), 0o600);
+    assert_eq!(mode(&dir.join("report.json")), 0o600);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_umask_overrides_modes() {
+    let dir = temp_dir("umask");
+    std::fs::create_dir_all(&dir).unwrap();
+    run_with_history(&dir, &["--umask", "027"]);
+
+    assert_eq!(mode(&dir.join("state")), 0o750);
+    assert_eq!(mode(&dir.join("state/history.jsonl")), 0o640);
+    assert_eq!(mode(&dir.join("report.json")), 0o640);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_parse_umask() {
+    assert_eq!(parse_umask("077"), Ok(0o077));
+    assert_eq!(parse_umask("0027"), Ok(0o027));
+    assert!(parse_umask("089").is_err());
+    assert!(parse_umask("1777").is_err());
+}
@@ -0,0 +1,24 @@
+use shell_hook::fixtures::write_fixtures;
+
+#[tokio::test]
+async fn test_write_fixtures_covers_every_format_and_event() {
+    let out = std::env::temp_dir().join(format!("shell_hook_fixtures_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&out);
+
+    let written = write_fixtures(&out).await.unwrap();
+    assert_eq!(written.len(), 10);
+
+    for format in ["google-chat", "slack"] {
+        for event in ["started", "output", "succeeded", "failed", "killed"] {
+            let path = out.join(format).join(format!("{}.json", event));
+            let payload: serde_json::Value =
+                serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+            assert!(payload["text"].is_string(), "{}", path.display());
+        }
+    }
+    let failed: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(out.join("slack/failed.json")).unwrap()).unwrap();
+    assert_eq!(failed["text"], "❌ Command failed with exit code 1.");
+
+    std::fs::remove_dir_all(out).unwrap();
+}
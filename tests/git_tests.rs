@@ -0,0 +1,67 @@
+use shell_hook::git::{head_commit, GitContext};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn temp_repo(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("shell_hook_git_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    git(&dir, &["init", "-q", "-b", "main"]);
+    std::fs::write(dir.join("file.txt"), "one").unwrap();
+    git(&dir, &["add", "file.txt"]);
+    git(&dir, &["commit", "-q", "-m", "initial"]);
+    dir
+}
+
+#[test]
+fn test_git_context_detects_branch_and_dirty_state() {
+    let dir = temp_repo("context");
+
+    let clean = GitContext::detect(&dir).unwrap();
+    assert_eq!(clean.branch.as_deref(), Some("main"));
+    assert!(!clean.dirty);
+    assert!(head_commit(&dir).unwrap().starts_with(&clean.short_sha));
+    assert_eq!(clean.to_string(), format!("main@{}", clean.short_sha));
+
+    std::fs::write(dir.join("file.txt"), "two").unwrap();
+    let dirty = GitContext::detect(&dir).unwrap();
+    assert!(dirty.dirty);
+    assert_eq!(
+        dirty.to_string(),
+        format!("main@{}, dirty", dirty.short_sha)
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_git_context_detached_head() {
+    let dir = temp_repo("detached");
+    git(&dir, &["checkout", "-q", "--detach"]);
+
+    let context = GitContext::detect(&dir).unwrap();
+    assert_eq!(context.branch, None);
+    assert!(context.to_string().starts_with("HEAD@"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_git_context_outside_repository() {
+    let dir = std::env::temp_dir().join(format!("shell_hook_not_git_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    assert_eq!(GitContext::detect(&dir), None);
+    assert_eq!(head_commit(&dir), None);
+    std::fs::remove_dir_all(dir).unwrap();
+}
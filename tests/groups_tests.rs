@@ -0,0 +1,128 @@
+use shell_hook::error::AppError;
+use shell_hook::groups::{group_summary, GroupRecord, GroupRun, GroupStore};
+use shell_hook::units::Units;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn temp_state_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("shell_hook_group_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn run(command: &str, exit_code: i32) -> GroupRun {
+    GroupRun {
+        command: command.to_string(),
+        exit_code,
+        started_at: 100,
+        finished_at: 112,
+    }
+}
+
+#[test]
+fn test_group_store_round_trip() {
+    let dir = temp_state_dir("round_trip");
+    let store = GroupStore::new(&dir);
+
+    let group = store.create("Release 1.2").unwrap();
+    assert_eq!(store.load(&group.id).unwrap(), group);
+    assert!(store.runs(&group.id).unwrap().is_empty());
+
+    store.record_run(&group.id, &run("make build", 0)).unwrap();
+    store.record_run(&group.id, &run("make test", 2)).unwrap();
+    assert_eq!(
+        store.runs(&group.id).unwrap(),
+        vec![run("make build", 0), run("make test", 2)]
+    );
+
+    store.remove(&group.id).unwrap();
+    assert!(matches!(
+        store.load(&group.id),
+        Err(AppError::UnknownGroup(_))
+    ));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_record_run_in_unknown_group_fails() {
+    let store = GroupStore::new(temp_state_dir("unknown"));
+    assert!(matches!(
+        store.record_run("nope", &run("true", 0)),
+        Err(AppError::UnknownGroup(_))
+    ));
+}
+
+#[test]
+fn test_group_summary() {
+    let group = GroupRecord {
+        id: "abcd1234".to_string(),
+        name: "Release 1.2".to_string(),
+        started_at: 100,
+    };
+    assert_eq!(
+        group_summary(
+            &group,
+            &[run("make build", 0), run("make test", 2)],
+            160,
+            Units::Human
+        ),
+        "❌ Group Release 1.2 finished: 2 runs, 1 failed, in 1m 00s\n\
+         ✅ `make build` (12s)\n\
+         ❌ `make test` exited 2 (12s)"
+    );
+    assert_eq!(
+        group_summary(&group, &[run("make build", 0)], 100, Units::Human),
+        "✅ Group Release 1.2 finished: 1 run, 0 failed, in 0s\n✅ `make build` (12s)"
+    );
+    assert_eq!(
+        group_summary(&group, &[run("make build", 0)], 160, Units::Raw),
+        "✅ Group Release 1.2 finished: 1 run, 0 failed, in 60s\n✅ `make build` (12s)"
+    );
+}
+
+#[test]
+fn test_group_start_run_end() {
+    let dir = temp_state_dir("cli");
+    let bin = env!("CARGO_BIN_EXE_shell_hook");
+    let base = ["--dry-run", "--state-dir", dir.to_str().unwrap()];
+
+    let output = Command::new(bin)
+        .args(base)
+        .args(["group", "start", "Release 1.2"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // Dry-run payloads are printed first; the export line comes last.
+    let id = stdout
+        .lines()
+        .last()
+        .unwrap()
+        .strip_prefix("export SHELL_HOOK_GROUP=")
+        .unwrap()
+        .to_string();
+
+    for command in ["true", "exit 3"] {
+        Command::new(bin)
+            .args(base)
+            .args(["run", "--", command])
+            .env("SHELL_HOOK_GROUP", &id)
+            .output()
+            .unwrap();
+    }
+
+    let output = Command::new(bin)
+        .args(base)
+        .args(["group", "end"])
+        .env("SHELL_HOOK_GROUP", &id)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Group Release 1.2 finished: 2 runs, 1 failed"));
+    assert!(stdout.contains("❌ `exit 3` exited 3"));
+    assert!(!dir.join("groups").join(&id).exists());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,113 @@
+use shell_hook::app::AppContext;
+use shell_hook::cli::Cli;
+use shell_hook::health::{run_health_checks, FlapFilter, Health};
+
+use clap::Parser;
+use httpmock::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_flap_filter_waits_for_consecutive_failures() {
+    let mut filter = FlapFilter::new(3);
+    assert_eq!(filter.observe(false), None);
+    assert_eq!(filter.observe(false), None);
+    assert_eq!(filter.observe(true), None);
+    assert_eq!(filter.observe(false), None);
+    assert_eq!(filter.observe(false), None);
+    assert_eq!(filter.observe(false), Some(Health::Failing));
+    assert_eq!(filter.observe(false), None);
+    assert_eq!(filter.consecutive_failures(), 4);
+    assert_eq!(filter.observe(true), Some(Health::Healthy));
+    assert_eq!(filter.observe(true), None);
+    assert_eq!(filter.state(), Health::Healthy);
+}
+
+#[test]
+fn test_flap_filter_threshold_of_one_reports_every_transition() {
+    let mut filter = FlapFilter::new(1);
+    assert_eq!(filter.observe(true), None);
+    assert_eq!(filter.observe(false), Some(Health::Failing));
+    assert_eq!(filter.observe(true), Some(Health::Healthy));
+}
+
+fn context_for(server: &MockServer) -> Arc<AppContext> {
+    let cli =
+        Cli::try_parse_from(["shell_hook", "--webhook-url", &server.url("/"), "check"]).unwrap();
+    Arc::new(AppContext::new(Arc::new(cli)).unwrap())
+}
+
+#[tokio::test]
+async fn test_health_checks_notify_on_failure_and_recovery_only() {
+    let server = MockServer::start();
+    let failing = server.mock(|when, then| {
+        when.method(POST)
+            .body_contains("Health check failing")
+            .body_contains("exit code 3, 2 checks in a row")
+            .body_contains("down");
+        then.status(200);
+    });
+    let recovered = server.mock(|when, then| {
+        when.method(POST).body_contains("Health check recovered");
+        then.status(200);
+    });
+
+    // Checks 1-3 fail, the rest pass.
+    let counter = std::env::temp_dir().join(format!("shell_hook_health_{}", std::process::id()));
+    let _ = std::fs::remove_file(&counter);
+    let command = vec![format!(
+        "echo x >> {0}; [ $(wc -l < {0}) -gt 3 ] || {{ echo down; exit 3; }}",
+        counter.display()
+    )];
+
+    let exit_code = run_health_checks(
+        &context_for(&server),
+        &command,
+        Duration::from_millis(10),
+        2,
+        Some(5),
+    )
+    .await
+    .unwrap();
+    let _ = std::fs::remove_file(&counter);
+
+    assert_eq!(exit_code, 0);
+    failing.assert_hits(1);
+    recovered.assert_hits(1);
+}
+
+#[tokio::test]
+async fn test_health_checks_exit_non_zero_when_last_check_fails() {
+    let server = MockServer::start();
+    let any = server.mock(|when, then| {
+        when.method(POST);
+        then.status(200);
+    });
+
+    let exit_code = run_health_checks(
+        &context_for(&server),
+        &["false".to_string()],
+        Duration::from_millis(10),
+        3,
+        Some(2),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(exit_code, 1);
+    // Two failures stay under the threshold of three.
+    any.assert_hits(0);
+}
+
+#[test]
+fn test_health_check_options_require_a_command() {
+    for flag in ["--interval=5s", "--failures=3", "--count=1"] {
+        assert!(
+            Cli::try_parse_from(["shell_hook", "check", flag]).is_err(),
+            "{}",
+            flag
+        );
+        assert!(Cli::try_parse_from(["shell_hook", "check", flag, "--", "true"]).is_ok());
+    }
+    assert!(Cli::try_parse_from(["shell_hook", "check"]).is_ok());
+}
@@ -0,0 +1,168 @@
+use shell_hook::history::{
+    outcome_strip, write_csv, write_jsonl, Flakiness, HistoryEntry, HistoryStore,
+};
+use std::path::PathBuf;
+use std::process::Command;
+
+fn temp_state_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "shell_hook_history_{}_{}",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn entry(job: &str, exit_code: i32) -> HistoryEntry {
+    HistoryEntry {
+        job: job.to_string(),
+        command: "make test".to_string(),
+        exit_code,
+        started_at_ms: 1_000,
+        finished_at_ms: 13_000,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_history_store_recent_runs_of_a_job() {
+    let dir = temp_state_dir("recent");
+    let store = HistoryStore::new(dir.join("history.jsonl"));
+    assert_eq!(store.load().unwrap(), vec![]);
+
+    for code in [0, 1, 2, 3] {
+        store.append(&entry("tests", code)).unwrap();
+        store.append(&entry("backup", 0)).unwrap();
+    }
+    assert_eq!(store.load().unwrap().len(), 8);
+    let recent: Vec<i32> = store
+        .recent("tests", 2)
+        .unwrap()
+        .iter()
+        .map(|run| run.exit_code)
+        .collect();
+    assert_eq!(recent, vec![2, 3]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_flakiness_of_failure() {
+    let previous = [entry("tests", 0), entry("tests", 1), entry("tests", 0)];
+    let flakiness = Flakiness::of_failure(&previous, 0.5).unwrap();
+    assert_eq!(flakiness, Flakiness { failed: 2, runs: 4 });
+    assert_eq!(flakiness.describe(), "flaky: failed 2 of last 4 runs");
+
+    assert_eq!(Flakiness::of_failure(&previous, 0.6), None);
+    // Always failing is broken rather than flaky, and a first failure is news.
+    assert_eq!(Flakiness::of_failure(&[entry("tests", 1)], 0.2), None);
+    assert_eq!(Flakiness::of_failure(&[], 0.2), None);
+}
+
+#[test]
+fn test_failures_are_annotated_from_history() {
+    let dir = temp_state_dir("e2e");
+    let run = |command: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+            .args(["--dry-run", "--history", "--flaky-threshold", "0.5"])
+            .arg("--state-dir")
+            .arg(&dir)
+            .args(["--title", "nightly", "run", "--", command])
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    assert!(!run("false").contains("flaky"));
+    run("true");
+    assert!(run("false").contains("flaky: failed 2 of last 3 runs"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_outcome_strip_ends_with_current_run() {
+    let previous = [entry("tests", 0), entry("tests", 2), entry("tests", 0)];
+    assert_eq!(outcome_strip(&previous, 1), "✅❌✅❌");
+    assert_eq!(outcome_strip(&[], 0), "✅");
+}
+
+#[test]
+fn test_final_message_shows_recent_outcomes() {
+    let dir = temp_state_dir("strip");
+    let run = |command: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+            .args(["--dry-run", "--history", "--outcome-strip", "3"])
+            .arg("--state-dir")
+            .arg(&dir)
+            .args(["--title", "nightly", "run", "--", command])
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    run("true");
+    run("false");
+    run("true");
+    assert!(run("false").contains("Recent runs: ❌✅❌"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_write_csv_quotes_fields() {
+    let runs = [
+        HistoryEntry {
+            deliveries: 3,
+            failed_deliveries: 1,
+            ..entry("tests", 2)
+        },
+        HistoryEntry {
+            command: r#"echo "a, b""#.to_string(),
+            ..entry("echo", 0)
+        },
+    ];
+    let mut csv = Vec::new();
+    write_csv(&runs, &mut csv).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "run_id,job,command,exit_code,started_at_ms,finished_at_ms,duration_ms,deliveries,failed_deliveries\n\
+         ,tests,make test,2,1000,13000,12000,3,1\n\
+         ,echo,\"echo \"\"a, b\"\"\",0,1000,13000,12000,0,0\n"
+    );
+}
+
+#[test]
+fn test_write_jsonl_adds_duration() {
+    let mut jsonl = Vec::new();
+    write_jsonl(&[entry("tests", 0)], &mut jsonl).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&jsonl).unwrap();
+    assert_eq!(value["job"], "tests");
+    assert_eq!(value["duration_ms"], 12_000);
+    assert_eq!(value["deliveries"], 0);
+}
+
+#[test]
+fn test_history_export_command() {
+    let dir = temp_state_dir("export");
+    let run = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "--history", "--state-dir"])
+        .arg(&dir)
+        .args(["--title", "nightly", "run", "--", "exit 3"])
+        .output()
+        .unwrap();
+    assert_eq!(run.status.code(), Some(3));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .arg("--state-dir")
+        .arg(&dir)
+        .args(["history", "export", "--as", "csv"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let csv = String::from_utf8_lossy(&output.stdout);
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows.len(), 2);
+    let (run_id, row) = rows[1].split_once(',').unwrap();
+    assert_eq!(run_id.len(), 16);
+    assert!(row.starts_with("nightly,exit 3,3,"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,80 @@
+#![cfg(feature = "shell")]
+
+use clap::Parser;
+use shell_hook::app::AppContext;
+use shell_hook::cli::Cli;
+use shell_hook::jobs::{strip_background, JobStatus, JobTable};
+use shell_hook::variables::SessionVariables;
+use std::sync::Arc;
+
+fn dry_run_context() -> Arc<AppContext> {
+    let cli = Cli::parse_from(["shell_hook", "--dry-run", "shell"]);
+    Arc::new(AppContext::new(Arc::new(cli)).unwrap())
+}
+
+#[test]
+fn test_strip_background() {
+    assert_eq!(strip_background("sleep 10 &"), Some("sleep 10"));
+    assert_eq!(
+        strip_background("make build 2>&1 &"),
+        Some("make build 2>&1")
+    );
+    assert_eq!(strip_background("make && make install"), None);
+    assert_eq!(strip_background("make &&"), None);
+    assert_eq!(strip_background("&"), None);
+    assert_eq!(strip_background("echo hi"), None);
+}
+
+#[tokio::test]
+async fn test_spawn_list_and_attach() {
+    let context = dry_run_context();
+    let mut jobs = JobTable::default();
+
+    let id = jobs.spawn(&context, "sleep 0.2; exit 4", &SessionVariables::default());
+    assert_eq!(
+        jobs.list(),
+        vec![JobStatus {
+            id,
+            command: "sleep 0.2; exit 4".to_string(),
+            finished: false,
+        }]
+    );
+
+    let result = jobs.attach(id).await.unwrap();
+    assert_eq!(result.unwrap(), 4);
+    assert!(jobs.is_empty());
+}
+
+#[tokio::test]
+async fn test_attach_unknown_job() {
+    let mut jobs = JobTable::default();
+    assert!(jobs.attach(42).await.is_none());
+}
+
+#[tokio::test]
+async fn test_handle_line_dispatches_job_commands() {
+    let context = dry_run_context();
+    let mut jobs = JobTable::default();
+    let variables = SessionVariables::default();
+
+    assert!(jobs.handle_line(&context, "true &", &variables).await);
+    assert!(jobs.handle_line(&context, ":jobs", &variables).await);
+    assert!(jobs.handle_line(&context, ":attach 1", &variables).await);
+    assert!(jobs.is_empty());
+    assert!(
+        !jobs
+            .handle_line(&context, "echo foreground", &variables)
+            .await
+    );
+}
+
+#[tokio::test]
+async fn test_wait_all() {
+    let context = dry_run_context();
+    let mut jobs = JobTable::default();
+    jobs.spawn(&context, "true", &SessionVariables::default());
+    jobs.spawn(&context, "sleep 0.1", &SessionVariables::default());
+
+    jobs.wait_all().await;
+    assert!(jobs.is_empty());
+}
@@ -0,0 +1,38 @@
+use shell_hook::language::{detect, Language};
+
+fn lines(text: &str) -> Vec<String> {
+    text.lines().map(str::to_string).collect()
+}
+
+#[test]
+fn test_detect_json() {
+    let pretty = lines("{\n  \"name\": \"web\",\n  \"replicas\": [\n    1,\n    2\n  ]\n}");
+    assert_eq!(detect(&pretty), Some(Language::Json));
+
+    let ndjson = lines("{\"level\":\"info\"}\n{\"level\":\"warn\"}");
+    assert_eq!(detect(&ndjson), Some(Language::Json));
+}
+
+#[test]
+fn test_detect_yaml() {
+    let yaml = lines(
+        "apiVersion: v1\nkind: Pod\nmetadata:\n  name: web\n  labels:\n    - app\n# trailing comment",
+    );
+    assert_eq!(detect(&yaml), Some(Language::Yaml));
+}
+
+#[test]
+fn test_detect_diff() {
+    let diff = lines(
+        "diff --git a/src/main.rs b/src/main.rs\nindex 1a2b..3c4d 100644\n--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,3 +1,3 @@\n fn main() {\n-    old();\n+    new();\n }",
+    );
+    assert_eq!(detect(&diff), Some(Language::Diff));
+}
+
+#[test]
+fn test_detect_plain_output() {
+    assert_eq!(detect(&lines("Compiling foo\nFinished in 2s")), None);
+    assert_eq!(detect(&lines("1\n2\n3")), None);
+    assert_eq!(detect(&lines("- not\n- a diff")), None);
+    assert_eq!(detect(&[]), None);
+}
@@ -0,0 +1,52 @@
+use shell_hook::links::{parse_link_template, Linker};
+use shell_hook::render::SlackRenderer;
+use std::path::PathBuf;
+
+fn linker() -> Linker {
+    Linker::new(
+        "https://github.com/org/repo/blob/{sha}/{path}#L{line}",
+        "abc123",
+        Some(PathBuf::from("/work/repo")),
+    )
+}
+
+#[test]
+fn test_link_references_in_compiler_output() {
+    let line = "error[E0308]: mismatched types --> src/app.rs:42:9";
+    assert_eq!(
+        linker().link_references(&SlackRenderer, line),
+        "error[E0308]: mismatched types --> \
+         <https://github.com/org/repo/blob/abc123/src/app.rs#L42|src/app.rs:42:9>"
+    );
+}
+
+#[test]
+fn test_link_references_strips_dot_slash_and_root() {
+    let linker = linker();
+    assert_eq!(
+        linker.link_references(&SlackRenderer, "./tests/a_test.py:7"),
+        "<https://github.com/org/repo/blob/abc123/tests/a_test.py#L7|./tests/a_test.py:7>"
+    );
+    assert_eq!(
+        linker.link_references(&SlackRenderer, "at /work/repo/lib/x.js:3"),
+        "at <https://github.com/org/repo/blob/abc123/lib/x.js#L3|/work/repo/lib/x.js:3>"
+    );
+}
+
+#[test]
+fn test_link_references_leaves_other_text_alone() {
+    let linker = linker();
+    for line in [
+        "started at 12:30",
+        "/usr/lib/libc.so:12",
+        "listening on localhost:8080",
+    ] {
+        assert_eq!(linker.link_references(&SlackRenderer, line), line);
+    }
+}
+
+#[test]
+fn test_parse_link_template() {
+    assert!(parse_link_template("https://x/{path}#L{line}").is_ok());
+    assert!(parse_link_template("https://x/").is_err());
+}
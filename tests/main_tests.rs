@@ -3,7 +3,18 @@ use std::process::Command;
 #[test]
 fn test_main_binary_success() {
     let output = Command::new("cargo")
-        .args(&["run", "--bin", "shell_hook", "--", "--webhook-url", "http://localhost", "run", "--", "echo", "hello"])
+        .args([
+            "run",
+            "--bin",
+            "shell_hook",
+            "--",
+            "--webhook-url",
+            "http://localhost",
+            "run",
+            "--",
+            "echo",
+            "hello",
+        ])
         .output()
         .expect("failed to execute process");
 
@@ -13,9 +24,399 @@ fn test_main_binary_success() {
 #[test]
 fn test_main_binary_error() {
     let output = Command::new("cargo")
-        .args(&["run", "--bin", "shell_hook", "--", "run"])
+        .args(["run", "--bin", "shell_hook", "--", "run"])
         .output()
         .expect("failed to execute process");
 
     assert!(!output.status.success());
 }
+
+#[test]
+fn test_status_to_webhook_only_keeps_stdout_clean() {
+    let server = httpmock::MockServer::start();
+    let start = server.mock(|when, then| {
+        when.body_contains("Starting command");
+        then.status(200);
+    });
+    server.mock(|_, then| {
+        then.status(200);
+    });
+
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args([
+            "--webhook-url",
+            &server.url("/"),
+            "--status-to-webhook-only",
+            "run",
+            "--",
+            "printf 'a,b\\n'; exit 1",
+        ])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a,b\n");
+    assert!(output.stderr.is_empty());
+    start.assert_hits(1);
+}
+
+#[test]
+fn test_raw_passthrough_preserves_bytes() {
+    let server = httpmock::MockServer::start();
+    let decoded = server.mock(|when, then| {
+        when.body_contains(r#""text":"bin"#);
+        then.status(200);
+    });
+    server.mock(|_, then| {
+        then.status(200);
+    });
+
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args([
+            "--webhook-url",
+            &server.url("/"),
+            "--status-to-webhook-only",
+            "--raw-passthrough",
+            "run",
+            "--",
+            "printf 'bin\\377\\r\\npartial'",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"bin\xff\r\npartial");
+    decoded.assert_hits(1);
+}
+
+fn run_nested(policy: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "--nested", policy, "run", "--", "echo hi"])
+        .env("SHELL_HOOK_PARENT", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_nested_policies() {
+    let inherit = run_nested("inherit");
+    assert!(!inherit.contains("Dry run"), "{}", inherit);
+    assert!(inherit.contains("Starting command"));
+    assert!(inherit.contains("hi"));
+
+    assert_eq!(run_nested("mute"), "hi\n");
+
+    assert!(run_nested("independent").contains("Dry run: Would send payload"));
+}
+
+#[test]
+fn test_wrapped_commands_are_marked_as_nested() {
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "--status-to-webhook-only", "run", "--"])
+        .arg("echo parent=$SHELL_HOOK_PARENT")
+        .env_remove("SHELL_HOOK_PARENT")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.lines().any(|line| line
+            .strip_prefix("parent=")
+            .is_some_and(|pid| pid.parse::<u32>().is_ok())),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_run_quotes_separate_arguments() {
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args([
+            "--dry-run",
+            "run",
+            "--show-effective-command",
+            "--",
+            "printf",
+            "[%s]\\n",
+            "a b",
+            "it's",
+            "$HOME",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[a b]\n[it's]\n[$HOME]\n"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("[shell_hook] Effective command: sh -c "));
+}
+
+#[test]
+fn test_run_unbuffer_wraps_command_in_stdbuf() {
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args([
+            "--dry-run",
+            "run",
+            "--unbuffer",
+            "--show-effective-command",
+            "--",
+            "echo unbuffered=$PYTHONUNBUFFERED",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("unbuffered=1\n"), "{}", stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(" -oL -eL sh -c ") || stderr.contains("stdbuf not found"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_run_outside_expected_duration_is_reported_as_warning() {
+    let run = |bound: &str, command: &str| {
+        Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+            .args(["--dry-run", "run", bound, "--", command])
+            .output()
+            .unwrap()
+    };
+
+    let too_fast = run("--expect-min-duration=10s", "true");
+    assert!(too_fast.status.success());
+    let stderr = String::from_utf8_lossy(&too_fast.stderr);
+    assert!(stderr.contains("less than the expected 10s"), "{}", stderr);
+
+    let too_slow = run("--expect-max-duration=10ms", "sleep 0.1");
+    assert!(too_slow.status.success());
+    let stderr = String::from_utf8_lossy(&too_slow.stderr);
+    assert!(
+        stderr.contains("longer than the expected 10ms"),
+        "{}",
+        stderr
+    );
+
+    let in_bounds = run("--expect-max-duration=10s", "true");
+    let stdout = String::from_utf8_lossy(&in_bounds.stdout);
+    assert!(stdout.contains("finished successfully."), "{}", stdout);
+    assert!(!stdout.contains("expected"), "{}", stdout);
+}
+
+#[test]
+fn test_run_without_expected_output_is_reported_as_warning() {
+    let run = |command: &str| {
+        Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+            .args([
+                "--dry-run",
+                "run",
+                "--expect-output",
+                "^Backed up [1-9]",
+                "--",
+                command,
+            ])
+            .output()
+            .unwrap()
+    };
+
+    let missing = run("echo 'Backed up 0 files'");
+    assert!(missing.status.success());
+    let stderr = String::from_utf8_lossy(&missing.stderr);
+    assert!(
+        stderr.contains("completed but expected output missing"),
+        "{}",
+        stderr
+    );
+
+    let present = run("echo 'Backed up 12 files'");
+    let stdout = String::from_utf8_lossy(&present.stdout);
+    assert!(stdout.contains("finished successfully."), "{}", stdout);
+}
+
+#[test]
+fn test_result_line_ends_stdout() {
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "run", "--result-line", "--", "echo hello"])
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let last = stdout.lines().last().unwrap();
+    assert!(
+        last.starts_with("SHELL_HOOK_RESULT exit=0 duration=0s run_id="),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_result_fd_writes_to_chosen_descriptor() {
+    let script = format!(
+        "{} --dry-run run --result-line --result-fd 3 -- 'exit 3' 3>&1 >/dev/null",
+        env!("CARGO_BIN_EXE_shell_hook")
+    );
+    let output = Command::new("sh").args(["-c", &script]).output().unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("SHELL_HOOK_RESULT exit=3 duration="),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_control_lines_flush_and_are_stripped() {
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args([
+            "--dry-run",
+            "run",
+            "--",
+            "printf 'alpha\\n::%s flush::\\nbeta\\n' shell_hook",
+        ])
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("::shell_hook flush::"), "{}", stdout);
+    let payloads: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.contains("Would send payload") && !line.contains("ommand"))
+        .collect();
+    assert_eq!(payloads.len(), 2, "{}", stdout);
+    assert!(payloads[0].contains("alpha") && !payloads[0].contains("beta"));
+    assert!(payloads[1].contains("beta"));
+}
+
+#[test]
+fn test_noise_preset_drops_lines_from_webhook_only() {
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args([
+            "--dry-run",
+            "--noise-preset",
+            "npm",
+            "run",
+            "--",
+            "echo 'npm WARN deprecated glob@7'; echo 'npm ERR! missing script'",
+        ])
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let payloads: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.contains("Would send payload") && !line.contains("ommand"))
+        .collect();
+    assert_eq!(payloads.len(), 1, "{}", stdout);
+    assert!(payloads[0].contains("npm ERR! missing script"));
+    assert!(!payloads[0].contains("deprecated"));
+    assert!(stdout
+        .lines()
+        .any(|line| line == "npm WARN deprecated glob@7"));
+}
+
+#[test]
+fn test_very_verbose_prints_batch_telemetry() {
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "-vv", "run", "--", "printf 'a\\nb\\n'"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("[shell_hook] Batch 1: 2 lines"));
+    assert!(stderr.contains("[shell_hook] Batch summary: batches 1, lines 2"));
+}
+
+#[test]
+fn test_binary_output_is_replaced_by_notice() {
+    let run = |allow_binary: bool| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_shell_hook"));
+        command.arg("--dry-run");
+        if allow_binary {
+            command.arg("--allow-binary");
+        }
+        let output = command
+            .args([
+                "run",
+                "--",
+                "printf 'PK\\003\\004\\000\\001\\002\\n\\377\\376\\n'",
+            ])
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let stdout = run(false);
+    assert!(stdout.contains("[stdout suppressed (binary output detected), 11 B]"));
+    let stdout = run(true);
+    assert!(!stdout.contains("suppressed"));
+}
+
+#[test]
+fn test_tmpdir_is_removed_on_success_and_kept_on_failure() {
+    let run = |script: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+            .args(["--dry-run", "run", "--tmpdir", "--", script])
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )
+    };
+
+    let (code, stdout) =
+        run(r#"test -d "$TMPDIR" && test "$TMPDIR" = "$SHELL_HOOK_TMP" && echo "$TMPDIR""#);
+    assert_eq!(code, Some(0));
+    let dir = stdout.lines().find(|line| line.starts_with('/')).unwrap();
+    assert!(!std::path::Path::new(dir).exists());
+
+    let (code, stdout) = run(r#"echo "$TMPDIR"; touch "$TMPDIR/partial"; exit 4"#);
+    assert_eq!(code, Some(4));
+    let dir = stdout.lines().find(|line| line.starts_with('/')).unwrap();
+    assert!(stdout.contains(&format!("Temp dir kept: {}", dir)));
+    assert!(std::path::Path::new(dir).join("partial").exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_result_line_run_id_matches_history_and_report() {
+    let dir = std::env::temp_dir().join(format!("shell_hook_run_id_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let report = dir.join("report.json");
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "--history", "--watermark", "--state-dir"])
+        .arg(&dir)
+        .args(["run", "--result-line", "--report"])
+        .arg(&report)
+        .args(["--", "true"])
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let run_id = stdout
+        .lines()
+        .last()
+        .and_then(|line| line.split_once("run_id="))
+        .map(|(_, id)| id.to_string())
+        .unwrap();
+    let report: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&report).unwrap()).unwrap();
+    assert_eq!(report["run_id"], run_id.as_str());
+    let history = std::fs::read_to_string(dir.join("history.jsonl")).unwrap();
+    let entry: serde_json::Value = serde_json::from_str(history.trim()).unwrap();
+    assert_eq!(entry["run_id"], run_id.as_str());
+    assert!(
+        stdout.contains(&format!(r#""run_id":"{}""#, run_id)),
+        "{}",
+        stdout
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,152 @@
+use httpmock::prelude::*;
+use shell_hook::app::run_from;
+use shell_hook::maintenance::{
+    active_window, parse_ical, parse_json, parse_timestamp, MaintenanceWindow,
+};
+use shell_hook::runs::unix_now;
+
+#[test]
+fn test_parse_timestamp() {
+    assert_eq!(parse_timestamp("2026-10-14T02:00:00Z"), Some(1791943200));
+    assert_eq!(
+        parse_timestamp("2026-10-14T04:00:00+02:00"),
+        Some(1791943200)
+    );
+    assert_eq!(
+        parse_timestamp("2026-10-13T21:00:00.250-05:00"),
+        Some(1791943200)
+    );
+    assert_eq!(parse_timestamp("20261014T020000Z"), Some(1791943200));
+    assert_eq!(parse_timestamp("2000-02-29T23:59:59Z"), Some(951868799));
+    assert_eq!(parse_timestamp("2026-13-01T00:00:00Z"), None);
+    assert_eq!(parse_timestamp("tomorrow"), None);
+}
+
+#[test]
+fn test_parse_timestamp_rejects_non_ascii() {
+    assert_eq!(parse_timestamp("xé12345"), None);
+    assert_eq!(parse_timestamp("2026-10-14T0é:00:00Z"), None);
+    assert_eq!(parse_timestamp("2026-10-14T02:00:00+0é:00"), None);
+
+    let ical = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART:xé12345\nEND:VEVENT\n";
+    assert!(parse_ical(ical).is_err());
+    assert!(parse_json(r#"[{"name": "w", "start": "xé12345", "end": "xé12345"}]"#).is_err());
+}
+
+#[test]
+fn test_parse_json_calendar() {
+    let windows = parse_json(
+        r#"[{"name": "db upgrade", "start": "2026-10-14T02:00:00Z", "end": "2026-10-14T04:00:00Z"}]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        windows,
+        vec![MaintenanceWindow {
+            name: "db upgrade".to_string(),
+            start: 1791943200,
+            end: 1791943200 + 7200,
+        }]
+    );
+
+    assert!(
+        parse_json(r#"[{"start": "2026-10-14T04:00:00Z", "end": "2026-10-14T02:00:00Z"}]"#)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_parse_ical_calendar() {
+    let ical = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Kernel patching\r\n\
+                DTSTART:20261014T020000Z\r\nDTEND:20261014T030000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+    let windows = parse_ical(ical).unwrap();
+    assert_eq!(windows.len(), 1);
+    assert_eq!(windows[0].name, "Kernel patching");
+    assert_eq!(windows[0].end - windows[0].start, 3600);
+
+    let local =
+        "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART;TZID=Europe/Berlin:20261014T020000\nEND:VEVENT\n";
+    assert!(parse_ical(local).is_err());
+}
+
+#[test]
+fn test_active_window() {
+    let windows = vec![MaintenanceWindow {
+        name: "w".to_string(),
+        start: 100,
+        end: 200,
+    }];
+    assert!(active_window(&windows, 99).is_none());
+    assert!(active_window(&windows, 100).is_some());
+    assert!(active_window(&windows, 200).is_none());
+}
+
+#[tokio::test]
+async fn test_run_is_skipped_inside_maintenance_window() {
+    let server = MockServer::start();
+    let notice = server.mock(|when, then| {
+        when.method(POST)
+            .path("/webhook")
+            .body_contains("Skipped `touch")
+            .body_contains("due to maintenance window 'freeze'");
+        then.status(200);
+    });
+    let url = server.url("/webhook");
+
+    let dir = std::env::temp_dir();
+    let calendar = dir.join(format!("shell_hook_calendar_{}.json", std::process::id()));
+    let marker = dir.join(format!("shell_hook_marker_{}", std::process::id()));
+    std::fs::write(
+        &calendar,
+        r#"[{"name": "freeze", "start": "2000-01-01T00:00:00Z", "end": "2100-01-01T00:00:00Z"}]"#,
+    )
+    .unwrap();
+    let command = format!("touch {}", marker.display());
+
+    let exit_code = run_from(vec![
+        "shell_hook",
+        "--webhook-url",
+        &url,
+        "run",
+        "--maintenance-calendar",
+        calendar.to_str().unwrap(),
+        "--",
+        &command,
+    ])
+    .await
+    .unwrap();
+
+    assert_eq!(exit_code, 0);
+    notice.assert_hits(1);
+    assert!(!marker.exists());
+    std::fs::remove_file(calendar).unwrap();
+}
+
+#[tokio::test]
+async fn test_run_outside_maintenance_window_proceeds() {
+    let dir = std::env::temp_dir();
+    let calendar = dir.join(format!(
+        "shell_hook_past_calendar_{}.json",
+        std::process::id()
+    ));
+    std::fs::write(
+        &calendar,
+        r#"[{"name": "old", "start": "2000-01-01T00:00:00Z", "end": "2000-01-02T00:00:00Z"}]"#,
+    )
+    .unwrap();
+    assert!(unix_now() > 946771200);
+
+    let exit_code = run_from(vec![
+        "shell_hook",
+        "--dry-run",
+        "run",
+        "--maintenance-calendar",
+        calendar.to_str().unwrap(),
+        "--",
+        "exit 3",
+    ])
+    .await
+    .unwrap();
+
+    assert_eq!(exit_code, 3);
+    std::fs::remove_file(calendar).unwrap();
+}
@@ -0,0 +1,29 @@
+use shell_hook::noise::{NoiseFilter, NoisePreset};
+
+#[test]
+fn test_no_presets_means_no_filter() {
+    assert!(NoiseFilter::new(&[]).is_none());
+}
+
+#[test]
+fn test_npm_preset_drops_chatter_but_keeps_errors() {
+    let filter = NoiseFilter::new(&[NoisePreset::Npm]).unwrap();
+    assert!(filter.is_noise("npm WARN deprecated inflight@1.0.6: This module is not supported"));
+    assert!(filter.is_noise("⠙ reify:typescript: timing reifyNode"));
+    assert!(filter.is_noise("12 packages are looking for funding"));
+    assert!(!filter.is_noise("npm ERR! code ERESOLVE"));
+    assert!(!filter.is_noise("added 312 packages in 4s"));
+}
+
+#[test]
+fn test_presets_combine() {
+    let filter = NoiseFilter::new(&[NoisePreset::Pip, NoisePreset::Apt]).unwrap();
+    assert!(filter.is_noise("  Downloading requests-2.32.3-py3-none-any.whl (64 kB)"));
+    assert!(filter.is_noise(
+        "     ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━ 64.9/64.9 kB 3.1 MB/s eta 0:00:00"
+    ));
+    assert!(filter.is_noise("Requirement already satisfied: idna<4,>=2.5 in ./venv/lib"));
+    assert!(filter.is_noise("Get:1 http://deb.debian.org/debian bookworm InRelease [151 kB]"));
+    assert!(!filter.is_noise("ERROR: No matching distribution found for nosuchpkg"));
+    assert!(!filter.is_noise("E: Unable to locate package nosuchpkg"));
+}
@@ -0,0 +1,39 @@
+use shell_hook::notify::{bell_count, notification_command};
+
+#[cfg(not(target_os = "macos"))]
+#[test]
+fn test_notification_command_uses_notify_send() {
+    let command = notification_command("builds", "❌ Command failed", true);
+    assert_eq!(command.get_program(), "notify-send");
+    let args: Vec<_> = command.get_args().collect();
+    assert_eq!(
+        args,
+        [
+            "--app-name=shell_hook",
+            "--urgency=critical",
+            "builds",
+            "❌ Command failed"
+        ]
+    );
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn test_notification_command_uses_osascript() {
+    let command = notification_command("builds", "say \"hi\"", false);
+    assert_eq!(command.get_program(), "osascript");
+    let args: Vec<_> = command.get_args().collect();
+    assert_eq!(
+        args,
+        [
+            "-e",
+            "display notification \"say \\\"hi\\\"\" with title \"builds\""
+        ]
+    );
+}
+
+#[test]
+fn test_bell_count_distinguishes_outcome() {
+    assert_eq!(bell_count(false), 1);
+    assert_eq!(bell_count(true), 3);
+}
@@ -0,0 +1,84 @@
+use regex::Regex;
+use serde_json::json;
+use shell_hook::cli::WebhookFormat;
+use shell_hook::language::Language;
+use shell_hook::render::{
+    highlight_matches, mark_diff, renderer_for, GoogleChatRenderer, Renderer, SlackRenderer,
+};
+
+#[test]
+fn test_render_slack() {
+    let payload = SlackRenderer.render("hello");
+    assert_eq!(payload, json!({ "text": "hello" }));
+}
+
+#[test]
+fn test_render_google_chat() {
+    let payload = GoogleChatRenderer.render("world");
+    assert_eq!(payload, json!({ "text": "world" }));
+}
+
+#[test]
+fn test_renderer_for_format() {
+    let slack = renderer_for(&WebhookFormat::Slack);
+    assert_eq!(slack.render("hi"), json!({ "text": "hi" }));
+
+    let google_chat = renderer_for(&WebhookFormat::GoogleChat);
+    assert_eq!(google_chat.render("hi"), json!({ "text": "hi" }));
+}
+
+#[test]
+fn test_highlight_matches() {
+    let pattern = Regex::new("ERROR|FAIL").unwrap();
+    assert_eq!(
+        highlight_matches(&SlackRenderer, &pattern, "test a ... FAIL (ERROR: boom)"),
+        "test a ... *FAIL* (*ERROR*: boom)"
+    );
+    assert_eq!(
+        highlight_matches(&GoogleChatRenderer, &pattern, "all good"),
+        "all good"
+    );
+}
+
+#[test]
+fn test_highlight_ignores_empty_matches() {
+    let pattern = Regex::new("x*").unwrap();
+    assert_eq!(highlight_matches(&SlackRenderer, &pattern, "axb"), "a*x*b");
+}
+
+#[test]
+fn test_link_markup() {
+    assert_eq!(SlackRenderer.link("https://x", "y"), "<https://x|y>");
+    assert_eq!(GoogleChatRenderer.link("https://x", "y"), "<https://x|y>");
+}
+
+#[test]
+fn test_tagged_code_block() {
+    assert_eq!(
+        GoogleChatRenderer.tagged_code_block(Language::Json, "{}"),
+        "```json\n{}\n```"
+    );
+    assert_eq!(
+        SlackRenderer.tagged_code_block(Language::Json, "{}"),
+        "```\n{}\n```"
+    );
+}
+
+#[test]
+fn test_mark_diff() {
+    let diff: Vec<String> = ["--- a/x", "+++ b/x", "@@ -1 +1 @@", "-old", "+new", " same"]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+    assert_eq!(
+        mark_diff(&diff),
+        vec![
+            "   --- a/x",
+            "   +++ b/x",
+            "   @@ -1 +1 @@",
+            "🟥 -old",
+            "🟩 +new",
+            "    same"
+        ]
+    );
+}
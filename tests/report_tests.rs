@@ -0,0 +1,157 @@
+use httpmock::prelude::*;
+use reqwest::Client;
+use shell_hook::app::run_from;
+use shell_hook::report::{is_secret_name, payload_sha256, redact_url, DeliveryLog};
+use shell_hook::transport::{HttpTransport, Transport};
+
+#[test]
+fn test_payload_sha256() {
+    assert_eq!(
+        payload_sha256(b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
+
+#[test]
+fn test_redact_url_drops_path_and_query() {
+    assert_eq!(
+        redact_url("https://hooks.slack.com/services/T000/B000/secret?x=1"),
+        "https://hooks.slack.com"
+    );
+    assert_eq!(
+        redact_url("http://127.0.0.1:8080/hook"),
+        "http://127.0.0.1:8080"
+    );
+}
+
+#[tokio::test]
+async fn test_http_transport_records_attempts() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST).path("/ok");
+        then.status(200);
+    });
+    server.mock(|when, then| {
+        when.method(POST).path("/fail");
+        then.status(502);
+    });
+
+    let log = DeliveryLog::default();
+    let ok = HttpTransport::new(Client::new(), server.url("/ok")).with_log(log.clone());
+    let fail = HttpTransport::new(Client::new(), server.url("/fail")).with_log(log.clone());
+    ok.deliver(b"abc").await.unwrap();
+    assert!(fail.deliver(b"abc").await.is_err());
+
+    let attempts = log.take();
+    assert_eq!(attempts.len(), 2);
+    assert_eq!(attempts[0].status, Some(200));
+    assert_eq!(attempts[0].error, None);
+    assert_eq!(attempts[0].destination, server.base_url());
+    assert_eq!(attempts[0].payload_sha256, payload_sha256(b"abc"));
+    assert_eq!(attempts[1].status, Some(502));
+    assert!(attempts[1].error.is_some());
+    assert!(log.take().is_empty());
+}
+
+#[tokio::test]
+async fn test_run_writes_report_with_deliveries() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST).path("/webhook");
+        then.status(200);
+    });
+    let url = server.url("/webhook");
+    let path = std::env::temp_dir().join(format!("shell_hook_report_{}.json", std::process::id()));
+    let path_str = path.to_str().unwrap();
+
+    let exit_code = run_from(vec![
+        "shell_hook",
+        "--webhook-url",
+        &url,
+        "run",
+        "--report",
+        path_str,
+        "--",
+        "echo hello; exit 4",
+    ])
+    .await
+    .unwrap();
+    assert_eq!(exit_code, 4);
+
+    let report: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(report["command"], "echo hello; exit 4");
+    assert_eq!(report["exit_code"], 4);
+    let deliveries = report["deliveries"].as_array().unwrap();
+    // Start message, the output batch and the final message.
+    assert_eq!(deliveries.len(), 3);
+    assert!(deliveries.iter().all(|d| d["status"] == 200));
+    assert_eq!(report["line_lag"]["lines"], 1);
+    assert!(
+        report["line_lag"]["p95_ms"].as_u64().unwrap()
+            <= report["line_lag"]["max_ms"].as_u64().unwrap()
+    );
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_is_secret_name() {
+    assert!(is_secret_name("GITHUB_TOKEN"));
+    assert!(is_secret_name("aws_secret_access_key"));
+    assert!(is_secret_name("WEBHOOK_URL"));
+    assert!(!is_secret_name("PATH"));
+}
+
+#[tokio::test]
+async fn test_run_report_captures_environment() {
+    std::env::set_var("SHELL_HOOK_CAPTURE_TEST_PLAIN", "visible");
+    std::env::set_var("SHELL_HOOK_CAPTURE_TEST_TOKEN", "hunter2");
+    let path =
+        std::env::temp_dir().join(format!("shell_hook_env_report_{}.json", std::process::id()));
+
+    run_from(vec![
+        "shell_hook",
+        "--dry-run",
+        "run",
+        "--report",
+        path.to_str().unwrap(),
+        "--capture-env",
+        "--capture-env-filter",
+        "^SHELL_HOOK_CAPTURE_TEST_",
+        "--capture-tool",
+        "rustc",
+        "--",
+        "true",
+    ])
+    .await
+    .unwrap();
+
+    let report: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    let environment = &report["environment"];
+    assert_eq!(
+        environment["variables"],
+        serde_json::json!({
+            "SHELL_HOOK_CAPTURE_TEST_PLAIN": "visible",
+            "SHELL_HOOK_CAPTURE_TEST_TOKEN": "<redacted>",
+        })
+    );
+    assert!(environment["tool_versions"]["rustc"]
+        .as_str()
+        .unwrap()
+        .starts_with("rustc "));
+    assert_eq!(
+        environment["cwd"],
+        std::env::current_dir().unwrap().to_str().unwrap()
+    );
+    assert_eq!(environment["shell_hook_version"], env!("CARGO_PKG_VERSION"));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_capture_env_requires_report() {
+    use clap::Parser;
+    let result =
+        shell_hook::cli::Cli::try_parse_from(["shell_hook", "run", "--capture-env", "--", "true"]);
+    assert!(result.is_err());
+}
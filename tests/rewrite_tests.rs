@@ -0,0 +1,47 @@
+use shell_hook::rewrite::{apply_rewrites, RewriteRule};
+
+fn rule(spec: &str) -> RewriteRule {
+    spec.parse().unwrap()
+}
+
+#[test]
+fn test_rewrite_strips_prefix() {
+    let rule = rule(r"s/^\[\d+\]\s*//");
+    assert_eq!(rule.apply("[1234]  started worker"), "started worker");
+    assert_eq!(rule.apply("no prefix"), "no prefix");
+}
+
+#[test]
+fn test_rewrite_first_match_unless_global() {
+    assert_eq!(rule("s/o/0/").apply("foo boo"), "f0o boo");
+    assert_eq!(rule("s/o/0/g").apply("foo boo"), "f00 b00");
+}
+
+#[test]
+fn test_rewrite_case_insensitive_and_backreferences() {
+    let rule = rule(r"s/container-([a-f0-9]+)/c:\1/i");
+    assert_eq!(rule.apply("CONTAINER-ab12 ready"), "c:ab12 ready");
+}
+
+#[test]
+fn test_rewrite_custom_and_escaped_delimiter() {
+    assert_eq!(rule("s|/var/log/||").apply("/var/log/app.log"), "app.log");
+    assert_eq!(rule(r"s/a\/b/x/").apply("a/b"), "x");
+}
+
+#[test]
+fn test_rewrite_invalid_rules() {
+    assert!("x/a/b/".parse::<RewriteRule>().is_err());
+    assert!("s/a/b".parse::<RewriteRule>().is_err());
+    assert!("s/a/b/q".parse::<RewriteRule>().is_err());
+    assert!("s/(/b/".parse::<RewriteRule>().is_err());
+}
+
+#[test]
+fn test_apply_rewrites_in_order() {
+    let rules = vec![rule("s/^ts=[0-9]+ //"), rule("s/level=//")];
+    assert_eq!(
+        apply_rewrites(&rules, "ts=1700000000 level=info ok".to_string()),
+        "info ok"
+    );
+}
@@ -0,0 +1,384 @@
+use shell_hook::error::AppError;
+use shell_hook::runs::{current_boot_id, RunRecord, RunState, RunStore};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+fn temp_state_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("shell_hook_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn test_run_store_round_trip() {
+    let dir = temp_state_dir("round_trip");
+    let store = RunStore::new(&dir);
+
+    let mut record = store.create("echo hello").unwrap();
+    assert_eq!(record.state, RunState::Starting);
+    assert_eq!(store.load(&record.id).unwrap(), record);
+
+    record.state = RunState::Finished;
+    record.exit_code = Some(3);
+    store.save(&record).unwrap();
+
+    let runs = store.list().unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].exit_code, Some(3));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_run_store_create_never_reuses_a_run_directory() {
+    let dir = temp_state_dir("create_unique");
+    let store = RunStore::new(&dir);
+
+    let mut ids: Vec<String> = (0..64).map(|_| store.create("true").unwrap().id).collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), 64);
+    assert_eq!(store.list().unwrap().len(), 64);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_run_store_unknown_run() {
+    let store = RunStore::new(temp_state_dir("unknown"));
+    assert!(matches!(store.load("nope"), Err(AppError::UnknownRun(_))));
+    assert!(store.list().unwrap().is_empty());
+}
+
+#[test]
+fn test_detached_run_reports_status_and_logs() {
+    let dir = temp_state_dir("detach");
+    let bin = env!("CARGO_BIN_EXE_shell_hook");
+    let state_dir = dir.to_str().unwrap();
+
+    let output = Command::new(bin)
+        .args([
+            "--dry-run",
+            "--state-dir",
+            state_dir,
+            "run",
+            "--detach",
+            "--",
+        ])
+        .arg("echo from-background; exit 2")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    assert_eq!(id.len(), 16);
+
+    let store = RunStore::new(dir.join("runs"));
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while store.load(&id).unwrap().state != RunState::Finished {
+        assert!(Instant::now() < deadline, "detached run did not finish");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(store.load(&id).unwrap().exit_code, Some(2));
+
+    let status = Command::new(bin)
+        .args(["--state-dir", state_dir, "status", &id])
+        .output()
+        .unwrap();
+    assert!(status.status.success());
+    assert!(String::from_utf8_lossy(&status.stdout).contains("finished"));
+
+    let logs = Command::new(bin)
+        .args(["--state-dir", state_dir, "logs", &id])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&logs.stdout).contains("from-background"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_status_unknown_run_fails() {
+    let dir = temp_state_dir("status_unknown");
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--state-dir", dir.to_str().unwrap(), "status", "deadbeef"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No run with ID"));
+}
+
+fn running_record(boot_id: Option<&str>, pid: Option<u32>) -> RunRecord {
+    RunRecord {
+        id: "abcd1234".to_string(),
+        command: "sleep 100".to_string(),
+        state: RunState::Running,
+        pid,
+        child_pid: None,
+        exit_code: None,
+        started_at: 0,
+        finished_at: None,
+        boot_id: boot_id.map(str::to_string),
+        cancel_reason: None,
+    }
+}
+
+#[test]
+fn test_is_interrupted_by_reboot() {
+    let record = running_record(Some("boot-a"), Some(std::process::id()));
+    assert!(record.is_interrupted(Some("boot-b")));
+    assert!(!record.is_interrupted(Some("boot-a")));
+
+    let finished = RunRecord {
+        state: RunState::Finished,
+        ..record
+    };
+    assert!(!finished.is_interrupted(Some("boot-b")));
+}
+
+#[test]
+fn test_is_interrupted_without_boot_id_uses_pid() {
+    assert!(!running_record(None, Some(std::process::id())).is_interrupted(None));
+    assert!(running_record(None, Some(i32::MAX as u32)).is_interrupted(None));
+}
+
+#[test]
+fn test_check_notifies_interrupted_runs() {
+    let dir = temp_state_dir("check");
+    let store = RunStore::new(dir.join("runs"));
+    let mut record = store.create("./backup.sh").unwrap();
+    record.state = RunState::Running;
+    record.pid = Some(i32::MAX as u32);
+    record.boot_id = current_boot_id().map(|_| "previous-boot".to_string());
+    store.save(&record).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "--state-dir", dir.to_str().unwrap(), "check"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("never completed (host rebooted)"));
+    assert_eq!(store.load(&record.id).unwrap().state, RunState::Interrupted);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_handoff_prints_command_pid() {
+    let dir = temp_state_dir("handoff");
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args([
+            "--dry-run",
+            "--state-dir",
+            dir.to_str().unwrap(),
+            "run",
+            "--handoff",
+            "--",
+        ])
+        .arg("sleep 0.5")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let pid: u32 = String::from_utf8(output.stdout)
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    assert!(std::path::Path::new(&format!("/proc/{}", pid)).exists());
+
+    let store = RunStore::new(dir.join("runs"));
+    let record = store.list().unwrap().pop().unwrap();
+    assert_eq!(record.child_pid, Some(pid));
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while store.load(&record.id).unwrap().state != RunState::Finished {
+        assert!(Instant::now() < deadline, "handed-off run did not finish");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(store.load(&record.id).unwrap().exit_code, Some(0));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_history_forward_resends_captured_output() {
+    let dir = temp_state_dir("forward");
+    let store = RunStore::new(dir.join("runs"));
+    let mut record = store.create("./deploy.sh").unwrap();
+    record.state = RunState::Finished;
+    record.exit_code = Some(2);
+    store.save(&record).unwrap();
+    std::fs::write(store.command_log_path(&record.id), "step one\nstep two\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "--state-dir", dir.to_str().unwrap()])
+        .args(["history", "forward", &record.id])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let payloads: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.contains("Would send payload"))
+        .collect();
+    assert_eq!(payloads.len(), 3, "{}", stdout);
+    assert!(payloads[0].contains(&format!("Forwarding run {}", record.id)));
+    assert!(payloads[1].contains("step one\\nstep two"));
+    assert!(payloads[2].contains("failed with exit code 2"));
+    assert_eq!(store.load(&record.id).unwrap(), record);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_kill_cancels_detached_run_with_reason() {
+    let dir = temp_state_dir("kill");
+    let bin = env!("CARGO_BIN_EXE_shell_hook");
+    let state_dir = dir.to_str().unwrap();
+
+    let output = Command::new(bin)
+        .args([
+            "--dry-run",
+            "--state-dir",
+            state_dir,
+            "run",
+            "--handoff",
+            "--",
+        ])
+        .arg("sleep 30")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let store = RunStore::new(dir.join("runs"));
+    let id = store.list().unwrap().pop().unwrap().id;
+
+    let kill = Command::new(bin)
+        .args([
+            "--state-dir",
+            state_dir,
+            "kill",
+            &id,
+            "--reason",
+            "wrong target",
+        ])
+        .output()
+        .unwrap();
+    assert!(kill.status.success(), "{:?}", kill);
+    assert!(String::from_utf8_lossy(&kill.stdout).contains(&format!("Cancelled run {}", id)));
+    assert_eq!(store.load(&id).unwrap().exit_code, Some(143));
+    let log = std::fs::read_to_string(store.log_path(&id)).unwrap();
+    assert!(
+        log.contains("Command was cancelled: wrong target"),
+        "{}",
+        log
+    );
+
+    let again = Command::new(bin)
+        .args(["--state-dir", state_dir, "kill", &id])
+        .output()
+        .unwrap();
+    assert_eq!(again.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&again.stderr).contains("is not running"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_kill_during_startup_is_not_lost() {
+    let dir = temp_state_dir("kill_startup");
+    let bin = env!("CARGO_BIN_EXE_shell_hook");
+    let state_dir = dir.to_str().unwrap();
+    let store = RunStore::new(dir.join("runs"));
+
+    // Killing straight after `--detach` lands while the background half is
+    // saving its PID and the command's.
+    for _ in 0..3 {
+        let output = Command::new(bin)
+            .args([
+                "--dry-run",
+                "--state-dir",
+                state_dir,
+                "run",
+                "--detach",
+                "--",
+            ])
+            .arg("sleep 30")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+        let kill = Command::new(bin)
+            .args([
+                "--state-dir",
+                state_dir,
+                "kill",
+                &id,
+                "--reason",
+                "too early",
+            ])
+            .output()
+            .unwrap();
+        assert!(kill.status.success(), "{:?}", kill);
+
+        let record = store.load(&id).unwrap();
+        assert_eq!(record.state, RunState::Finished);
+        assert_eq!(record.exit_code, Some(143));
+        assert_eq!(record.cancel_reason.as_deref(), Some("too early"));
+        let log = std::fs::read_to_string(store.log_path(&id)).unwrap();
+        assert!(log.contains("Command was cancelled: too early"), "{}", log);
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_history_forward_does_not_repeat_status_lines() {
+    let dir = temp_state_dir("forward_detached");
+    let bin = env!("CARGO_BIN_EXE_shell_hook");
+    let state_dir = dir.to_str().unwrap();
+
+    let output = Command::new(bin)
+        .args([
+            "--dry-run",
+            "--state-dir",
+            state_dir,
+            "run",
+            "--detach",
+            "--",
+        ])
+        .arg("echo from-background; exit 2")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    let store = RunStore::new(dir.join("runs"));
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while store.load(&id).unwrap().state != RunState::Finished {
+        assert!(Instant::now() < deadline, "detached run did not finish");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let forward = Command::new(bin)
+        .args(["--dry-run", "--state-dir", state_dir])
+        .args(["history", "forward", &id])
+        .output()
+        .unwrap();
+    assert!(forward.status.success());
+    let stdout = String::from_utf8_lossy(&forward.stdout);
+    let payloads: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.contains("Would send payload"))
+        .collect();
+    assert_eq!(payloads.len(), 3, "{}", stdout);
+    assert!(payloads[1].contains("from-background"), "{}", stdout);
+    for line in &payloads[1..] {
+        let (_, payload) = line.split_once("payload: ").unwrap();
+        assert!(!payload.contains("Starting command"), "{}", stdout);
+        assert!(!payload.contains("[shell_hook]"), "{}", stdout);
+    }
+    assert_eq!(stdout.matches("exit code 2").count(), 1, "{}", stdout);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
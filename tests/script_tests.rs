@@ -0,0 +1,59 @@
+use shell_hook::script::{script_argv, script_title};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn write_script(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("shell_hook_script_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_script_argv_follows_shebang() {
+    let path = write_script("env.sh", "#!/usr/bin/env python3\nprint('hi')\n");
+    let script = path.to_str().unwrap();
+    assert_eq!(
+        script_argv(script, &["a b".to_string()]).unwrap(),
+        vec!["/usr/bin/env", "python3", script, "a b"]
+    );
+
+    let path = write_script("bash.sh", "#! /bin/bash\necho hi\n");
+    let script = path.to_str().unwrap();
+    assert_eq!(script_argv(script, &[]).unwrap(), vec!["/bin/bash", script]);
+}
+
+#[test]
+fn test_script_argv_defaults_to_sh() {
+    let path = write_script("plain.sh", "echo hi\n");
+    let script = path.to_str().unwrap();
+    assert_eq!(
+        script_argv(script, &["x".to_string()]).unwrap(),
+        vec!["sh", script, "x"]
+    );
+    assert!(script_argv("/nonexistent/shell_hook.sh", &[]).is_err());
+}
+
+#[test]
+fn test_script_title_is_file_name() {
+    assert_eq!(script_title("./ops/task.sh"), "task.sh");
+    assert_eq!(script_title("task.sh"), "task.sh");
+}
+
+#[test]
+fn test_run_script_passes_arguments_and_titles_messages() {
+    let path = write_script("args.sh", "#!/bin/sh\necho \"got [$1] [$2]\"\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "run-script"])
+        .arg(&path)
+        .args(["one two", "$HOME"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("got [one two] [$HOME]"));
+    assert!(stdout.contains("[args.sh]"));
+}
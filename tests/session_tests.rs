@@ -0,0 +1,124 @@
+use clap::Parser;
+use shell_hook::cli::Cli;
+use shell_hook::message::OutputStream;
+use shell_hook::session::{Session, SessionEvent};
+
+fn dry_run_cli() -> Cli {
+    Cli::parse_from(["shell_hook", "--dry-run", "run", "--", "true"])
+}
+
+#[tokio::test]
+async fn test_session_reports_structured_events() {
+    let (session, mut events) = Session::new(dry_run_cli()).unwrap();
+
+    let exit_code = session.submit("echo hello; echo oops >&2").await.unwrap();
+    assert_eq!(exit_code, 0);
+    drop(session);
+
+    let mut received = Vec::new();
+    while let Some(event) = events.recv().await {
+        received.push(event);
+    }
+
+    assert_eq!(
+        received.first(),
+        Some(&SessionEvent::Started {
+            command: "echo hello; echo oops >&2".to_string()
+        })
+    );
+    assert!(received.contains(&SessionEvent::Output {
+        line: "hello".to_string(),
+        stream: OutputStream::Stdout,
+    }));
+    assert!(received.contains(&SessionEvent::Output {
+        line: "oops".to_string(),
+        stream: OutputStream::Stderr,
+    }));
+    assert!(matches!(
+        received.last(),
+        Some(SessionEvent::Finished { exit_code: 0, .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_session_runs_multiple_commands() {
+    let (session, mut events) = Session::new(dry_run_cli()).unwrap();
+
+    assert_eq!(session.submit("exit 3").await.unwrap(), 3);
+    assert_eq!(session.submit("non_existent_command").await.unwrap(), 127);
+    drop(session);
+
+    let mut exit_codes = Vec::new();
+    while let Some(event) = events.recv().await {
+        if let SessionEvent::Finished { exit_code, .. } = event {
+            exit_codes.push(exit_code);
+        }
+    }
+    assert_eq!(exit_codes, vec![3, 127]);
+}
+
+#[tokio::test]
+async fn test_session_reports_delivery_failures_as_warnings() {
+    let server = httpmock::MockServer::start();
+    server.mock(|when, then| {
+        when.method(httpmock::Method::POST);
+        then.status(400);
+    });
+    let url = server.url("/hook");
+    let cli = Cli::parse_from(["shell_hook", "--webhook-url", &url, "run", "--", "true"]);
+    let (session, mut events) = Session::new(cli).unwrap();
+
+    assert_eq!(session.submit("echo hello").await.unwrap(), 0);
+    drop(session);
+
+    let mut warnings = Vec::new();
+    while let Some(event) = events.recv().await {
+        if let SessionEvent::Warning { message } = event {
+            warnings.push(message);
+        }
+    }
+    assert!(
+        warnings
+            .iter()
+            .any(|w| w.starts_with("Failed to send start message")),
+        "{:?}",
+        warnings
+    );
+    assert!(
+        warnings
+            .iter()
+            .any(|w| w.starts_with("Failed to send final message")),
+        "{:?}",
+        warnings
+    );
+}
+
+#[tokio::test]
+async fn test_session_runs_builtins_as_plain_commands() {
+    let (session, mut events) = Session::new(dry_run_cli()).unwrap();
+
+    // `sh` has no `:note` command, so nothing is posted and the run fails.
+    assert_eq!(session.submit(":note deploy started").await.unwrap(), 127);
+    drop(session);
+
+    let mut received = Vec::new();
+    while let Some(event) = events.recv().await {
+        received.push(event);
+    }
+    assert_eq!(
+        received.first(),
+        Some(&SessionEvent::Started {
+            command: ":note deploy started".to_string()
+        })
+    );
+    assert!(matches!(
+        received.last(),
+        Some(SessionEvent::Finished { exit_code: 127, message }) if !message.contains("Note:")
+    ));
+}
+
+#[test]
+fn test_session_requires_webhook_url() {
+    let cli = Cli::parse_from(["shell_hook", "run", "--", "true"]);
+    assert!(Session::new(cli).is_err());
+}
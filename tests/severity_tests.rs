@@ -0,0 +1,121 @@
+use httpmock::prelude::*;
+use shell_hook::app::run_from;
+use shell_hook::severity::{line_severity, score, Severity, SeverityRule};
+
+fn rules(specs: &[&str]) -> Vec<SeverityRule> {
+    specs.iter().map(|s| s.parse().unwrap()).collect()
+}
+
+#[test]
+fn test_parse_severity_rules() {
+    assert!("exit=137:critical".parse::<SeverityRule>().is_ok());
+    // The severity follows the last colon, so patterns may contain colons.
+    let rule: SeverityRule = "match=error: out of memory:high".parse().unwrap();
+    assert_eq!(rule.severity, Severity::High);
+
+    assert!("exit=abc:low".parse::<SeverityRule>().is_err());
+    assert!("exit=1:urgent".parse::<SeverityRule>().is_err());
+    assert!("status=1:low".parse::<SeverityRule>().is_err());
+    assert!("match=(:low".parse::<SeverityRule>().is_err());
+}
+
+#[test]
+fn test_score_defaults() {
+    assert_eq!(score(&[], Some(0), None), Severity::Info);
+    assert_eq!(score(&[], Some(2), None), Severity::Medium);
+    assert_eq!(score(&[], None, None), Severity::High);
+}
+
+#[test]
+fn test_exit_rule_replaces_default_and_match_raises() {
+    let rules = rules(&["exit=1:low", "match=panicked:critical"]);
+    assert_eq!(score(&rules, Some(1), None), Severity::Low);
+    assert_eq!(
+        score(&rules, Some(1), Some(Severity::Critical)),
+        Severity::Critical
+    );
+    assert_eq!(score(&rules, Some(3), None), Severity::Medium);
+}
+
+#[test]
+fn test_line_severity_takes_highest_match() {
+    let rules = rules(&["match=warn:low", "match=OOM:critical", "exit=1:low"]);
+    assert_eq!(
+        line_severity(&rules, "warn: OOM killer"),
+        Some(Severity::Critical)
+    );
+    assert_eq!(line_severity(&rules, "warn: disk"), Some(Severity::Low));
+    assert_eq!(line_severity(&rules, "fine"), None);
+}
+
+#[tokio::test]
+async fn test_panic_triggers_mention() {
+    let server = MockServer::start();
+    let final_message = server.mock(|when, then| {
+        when.method(POST)
+            .body_contains("exit code 1. (severity: critical) <!here>");
+        then.status(200);
+    });
+    server.mock(|when, then| {
+        when.method(POST);
+        then.status(200);
+    });
+    let url = server.url("/hook");
+
+    let exit_code = run_from(vec![
+        "shell_hook",
+        "--webhook-url",
+        &url,
+        "--severity-rule",
+        "exit=1:low",
+        "--severity-rule",
+        "match=panicked:critical",
+        "--mention",
+        "<!here>",
+        "run",
+        "--",
+        "echo thread main panicked; exit 1",
+    ])
+    .await
+    .unwrap();
+
+    assert_eq!(exit_code, 1);
+    final_message.assert_hits(1);
+}
+
+#[tokio::test]
+async fn test_routine_failure_does_not_mention() {
+    let server = MockServer::start();
+    let mention = server.mock(|when, then| {
+        when.method(POST).body_contains("<!here>");
+        then.status(200);
+    });
+    let final_message = server.mock(|when, then| {
+        when.method(POST)
+            .body_contains("exit code 1. (severity: low)");
+        then.status(200);
+    });
+    server.mock(|when, then| {
+        when.method(POST);
+        then.status(200);
+    });
+    let url = server.url("/hook");
+
+    run_from(vec![
+        "shell_hook",
+        "--webhook-url",
+        &url,
+        "--severity-rule",
+        "exit=1:low",
+        "--mention",
+        "<!here>",
+        "run",
+        "--",
+        "grep -q needle /dev/null",
+    ])
+    .await
+    .unwrap();
+
+    mention.assert_hits(0);
+    final_message.assert_hits(1);
+}
@@ -0,0 +1,111 @@
+use reqwest::Url;
+use shell_hook::sigv4::{sign, AwsCredentials, SigV4Scope};
+
+/// 2015-08-30T12:36:00Z, the timestamp used by the AWS SigV4 examples.
+const EXAMPLE_TIME: u64 = 1_440_938_160;
+
+fn example_credentials() -> AwsCredentials {
+    AwsCredentials {
+        access_key_id: "AKIDEXAMPLE".to_string(),
+        secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+        session_token: None,
+    }
+}
+
+fn example_scope() -> SigV4Scope {
+    "us-east-1/service".parse().unwrap()
+}
+
+/// The ListUsers example from the AWS SigV4 documentation.
+#[test]
+fn test_sign_matches_aws_documentation_example() {
+    let url = Url::parse("https://iam.amazonaws.com/?Version=2010-05-08&Action=ListUsers").unwrap();
+    let headers = sign(
+        &example_credentials(),
+        &"us-east-1/iam".parse().unwrap(),
+        "GET",
+        &url,
+        &[(
+            "content-type",
+            "application/x-www-form-urlencoded; charset=utf-8",
+        )],
+        b"",
+        EXAMPLE_TIME,
+    )
+    .unwrap();
+    assert_eq!(
+        headers,
+        vec![
+            ("x-amz-date".to_string(), "20150830T123600Z".to_string()),
+            (
+                "authorization".to_string(),
+                "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+                 SignedHeaders=content-type;host;x-amz-date, \
+                 Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+                    .to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_sign_includes_session_token() {
+    let mut credentials = example_credentials();
+    credentials.session_token = Some("token".to_string());
+    let url = Url::parse("https://example.amazon.com/").unwrap();
+    let headers = sign(
+        &credentials,
+        &example_scope(),
+        "POST",
+        &url,
+        &[],
+        b"{}",
+        EXAMPLE_TIME,
+    )
+    .unwrap();
+
+    assert_eq!(
+        headers[1],
+        ("x-amz-security-token".to_string(), "token".to_string())
+    );
+    assert!(headers[2]
+        .1
+        .contains("SignedHeaders=host;x-amz-date;x-amz-security-token,"));
+}
+
+#[test]
+fn test_parse_scope() {
+    assert_eq!(
+        "eu-west-1/execute-api".parse::<SigV4Scope>(),
+        Ok(SigV4Scope {
+            region: "eu-west-1".to_string(),
+            service: "execute-api".to_string(),
+        })
+    );
+    assert!("eu-west-1".parse::<SigV4Scope>().is_err());
+    assert!("/lambda".parse::<SigV4Scope>().is_err());
+}
+
+#[test]
+fn test_credentials_from_profile() {
+    let contents = "\
+[default]
+aws_access_key_id = AKIADEFAULT
+aws_secret_access_key = default-secret
+
+[ci]
+aws_access_key_id=AKIACI
+aws_secret_access_key=ci-secret
+aws_session_token=ci-token
+";
+    let ci = AwsCredentials::from_profile(contents, "ci").unwrap();
+    assert_eq!(ci.access_key_id, "AKIACI");
+    assert_eq!(ci.secret_access_key, "ci-secret");
+    assert_eq!(ci.session_token.as_deref(), Some("ci-token"));
+
+    let default = AwsCredentials::from_profile(contents, "default").unwrap();
+    assert_eq!(default.access_key_id, "AKIADEFAULT");
+    assert_eq!(default.session_token, None);
+
+    assert!(AwsCredentials::from_profile(contents, "missing").is_none());
+}
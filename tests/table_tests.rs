@@ -0,0 +1,74 @@
+use shell_hook::cli::TableMode;
+use shell_hook::table::{align_rows, segment_lines, split_row, Segment};
+
+fn lines(input: &[&str]) -> Vec<String> {
+    input.iter().map(|l| l.to_string()).collect()
+}
+
+#[test]
+fn test_split_row_tab_and_csv() {
+    assert_eq!(
+        split_row("a\tb\tc", TableMode::Tab),
+        Some(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+    );
+    assert_eq!(
+        split_row(r#"1,"Smith, J","say ""hi""""#, TableMode::Csv),
+        Some(vec![
+            "1".to_string(),
+            "Smith, J".to_string(),
+            r#"say "hi""#.to_string()
+        ])
+    );
+    assert_eq!(split_row("no delimiter here", TableMode::Csv), None);
+}
+
+#[test]
+fn test_align_rows() {
+    let rows = vec![
+        vec!["NAME".to_string(), "STATUS".to_string()],
+        vec!["web-7f9c".to_string(), "Running".to_string()],
+    ];
+    assert_eq!(
+        align_rows(&rows),
+        vec!["NAME      STATUS", "web-7f9c  Running"]
+    );
+}
+
+#[test]
+fn test_segment_lines_groups_consecutive_rows() {
+    let input = lines(&["header text", "a,b", "1,2", "3,4", "done"]);
+    let segments = segment_lines(&input, TableMode::Csv);
+    assert_eq!(
+        segments,
+        vec![
+            Segment::Text("header text".to_string()),
+            Segment::Table(vec![
+                vec!["a".to_string(), "b".to_string()],
+                vec!["1".to_string(), "2".to_string()],
+                vec!["3".to_string(), "4".to_string()],
+            ]),
+            Segment::Text("done".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_segment_lines_single_row_stays_text() {
+    let input = lines(&["just, one comma", "plain"]);
+    let segments = segment_lines(&input, TableMode::Csv);
+    assert_eq!(
+        segments,
+        vec![
+            Segment::Text("just, one comma".to_string()),
+            Segment::Text("plain".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_segment_lines_splits_on_column_count_change() {
+    let input = lines(&["a,b", "1,2", "x,y,z", "7,8,9"]);
+    let segments = segment_lines(&input, TableMode::Csv);
+    assert_eq!(segments.len(), 2);
+    assert!(segments.iter().all(|s| matches!(s, Segment::Table(_))));
+}
@@ -0,0 +1,69 @@
+use shell_hook::telemetry::{BatchSample, BatchStats, LagSummary};
+use shell_hook::units::Units;
+use std::time::Duration;
+
+fn sample(lines: usize, latency_ms: u64, retries: usize) -> BatchSample {
+    BatchSample {
+        lines,
+        bytes: lines * 100,
+        render: Duration::from_micros(250),
+        latency: Duration::from_millis(latency_ms),
+        retries,
+    }
+}
+
+#[test]
+fn test_batch_sample_describe() {
+    assert_eq!(
+        sample(10, 120, 1).describe(3, Units::Human),
+        "[shell_hook] Batch 3: 10 lines, 1000 B, rendered in 0.2ms, sent in 120ms, retries 1"
+    );
+}
+
+#[test]
+fn test_batch_stats_summary() {
+    let stats = BatchStats::default();
+    assert_eq!(stats.summary(Units::Human, 10), None);
+
+    assert_eq!(stats.record(sample(10, 50, 0)), 1);
+    stats.record(sample(10, 300, 2));
+    stats.record(sample(4, 2500, 0));
+    assert_eq!(
+        stats.summary(Units::Human, 10).unwrap(),
+        "[shell_hook] Batch summary: batches 3, lines 24, payload 2.3 KB, retries 2\n\
+         [shell_hook]   lines per batch: min 4, avg 8.0, max 10 (buffer size 10)\n\
+         [shell_hook]   send latency: <100ms 1 | <500ms 1 | <1s 0 | >=1s 1"
+    );
+}
+
+#[test]
+fn test_line_lag_percentiles() {
+    let stats = BatchStats::default();
+    assert_eq!(stats.lag(), None);
+
+    for (count, ms) in [(90, 10), (5, 200), (5, 2000)] {
+        for _ in 0..count {
+            stats.record_lag(Duration::from_millis(ms));
+        }
+    }
+    let lag = stats.lag().unwrap();
+    assert_eq!(
+        lag,
+        LagSummary {
+            lines: 100,
+            p50_ms: 10,
+            p95_ms: 200,
+            max_ms: 2000
+        }
+    );
+    assert_eq!(
+        lag.describe(Units::Human),
+        "p50 10ms, p95 200ms, max 2s (100 lines)"
+    );
+
+    stats.record(sample(100, 50, 0));
+    assert!(stats
+        .summary(Units::Human, 100)
+        .unwrap()
+        .ends_with("\n[shell_hook]   line lag: p50 10ms, p95 200ms, max 2s (100 lines)"));
+}
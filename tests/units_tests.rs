@@ -0,0 +1,31 @@
+use shell_hook::units::Units;
+use std::time::Duration;
+
+#[test]
+fn test_human_durations() {
+    let human = |secs| Units::Human.seconds(secs);
+    assert_eq!(Units::Human.duration(Duration::from_millis(850)), "850ms");
+    assert_eq!(human(0), "0s");
+    assert_eq!(human(12), "12s");
+    assert_eq!(human(185), "3m 05s");
+    assert_eq!(human(3720), "1h 02m");
+    assert_eq!(Units::Raw.seconds(3720), "3720s");
+}
+
+#[test]
+fn test_human_bytes() {
+    assert_eq!(Units::Human.bytes(512), "512 B");
+    assert_eq!(Units::Human.bytes(1536), "1.5 KB");
+    assert_eq!(Units::Human.bytes(1 << 20), "1 MB");
+    assert_eq!(Units::Human.bytes(3_565_158), "3.4 MB");
+    assert_eq!(Units::Raw.bytes(3_565_158), "3565158B");
+}
+
+#[test]
+fn test_counts() {
+    assert_eq!(Units::Human.count(12_345, "line"), "12,345 lines");
+    assert_eq!(Units::Human.count(1_000_000, "line"), "1,000,000 lines");
+    assert_eq!(Units::Human.count(1, "line"), "1 line");
+    assert_eq!(Units::Human.count(0, "run"), "0 runs");
+    assert_eq!(Units::Raw.count(12_345, "line"), "12345 lines");
+}
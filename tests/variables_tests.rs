@@ -0,0 +1,85 @@
+#![cfg(feature = "shell")]
+
+use clap::Parser;
+use httpmock::prelude::*;
+use shell_hook::app::{process_shell_command, AppContext};
+use shell_hook::cli::Cli;
+use shell_hook::variables::{parse_assignment, SessionVariables};
+use std::sync::Arc;
+
+fn webhook_context(server: &MockServer) -> Arc<AppContext> {
+    let url = server.url("/webhook");
+    let cli = Cli::parse_from(["shell_hook", "--webhook-url", &url, "shell"]);
+    Arc::new(AppContext::new(Arc::new(cli)).unwrap())
+}
+
+#[test]
+fn test_parse_assignment() {
+    assert_eq!(parse_assignment("TARGET=prod"), Ok(("TARGET", "prod")));
+    assert_eq!(parse_assignment("_A1=x=y"), Ok(("_A1", "x=y")));
+    assert_eq!(parse_assignment("EMPTY="), Ok(("EMPTY", "")));
+    assert!(parse_assignment("TARGET").is_err());
+    assert!(parse_assignment("1A=x").is_err());
+    assert!(parse_assignment("A-B=x").is_err());
+}
+
+#[test]
+fn test_describe_redacts_secrets() {
+    let mut variables = SessionVariables::default();
+    variables.set("TARGET", "prod", false);
+    variables.set("TOKEN", "hunter2", true);
+
+    assert_eq!(variables.describe("TARGET").unwrap(), "TARGET=prod");
+    assert_eq!(variables.describe("TOKEN").unwrap(), "TOKEN=<redacted>");
+    assert_eq!(
+        variables.env(),
+        vec![
+            ("TARGET".to_string(), "prod".to_string()),
+            ("TOKEN".to_string(), "hunter2".to_string())
+        ]
+    );
+    assert!(variables.unset("TARGET"));
+    assert!(!variables.unset("TARGET"));
+}
+
+#[tokio::test]
+async fn test_set_variables_reach_commands_and_secrets_stay_out_of_messages() {
+    let server = MockServer::start();
+    let set = server.mock(|when, then| {
+        when.body_contains("Set TOKEN=<redacted>");
+        then.status(200);
+    });
+    let output = server.mock(|when, then| {
+        when.body_contains("deploying prod with 7 chars");
+        then.status(200);
+    });
+    let leaked = server.mock(|when, then| {
+        when.body_contains("hunter2");
+        then.status(200);
+    });
+    server.mock(|_, then| {
+        then.status(200);
+    });
+    let context = webhook_context(&server);
+    let mut variables = SessionVariables::default();
+
+    assert!(variables.handle_line(&context, ":set TARGET=prod").await);
+    assert!(
+        variables
+            .handle_line(&context, ":secret TOKEN=hunter2")
+            .await
+    );
+    assert!(!variables.handle_line(&context, "echo $TARGET").await);
+    let code = process_shell_command(
+        &context,
+        r#"echo "deploying $TARGET with ${#TOKEN} chars""#,
+        &variables,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(code, 0);
+    set.assert_hits(1);
+    output.assert_hits(1);
+    leaked.assert_hits(0);
+}
@@ -0,0 +1,72 @@
+use serde_json::json;
+use shell_hook::watermark::{host_id, host_name, Watermark};
+
+#[test]
+fn test_stamp_numbers_payloads_in_order() {
+    let watermark = Watermark::with_host(
+        Some("web-1".to_string()),
+        Some("abc".to_string()),
+        Some("boot".to_string()),
+        "run1".to_string(),
+    );
+    let mut first = json!({"text": "a"});
+    let mut second = json!({"text": "b"});
+    watermark.stamp(&mut first);
+    watermark.stamp(&mut second);
+
+    assert_eq!(
+        first,
+        json!({
+            "text": "a",
+            "shell_hook": {"host": "web-1", "host_id": "abc", "boot_id": "boot", "run_id": "run1", "seq": 1}
+        })
+    );
+    assert_eq!(second["shell_hook"]["seq"], 2);
+}
+
+#[test]
+fn test_stamp_leaves_out_unknown_fields() {
+    let watermark = Watermark::with_host(None, None, None, "run1".to_string());
+    let mut payload = json!({"text": "a"});
+    watermark.stamp(&mut payload);
+    assert_eq!(payload["shell_hook"], json!({"run_id": "run1", "seq": 1}));
+}
+
+#[test]
+fn test_host_id_is_keyed_hash_of_machine_id() {
+    let machine_id = "4c1f2a7e9b3d4e5f8a6b7c8d9e0f1a2b";
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, b"shell_hook watermark");
+    let tag = ring::hmac::sign(&key, machine_id.as_bytes());
+    let expected: String = tag.as_ref()[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    let id = host_id(machine_id);
+    assert_eq!(id, expected);
+    assert_eq!(id.len(), 32);
+    assert_ne!(id, machine_id);
+}
+
+#[test]
+fn test_host_name_is_not_empty() {
+    assert!(host_name().is_some_and(|name| !name.is_empty()));
+}
+
+#[test]
+fn test_dry_run_payloads_carry_watermark() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "--watermark", "run", "--", "echo hi"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let seqs: Vec<u64> = stdout
+        .lines()
+        .filter_map(|line| line.split_once("Would send payload: "))
+        .map(|(_, payload)| {
+            let payload: serde_json::Value = serde_json::from_str(payload).unwrap();
+            payload["shell_hook"]["seq"].as_u64().unwrap()
+        })
+        .collect();
+    assert_eq!(seqs, [1, 2, 3], "{}", stdout);
+}
@@ -1,11 +1,11 @@
+use clap::Parser;
 use httpmock::prelude::*;
 use httpmock::MockServer;
-use reqwest::Client;
-use serde_json::json;
 use shell_hook::app::AppContext;
-use shell_hook::cli::{Cli, Command, RunArgs, WebhookFormat};
+use shell_hook::cli::{Cli, Command, RunArgs};
 use shell_hook::message::StreamMessage;
-use shell_hook::webhook::{create_payload, run_webhook_sender, send_buffered_lines, send_payload};
+use shell_hook::session::SessionEvent;
+use shell_hook::webhook::{format_batch, run_webhook_sender, send_buffered_lines};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
@@ -17,54 +17,21 @@ fn mock_context(server: &MockServer, dry_run: bool) -> Arc<AppContext> {
         on_success: None,
         on_failure: None,
         quiet: false,
+        ..Default::default()
     };
 
-    let cli = Cli {
-        webhook_url: Some(server.url("/")),
-        dry_run,
-        command: Command::Run(run_args),
-        title: None,
-        format: WebhookFormat::GoogleChat,
-        buffer_size: 10,
-        buffer_timeout: 2.0,
-    };
-
-    Arc::new(AppContext {
-        cli: Arc::new(cli),
-        client: Client::new(),
-    })
-}
-
-#[test]
-fn test_create_payload_slack() {
-    let message = "hello";
-    let payload = create_payload(message, &WebhookFormat::Slack);
-    assert_eq!(payload, json!({ "text": "hello" }));
-}
-
-#[test]
-fn test_create_payload_google_chat() {
-    let message = "world";
-    let payload = create_payload(message, &WebhookFormat::GoogleChat);
-    assert_eq!(payload, json!({ "text": "world" }));
-}
-
-#[tokio::test]
-async fn test_send_payload_dry_run() {
-    let server = MockServer::start();
-    let mock = server.mock(|when, then| {
-        when.method(POST).path("/");
-        then.status(200);
-    });
-
-    let client = Client::new();
-    let payload = json!({"text": "test"});
-
-    // This should not send a request
-    let _ = send_payload(&client, Some(&server.url("/")), &payload, true).await;
+    let mut cli = Cli::parse_from([
+        "shell_hook",
+        "--webhook-url",
+        &server.url("/"),
+        "run",
+        "--",
+        "true",
+    ]);
+    cli.dry_run = dry_run;
+    cli.command = Command::Run(run_args);
 
-    // Assert that the mock was not called
-    mock.assert_hits(0);
+    Arc::new(AppContext::new(Arc::new(cli)).unwrap())
 }
 
 #[tokio::test]
@@ -154,19 +121,253 @@ async fn test_run_webhook_sender_flushes_on_message() {
     mock.assert_hits(1);
 }
 
+#[test]
+fn test_format_batch_highlights_matches() {
+    let cli = Cli::parse_from([
+        "shell_hook",
+        "--dry-run",
+        "--highlight",
+        "ERROR",
+        "run",
+        "--",
+        "true",
+    ]);
+    let context = AppContext::new(Arc::new(cli)).unwrap();
+
+    let lines = vec!["ok".to_string(), "ERROR: disk full".to_string()];
+    assert_eq!(format_batch(&context, &lines), "ok\n*ERROR*: disk full");
+}
+
+#[test]
+fn test_format_batch_renders_tables() {
+    let cli = Cli::parse_from([
+        "shell_hook",
+        "--dry-run",
+        "--table",
+        "tab",
+        "run",
+        "--",
+        "true",
+    ]);
+    let context = AppContext::new(Arc::new(cli)).unwrap();
+
+    let lines = vec![
+        "Filesystem:".to_string(),
+        "dev\tsize".to_string(),
+        "/dev/sda1\t20G".to_string(),
+    ];
+    assert_eq!(
+        format_batch(&context, &lines),
+        "Filesystem:\n```\ndev        size\n/dev/sda1  20G\n```"
+    );
+}
+
+#[test]
+fn test_format_batch_links_file_references() {
+    let cli = Cli::parse_from([
+        "shell_hook",
+        "--dry-run",
+        "--link-template",
+        "https://example.com/{path}#L{line}",
+        "run",
+        "--",
+        "true",
+    ]);
+    let context = AppContext::new(Arc::new(cli)).unwrap();
+
+    let lines = vec!["failed at src/main.rs:10".to_string()];
+    assert_eq!(
+        format_batch(&context, &lines),
+        "failed at <https://example.com/src/main.rs#L10|src/main.rs:10>"
+    );
+}
+
 #[tokio::test]
-async fn test_send_payload_failure() {
+async fn test_run_webhook_sender_truncates_after_byte_limit() {
     let server = MockServer::start();
-    let mock = server.mock(|when, then| {
-        when.method(POST).path("/");
-        then.status(500);
+    let first_batch = server.mock(|when, then| {
+        when.method(POST)
+            .body(r#"{"text":"0123456789\n0123456789"}"#);
+        then.status(200);
+    });
+    let notice = server.mock(|when, then| {
+        when.method(POST)
+            .body_contains("Output truncated after 25 B")
+            .body_contains("local output is complete");
+        then.status(200);
+    });
+    let other = server.mock(|when, then| {
+        when.method(POST);
+        then.status(200);
     });
 
-    let client = Client::new();
-    let payload = json!({"text": "test"});
+    let cli = Cli::parse_from([
+        "shell_hook",
+        "--webhook-url",
+        &server.url("/"),
+        "--max-total-webhook-bytes",
+        "25",
+        "run",
+        "--",
+        "true",
+    ]);
+    let context = Arc::new(AppContext::new(Arc::new(cli)).unwrap());
+    let (tx, rx) = mpsc::channel(100);
 
-    let result = send_payload(&client, Some(&server.url("/")), &payload, false).await;
+    for _ in 0..3 {
+        tx.send(StreamMessage::Line("0123456789".to_string()))
+            .await
+            .unwrap();
+    }
+    tx.send(StreamMessage::Flush).await.unwrap();
+    tx.send(StreamMessage::Line("more".to_string()))
+        .await
+        .unwrap();
+    tx.send(StreamMessage::CommandFinished).await.unwrap();
+    run_webhook_sender(context, rx).await.unwrap();
 
-    mock.assert_hits(1);
-    assert!(result.is_err());
+    first_batch.assert_hits(1);
+    notice.assert_hits(1);
+    other.assert_hits(0);
+}
+
+#[test]
+fn test_format_batch_code_fence_tags_language() {
+    let cli = Cli::parse_from([
+        "shell_hook",
+        "--dry-run",
+        "--code-fence",
+        "--highlight",
+        "web",
+        "run",
+        "--",
+        "true",
+    ]);
+    let context = AppContext::new(Arc::new(cli)).unwrap();
+
+    let yaml = vec!["kind: Pod".to_string(), "name: web".to_string()];
+    assert_eq!(
+        format_batch(&context, &yaml),
+        "```yaml\nkind: Pod\nname: web\n```"
+    );
+    let plain = vec!["building web".to_string()];
+    assert_eq!(format_batch(&context, &plain), "```\nbuilding web\n```");
+}
+
+#[test]
+fn test_format_batch_marks_diffs() {
+    let cli = Cli::parse_from([
+        "shell_hook",
+        "--dry-run",
+        "--diff-markup",
+        "run",
+        "--",
+        "true",
+    ]);
+    let context = AppContext::new(Arc::new(cli)).unwrap();
+
+    let diff = vec![
+        "@@ -1 +1 @@".to_string(),
+        "-a".to_string(),
+        "+b".to_string(),
+    ];
+    assert_eq!(
+        format_batch(&context, &diff),
+        "```\n   @@ -1 +1 @@\n🟥 -a\n🟩 +b\n```"
+    );
+    let plain = vec!["-a".to_string()];
+    assert_eq!(format_batch(&context, &plain), "-a");
+}
+
+#[tokio::test]
+async fn test_adaptive_batching_splits_batches_at_format_size_limit() {
+    for (adaptive, expected_hits) in [(false, 1), (true, 2)] {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST);
+            then.status(200);
+        });
+        let mut args = vec!["shell_hook", "--webhook-url"];
+        let url = server.url("/");
+        args.push(&url);
+        if adaptive {
+            args.push("--adaptive-batching");
+        }
+        args.extend(["run", "--", "true"]);
+        let context = Arc::new(AppContext::new(Arc::new(Cli::parse_from(args))).unwrap());
+        let (tx, rx) = mpsc::channel(100);
+
+        for _ in 0..5 {
+            tx.send(StreamMessage::Line("x".repeat(1000)))
+                .await
+                .unwrap();
+        }
+        tx.send(StreamMessage::CommandFinished).await.unwrap();
+        run_webhook_sender(context, rx).await.unwrap();
+
+        mock.assert_hits(expected_hits);
+    }
+}
+
+#[tokio::test]
+async fn test_retried_output_batch_reports_warning() {
+    let server = MockServer::start();
+    let primary = server.mock(|when, then| {
+        when.method(POST).path("/primary");
+        then.status(503);
+    });
+    let fallback = server.mock(|when, then| {
+        when.method(POST).path("/fallback");
+        then.status(200);
+    });
+    let cli = Cli::parse_from([
+        "shell_hook",
+        "--webhook-url",
+        &server.url("/primary"),
+        "--fallback-webhook-url",
+        &server.url("/fallback"),
+        "run",
+        "--",
+        "true",
+    ]);
+    let (tx, mut events) = mpsc::unbounded_channel();
+    let mut context = AppContext::new(Arc::new(cli)).unwrap();
+    context.events = Some(tx);
+    let context = Arc::new(context);
+
+    let mut buffer = vec!["line 1".to_string()];
+    send_buffered_lines(&context, &mut buffer).await.unwrap();
+
+    primary.assert_hits(3);
+    fallback.assert_hits(1);
+    assert_eq!(
+        events.try_recv().unwrap(),
+        SessionEvent::Warning {
+            message: "Message delivered after 3 failed attempts".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_forward_warnings_sends_tagged_payload() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_shell_hook"))
+        .args(["--dry-run", "--forward-warnings", "run", "--report"])
+        .arg(std::env::temp_dir().join("shell_hook_no_such_dir/report.json"))
+        .args(["--", "true"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let warnings: Vec<serde_json::Value> = stdout
+        .lines()
+        .filter_map(|line| line.split_once("Would send payload: "))
+        .map(|(_, payload)| serde_json::from_str(payload).unwrap())
+        .filter(|payload: &serde_json::Value| payload.get("source").is_some())
+        .collect();
+    assert_eq!(warnings.len(), 1, "{}", stdout);
+    assert_eq!(warnings[0]["source"], "shell_hook");
+    assert!(warnings[0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("Failed to write report"));
 }